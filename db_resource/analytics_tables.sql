@@ -0,0 +1,21 @@
+-- 充电请求各环节时间点（充电站当地时间），充电结算时写入
+CREATE TABLE request_timelines (
+    request_id BINARY(16) PRIMARY KEY,
+    user_id BINARY(16) NOT NULL,
+    mode VARCHAR(10) NOT NULL,
+    pile_id VARCHAR(255) NOT NULL,
+    submitted_at DATETIME NULL,
+    dispatched_at DATETIME NULL,
+    started_at DATETIME NULL,
+    completed_at DATETIME NULL,
+    INDEX idx_submitted_at (submitted_at)
+);
+
+-- 充电桩故障时段（充电站当地时间），end_time 为空表示尚未恢复
+CREATE TABLE pile_faults (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    pile_id VARCHAR(255) NOT NULL,
+    start_time DATETIME NOT NULL,
+    end_time DATETIME NULL,
+    INDEX idx_pile_time (pile_id, start_time)
+);
@@ -0,0 +1,25 @@
+-- 创建管理操作审计日志表（只追加）
+CREATE TABLE audit_logs (
+    seq BIGINT AUTO_INCREMENT PRIMARY KEY,
+    id BINARY(16) NOT NULL,
+    actor_id BINARY(16) NOT NULL,
+    actor_role VARCHAR(16) NOT NULL,
+    action VARCHAR(32) NOT NULL,
+    target_type VARCHAR(32) NOT NULL,
+    target_id VARCHAR(64) NOT NULL,
+    before_value TEXT NULL,
+    after_value TEXT NULL,
+    source_ip VARCHAR(64) NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE KEY id (id),
+    KEY target (target_type, target_id, created_at),
+    KEY actor_id (actor_id, created_at),
+    KEY created_at (created_at)
+);
+
+-- 禁止修改和删除审计日志
+CREATE TRIGGER audit_logs_no_update BEFORE UPDATE ON audit_logs
+FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'audit_logs is append-only';
+
+CREATE TRIGGER audit_logs_no_delete BEFORE DELETE ON audit_logs
+FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'audit_logs is append-only';
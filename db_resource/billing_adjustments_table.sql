@@ -0,0 +1,28 @@
+-- 创建账单调整表（管理员对充电详单的退款，只追加）
+CREATE TABLE billing_adjustments (
+    seq BIGINT AUTO_INCREMENT PRIMARY KEY,
+    id BINARY(16) NOT NULL,
+    record_id BINARY(16) NOT NULL,
+    user_id BINARY(16) NOT NULL,
+    amount DECIMAL(10,2) NOT NULL,
+    reason VARCHAR(255) NOT NULL,
+    admin_id BINARY(16) NOT NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE KEY id (id),
+    KEY record_id (record_id),
+    KEY user_id (user_id, created_at)
+);
+
+-- 禁止修改和删除调整记录
+CREATE TRIGGER billing_adjustments_no_update BEFORE UPDATE ON billing_adjustments
+FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'billing_adjustments is append-only';
+
+CREATE TRIGGER billing_adjustments_no_delete BEFORE DELETE ON billing_adjustments
+FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'billing_adjustments is append-only';
+
+-- 充电详单生成后不可修改和删除，更正通过账单调整完成
+CREATE TRIGGER charging_records_no_update BEFORE UPDATE ON charging_records
+FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'charging_records is immutable';
+
+CREATE TRIGGER charging_records_no_delete BEFORE DELETE ON charging_records
+FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'charging_records is immutable';
@@ -0,0 +1,20 @@
+-- 创建支付账单表（每个充电请求或自助充值一张，状态 Pending/Authorized/Paid/Failed/Refunded）
+CREATE TABLE bills (
+    id BINARY(16) PRIMARY KEY,
+    request_id BINARY(16) NOT NULL,
+    user_id BINARY(16) NOT NULL,
+    kind VARCHAR(20) NOT NULL DEFAULT 'Charging',
+    provider VARCHAR(32) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    authorized_amount DECIMAL(12,2) NOT NULL,
+    amount_due DECIMAL(12,2) NULL,
+    captured_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
+    refunded_amount DECIMAL(12,2) NOT NULL DEFAULT 0,
+    reference VARCHAR(128) NULL,
+    failure_reason VARCHAR(255) NULL,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    UNIQUE KEY request_id (request_id),
+    KEY user_id (user_id, created_at),
+    KEY reference (reference)
+);
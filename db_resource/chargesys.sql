@@ -28,9 +28,12 @@ CREATE TABLE `charging_records` (
   `pile_id` varchar(255) NOT NULL,
   `mode` enum('Fast','Slow') NOT NULL,
   `charging_amount` double NOT NULL,
-  `charging_fee` double NOT NULL,
-  `service_fee` double NOT NULL,
-  `total_fee` double NOT NULL,
+  `charging_fee` decimal(10,2) NOT NULL,
+  `service_fee` decimal(10,2) NOT NULL,
+  `service_rate` decimal(10,4) DEFAULT NULL,
+  `idle_fee` decimal(10,2) NOT NULL DEFAULT '0.00',
+  `discount_fee` decimal(10,2) NOT NULL DEFAULT '0.00',
+  `total_fee` decimal(10,2) NOT NULL,
   `start_time` datetime NOT NULL,
   `end_time` datetime NOT NULL,
   `created_at` datetime NOT NULL,
@@ -62,6 +65,7 @@ CREATE TABLE `users` (
   `username` varchar(255) NOT NULL,
   `password_hash` varchar(255) NOT NULL,
   `is_admin` tinyint(1) NOT NULL DEFAULT '0',
+  `role` varchar(16) NOT NULL DEFAULT 'driver',
   `created_at` datetime(6) NOT NULL,
   PRIMARY KEY (`id`),
   UNIQUE KEY `username` (`username`)
@@ -74,7 +78,7 @@ CREATE TABLE `users` (
 
 LOCK TABLES `users` WRITE;
 /*!40000 ALTER TABLE `users` DISABLE KEYS */;
-INSERT INTO `users` VALUES (_binary 'y\\LŔR\Ԫ\.\','Hunger','dd13c90b6042bd26042b0ac42ec21dfe0269e71c0b26eb7ac725c91d0fe1836c',0,'2025-06-08 06:18:22.146289'),(_binary ' QA.#X\\','Hunger2','dd13c90b6042bd26042b0ac42ec21dfe0269e71c0b26eb7ac725c91d0fe1836c',0,'2025-06-08 11:32:10.439804');
+INSERT INTO `users` VALUES (_binary 'y\\LŔR\Ԫ\.\','Hunger','dd13c90b6042bd26042b0ac42ec21dfe0269e71c0b26eb7ac725c91d0fe1836c',0,'driver','2025-06-08 06:18:22.146289'),(_binary ' QA.#X\\','Hunger2','dd13c90b6042bd26042b0ac42ec21dfe0269e71c0b26eb7ac725c91d0fe1836c',0,'driver','2025-06-08 11:32:10.439804');
 /*!40000 ALTER TABLE `users` ENABLE KEYS */;
 UNLOCK TABLES;
 /*!40103 SET TIME_ZONE=@OLD_TIME_ZONE */;
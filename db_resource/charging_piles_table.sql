@@ -7,8 +7,8 @@ CREATE TABLE charging_piles (
     total_charge_count INT NOT NULL,
     total_charge_time DOUBLE NOT NULL,
     total_charge_amount DOUBLE NOT NULL,
-    total_charging_fee DOUBLE NOT NULL,
-    total_service_fee DOUBLE NOT NULL,
+    total_charging_fee DECIMAL(12,2) NOT NULL,
+    total_service_fee DECIMAL(12,2) NOT NULL,
     started_at DATETIME NULL
 );
 
@@ -0,0 +1,17 @@
+-- 创建充电详单明细表（每个电价时段的电量、单价和小计，以及优惠减免）
+CREATE TABLE charging_record_items (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    record_id BINARY(16) NOT NULL,
+    seq INT NOT NULL,
+    kind VARCHAR(20) NOT NULL DEFAULT 'Period',
+    period_name VARCHAR(64) NOT NULL,
+    start_time DATETIME NOT NULL,
+    end_time DATETIME NOT NULL,
+    energy_kwh DOUBLE NOT NULL,
+    energy_rate DECIMAL(10,4) NOT NULL,
+    service_rate DECIMAL(10,4) NOT NULL,
+    charging_fee DECIMAL(10,2) NOT NULL,
+    service_fee DECIMAL(10,2) NOT NULL,
+    subtotal DECIMAL(10,2) NOT NULL,
+    KEY record_id (record_id, seq)
+);
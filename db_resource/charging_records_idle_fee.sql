@@ -0,0 +1,3 @@
+-- 充电详单增加超时占位费（已有数据库执行）
+ALTER TABLE charging_records
+    ADD COLUMN idle_fee DECIMAL(10,2) NOT NULL DEFAULT 0 AFTER service_fee;
@@ -0,0 +1,27 @@
+-- 创建账单表（每个用户每日或每月一张，汇总周期内的充电详单）
+CREATE TABLE consolidated_bills (
+    id BINARY(16) PRIMARY KEY,
+    bill_no VARCHAR(32) NOT NULL,
+    user_id BINARY(16) NOT NULL,
+    period VARCHAR(10) NOT NULL,
+    period_start DATE NOT NULL,
+    period_end DATE NOT NULL,
+    record_count BIGINT NOT NULL,
+    total_energy DOUBLE NOT NULL,
+    energy_fee DECIMAL(10,2) NOT NULL,
+    service_fee DECIMAL(10,2) NOT NULL,
+    idle_fee DECIMAL(10,2) NOT NULL,
+    discount_fee DECIMAL(10,2) NOT NULL,
+    total_fee DECIMAL(10,2) NOT NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE KEY bill_no (bill_no),
+    KEY user_id (user_id, period_start)
+);
+
+-- 账单包含的充电详单
+CREATE TABLE consolidated_bill_records (
+    bill_id BINARY(16) NOT NULL,
+    record_id BINARY(16) NOT NULL,
+    PRIMARY KEY (bill_id, record_id),
+    KEY record_id (record_id)
+);
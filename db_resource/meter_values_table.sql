@@ -0,0 +1,10 @@
+-- 创建电表采样表（每次充电的计量曲线）
+CREATE TABLE meter_values (
+    id BINARY(16) PRIMARY KEY,
+    record_id BINARY(16) NOT NULL,
+    pile_id VARCHAR(255) NOT NULL,
+    timestamp DATETIME(3) NOT NULL,
+    energy_kwh DOUBLE NOT NULL,
+    power_kw DOUBLE NOT NULL,
+    KEY record_id (record_id, timestamp)
+);
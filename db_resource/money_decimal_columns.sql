@@ -0,0 +1,10 @@
+-- 金额字段由 DOUBLE 改为 DECIMAL（已有数据库执行），原有金额四舍五入到分
+ALTER TABLE charging_records
+    MODIFY COLUMN charging_fee DECIMAL(10,2) NOT NULL,
+    MODIFY COLUMN service_fee DECIMAL(10,2) NOT NULL,
+    MODIFY COLUMN idle_fee DECIMAL(10,2) NOT NULL DEFAULT 0,
+    MODIFY COLUMN total_fee DECIMAL(10,2) NOT NULL;
+
+ALTER TABLE charging_piles
+    MODIFY COLUMN total_charging_fee DECIMAL(12,2) NOT NULL,
+    MODIFY COLUMN total_service_fee DECIMAL(12,2) NOT NULL;
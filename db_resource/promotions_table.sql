@@ -0,0 +1,35 @@
+-- 创建促销规则表（条件为空表示不限制；period_names 为逗号分隔的电价时段名称）
+CREATE TABLE promotions (
+    id BINARY(16) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    mode VARCHAR(10) NULL,
+    period_names VARCHAR(255) NOT NULL DEFAULT '',
+    user_segment VARCHAR(20) NULL,
+    valid_from DATETIME NULL,
+    valid_to DATETIME NULL,
+    component VARCHAR(10) NOT NULL,
+    discount_type VARCHAR(20) NOT NULL,
+    discount_value DECIMAL(10,4) NOT NULL,
+    max_energy_kwh DOUBLE NULL,
+    requires_coupon BOOLEAN NOT NULL DEFAULT FALSE,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at DATETIME NOT NULL
+);
+
+-- 创建优惠券表（一次性使用，核销时写入用户和详单）
+CREATE TABLE coupons (
+    code VARCHAR(32) PRIMARY KEY,
+    promotion_id BINARY(16) NOT NULL,
+    redeemed_by BINARY(16) NULL,
+    record_id BINARY(16) NULL,
+    redeemed_at DATETIME NULL,
+    created_at DATETIME NOT NULL,
+    KEY promotion_id (promotion_id)
+);
+
+-- 充电详单增加优惠减免，明细增加类型（已有数据库执行）
+ALTER TABLE charging_records
+    ADD COLUMN discount_fee DECIMAL(10,2) NOT NULL DEFAULT 0 AFTER idle_fee;
+
+ALTER TABLE charging_record_items
+    ADD COLUMN kind VARCHAR(20) NOT NULL DEFAULT 'Period' AFTER seq;
@@ -0,0 +1,7 @@
+-- 费率字段由 DOUBLE 改为 DECIMAL（已有数据库执行），原有费率四舍五入到 0.0001 元
+ALTER TABLE tariff_periods
+    MODIFY COLUMN energy_rate DECIMAL(10,4) NOT NULL,
+    MODIFY COLUMN service_rate DECIMAL(10,4) NOT NULL;
+
+ALTER TABLE service_rates
+    MODIFY COLUMN rate DECIMAL(10,4) NOT NULL;
@@ -0,0 +1,12 @@
+-- 创建服务费率表（pile_id 为空字符串表示该充电模式的全部充电桩）
+CREATE TABLE service_rates (
+    mode VARCHAR(10) NOT NULL,
+    pile_id VARCHAR(255) NOT NULL DEFAULT '',
+    rate DECIMAL(10,4) NOT NULL,
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (mode, pile_id)
+);
+
+-- 详单记录实际使用的服务费率
+ALTER TABLE charging_records
+    ADD COLUMN service_rate DECIMAL(10,4) NULL AFTER service_fee;
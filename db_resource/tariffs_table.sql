@@ -0,0 +1,21 @@
+-- 创建电价方案表
+CREATE TABLE tariffs (
+    id BINARY(16) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    effective_from DATETIME NOT NULL,
+    effective_to DATETIME NULL,
+    created_at DATETIME NOT NULL,
+    KEY effective_from (effective_from)
+);
+
+-- 创建电价时段表（结束时刻不晚于开始时刻表示跨零点）
+CREATE TABLE tariff_periods (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    tariff_id BINARY(16) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    start_time TIME NOT NULL,
+    end_time TIME NOT NULL,
+    energy_rate DECIMAL(10,4) NOT NULL,
+    service_rate DECIMAL(10,4) NOT NULL,
+    KEY tariff_id (tariff_id)
+);
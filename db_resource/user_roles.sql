@@ -0,0 +1,6 @@
+-- 用户角色（已有数据库执行）：driver 车主、operator 运维、finance 财务、superadmin 超级管理员
+ALTER TABLE users
+    ADD COLUMN role VARCHAR(16) NOT NULL DEFAULT 'driver' AFTER is_admin;
+
+-- 原有管理员升级为超级管理员
+UPDATE users SET role = 'superadmin' WHERE is_admin = 1;
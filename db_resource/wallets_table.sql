@@ -0,0 +1,28 @@
+-- 创建钱包表
+CREATE TABLE wallets (
+    user_id BINARY(16) PRIMARY KEY,
+    balance DECIMAL(12,2) NOT NULL DEFAULT 0,
+    updated_at DATETIME NOT NULL
+);
+
+-- 创建钱包流水表（只追加：充值为正，充电扣费为负）
+CREATE TABLE wallet_transactions (
+    seq BIGINT AUTO_INCREMENT PRIMARY KEY,
+    id BINARY(16) NOT NULL,
+    user_id BINARY(16) NOT NULL,
+    kind VARCHAR(20) NOT NULL,
+    amount DECIMAL(12,2) NOT NULL,
+    balance_after DECIMAL(12,2) NOT NULL,
+    record_id BINARY(16) NULL,
+    description VARCHAR(255) NOT NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE KEY id (id),
+    KEY user_id (user_id, created_at)
+);
+
+-- 禁止修改和删除流水
+CREATE TRIGGER wallet_transactions_no_update BEFORE UPDATE ON wallet_transactions
+FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'wallet_transactions is append-only';
+
+CREATE TRIGGER wallet_transactions_no_delete BEFORE DELETE ON wallet_transactions
+FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'wallet_transactions is append-only';
@@ -0,0 +1,18 @@
+mod utilization;
+mod wait_time;
+
+pub use utilization::{HourUtilization, PileUtilization, UtilizationReport};
+pub use wait_time::{WaitTimeReport, WaitTimeStats};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// 统计区间 [开始日期0点, 结束日期次日0点)，充电站当地时间
+pub(crate) fn date_range(start_date: NaiveDate, end_date: NaiveDate) -> (NaiveDateTime, NaiveDateTime) {
+    let end = end_date.succ_opt().unwrap_or(end_date);
+    (start_date.and_hms_opt(0, 0, 0).unwrap(), end.and_hms_opt(0, 0, 0).unwrap())
+}
+
+/// 保留两位小数
+pub(crate) fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Timelike, Utc};
+use serde::Serialize;
+
+use super::{date_range, round2};
+use crate::models::{to_station_local, ChargingRecord, PileFault};
+
+type Interval = (NaiveDateTime, NaiveDateTime);
+
+/// 某一小时（当地时间）内充电、故障和空闲时间的占比（%）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HourUtilization {
+    pub hour: u32,
+    pub charging_pct: f64,
+    pub faulted_pct: f64,
+    pub idle_pct: f64,
+}
+
+impl HourUtilization {
+    fn new(hour: u32, total: f64, charging: f64, faulted: f64) -> Self {
+        let pct = |seconds: f64| if total > 0.0 { round2(seconds / total * 100.0) } else { 0.0 };
+        Self {
+            hour,
+            charging_pct: pct(charging),
+            faulted_pct: pct(faulted),
+            idle_pct: pct((total - charging - faulted).max(0.0)),
+        }
+    }
+}
+
+/// 单个充电桩的利用率：整体占比和按一天中各小时的占比
+#[derive(Debug, Clone, Serialize)]
+pub struct PileUtilization {
+    pub pile_id: String,
+    pub charging_pct: f64,
+    pub faulted_pct: f64,
+    pub idle_pct: f64,
+    pub hours: Vec<HourUtilization>,
+}
+
+/// 充电桩利用率报告
+#[derive(Debug, Clone, Serialize)]
+pub struct UtilizationReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub piles: Vec<PileUtilization>,
+}
+
+/// 将 [start, end) 按当地时间的小时拆分，累加到对应小时的秒数
+fn add_by_hour(buckets: &mut [f64; 24], start: NaiveDateTime, end: NaiveDateTime) {
+    let mut cursor = start;
+    while cursor < end {
+        let hour_start = cursor.date().and_hms_opt(cursor.hour(), 0, 0).unwrap();
+        let next = (hour_start + Duration::hours(1)).min(end);
+        buckets[cursor.hour() as usize] += (next - cursor).num_seconds() as f64;
+        cursor = next;
+    }
+}
+
+/// 从充电时段中扣除故障时段（故障期间不计为充电），faults 须按开始时间排序
+fn subtract(start: NaiveDateTime, end: NaiveDateTime, faults: &[Interval]) -> Vec<Interval> {
+    let mut pieces = Vec::new();
+    let mut cursor = start;
+    for &(fault_start, fault_end) in faults {
+        if fault_end <= cursor || fault_start >= end {
+            continue;
+        }
+        if fault_start > cursor {
+            pieces.push((cursor, fault_start));
+        }
+        cursor = cursor.max(fault_end);
+    }
+    if cursor < end {
+        pieces.push((cursor, end));
+    }
+    pieces
+}
+
+impl UtilizationReport {
+    /// 按充电时段和故障时段计算利用率，统计截止到 now（当地时间），不含未来时间
+    pub fn from_intervals(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        now: NaiveDateTime,
+        pile_ids: &[String],
+        charging: &[(String, NaiveDateTime, NaiveDateTime)],
+        faults: &[PileFault],
+    ) -> Self {
+        let (range_start, range_end) = date_range(start_date, end_date);
+        let range_end = range_end.min(now);
+        let clip = |start: NaiveDateTime, end: NaiveDateTime| (start.max(range_start), end.min(range_end));
+
+        let mut totals = [0.0; 24];
+        add_by_hour(&mut totals, range_start, range_end);
+
+        let piles = pile_ids
+            .iter()
+            .map(|pile_id| {
+                let mut pile_faults: Vec<Interval> = faults
+                    .iter()
+                    .filter(|f| &f.pile_id == pile_id)
+                    .map(|f| clip(f.start_time, f.end_time.unwrap_or(range_end)))
+                    .collect();
+                pile_faults.sort();
+
+                let mut faulted = [0.0; 24];
+                for &(start, end) in &pile_faults {
+                    add_by_hour(&mut faulted, start, end);
+                }
+                let mut charged = [0.0; 24];
+                for (_, start, end) in charging.iter().filter(|(id, _, _)| id == pile_id) {
+                    let (start, end) = clip(*start, *end);
+                    for (start, end) in subtract(start, end, &pile_faults) {
+                        add_by_hour(&mut charged, start, end);
+                    }
+                }
+
+                let overall = HourUtilization::new(
+                    0,
+                    totals.iter().sum(),
+                    charged.iter().sum(),
+                    faulted.iter().sum(),
+                );
+                PileUtilization {
+                    pile_id: pile_id.clone(),
+                    charging_pct: overall.charging_pct,
+                    faulted_pct: overall.faulted_pct,
+                    idle_pct: overall.idle_pct,
+                    hours: (0..24)
+                        .map(|hour| HourUtilization::new(hour as u32, totals[hour], charged[hour], faulted[hour]))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Self { start_date, end_date, piles }
+    }
+
+    /// 统计 [start_date, end_date] 内各充电桩的利用率，指定 pile_id 时只统计该充电桩
+    pub async fn generate(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        now: DateTime<Utc>,
+        pile_ids: &[String],
+        pile_id: Option<String>,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Self, sqlx::Error> {
+        let (start, end) = date_range(start_date, end_date);
+        let charging = ChargingRecord::charging_intervals(start, end, pool).await?;
+        let faults = PileFault::find_overlapping(start, end, pool).await?;
+
+        let mut pile_ids: Vec<String> = match pile_id {
+            Some(pile_id) => vec![pile_id],
+            None => pile_ids.to_vec(),
+        };
+        pile_ids.sort();
+        Ok(Self::from_intervals(start_date, end_date, to_station_local(now), &pile_ids, &charging, &faults))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_time_is_not_counted_as_charging() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let at = |h, m| date.and_hms_opt(h, m, 0).unwrap();
+        let pile_ids = vec!["F1".to_string(), "F2".to_string()];
+        let charging = vec![("F1".to_string(), at(8, 0), at(9, 30))];
+        let faults = vec![PileFault { pile_id: "F1".to_string(), start_time: at(9, 0), end_time: Some(at(10, 0)) }];
+
+        let report = UtilizationReport::from_intervals(date, date, at(23, 59) + Duration::days(1), &pile_ids, &charging, &faults);
+        let f1 = &report.piles[0];
+        assert_eq!(f1.hours[8], HourUtilization { hour: 8, charging_pct: 100.0, faulted_pct: 0.0, idle_pct: 0.0 });
+        assert_eq!(f1.hours[9], HourUtilization { hour: 9, charging_pct: 0.0, faulted_pct: 100.0, idle_pct: 0.0 });
+        assert_eq!(f1.hours[10].idle_pct, 100.0);
+        assert_eq!((f1.charging_pct, f1.faulted_pct, f1.idle_pct), (4.17, 4.17, 91.67));
+        assert_eq!(report.piles[1].idle_pct, 100.0);
+
+        // 统计截止到当前时间，未结束的故障持续到当前时间
+        let open_fault = vec![PileFault { pile_id: "F2".to_string(), start_time: at(11, 0), end_time: None }];
+        let report = UtilizationReport::from_intervals(date, date, at(12, 0), &pile_ids, &[], &open_fault);
+        assert_eq!(report.piles[1].faulted_pct, 8.33);
+        assert_eq!(report.piles[1].hours[12].idle_pct, 0.0);
+    }
+}
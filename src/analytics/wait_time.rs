@@ -0,0 +1,121 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::{date_range, round2};
+use crate::models::{ChargingMode, RequestTimeline};
+
+/// 一组等待时长的统计（分钟）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WaitTimeStats {
+    pub count: usize,
+    pub average: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl WaitTimeStats {
+    pub fn from_samples(mut samples: Vec<f64>) -> Self {
+        samples.sort_by(|a, b| a.total_cmp(b));
+        let count = samples.len();
+        let average = if count == 0 { 0.0 } else { samples.iter().sum::<f64>() / count as f64 };
+        Self {
+            count,
+            average: round2(average),
+            p50: percentile(&samples, 50.0),
+            p90: percentile(&samples, 90.0),
+            p95: percentile(&samples, 95.0),
+            max: round2(samples.last().copied().unwrap_or(0.0)),
+        }
+    }
+}
+
+/// 最近秩法百分位数，samples 须已升序排列
+fn percentile(samples: &[f64], p: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * samples.len() as f64).ceil() as usize;
+    round2(samples[rank.clamp(1, samples.len()) - 1])
+}
+
+/// 等候区与充电桩队列等待时长报告
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitTimeReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub mode: Option<ChargingMode>,
+    pub waiting_area: WaitTimeStats, // 提交请求到调度至充电桩队列
+    pub pile_queue: WaitTimeStats,   // 进入充电桩队列到开始充电
+}
+
+impl WaitTimeReport {
+    pub fn from_timelines(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        mode: Option<ChargingMode>,
+        timelines: &[RequestTimeline],
+    ) -> Self {
+        Self {
+            start_date,
+            end_date,
+            mode,
+            waiting_area: WaitTimeStats::from_samples(
+                timelines.iter().filter_map(|t| t.waiting_area_minutes()).collect(),
+            ),
+            pile_queue: WaitTimeStats::from_samples(
+                timelines.iter().filter_map(|t| t.pile_queue_minutes()).collect(),
+            ),
+        }
+    }
+
+    /// 统计提交日期在 [start_date, end_date] 内的已完成请求
+    pub async fn generate(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        mode: Option<ChargingMode>,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Self, sqlx::Error> {
+        let (start, end) = date_range(start_date, end_date);
+        let mode_name = mode.map(|m| m.to_string());
+        let timelines = RequestTimeline::find(start, end, mode_name.as_deref(), pool).await?;
+        Ok(Self::from_timelines(start_date, end_date, mode, &timelines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_wait_time_percentiles() {
+        let submitted = Utc.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap();
+        let timelines: Vec<_> = (1..=10)
+            .map(|i| RequestTimeline {
+                submitted_at: Some(submitted),
+                dispatched_at: Some(submitted + Duration::minutes(i)),
+                started_at: Some(submitted + Duration::minutes(i + 2)),
+                completed_at: None,
+            })
+            .chain(std::iter::once(RequestTimeline {
+                submitted_at: Some(submitted), // 未调度的请求不计入
+                ..Default::default()
+            }))
+            .collect();
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let report = WaitTimeReport::from_timelines(date, date, None, &timelines);
+        assert_eq!(report.waiting_area.count, 10);
+        assert_eq!(report.waiting_area.average, 5.5);
+        assert_eq!(report.waiting_area.p50, 5.0);
+        assert_eq!(report.waiting_area.p90, 9.0);
+        assert_eq!(report.waiting_area.p95, 10.0);
+        assert_eq!(report.waiting_area.max, 10.0);
+        assert_eq!(report.pile_queue.average, 2.0);
+
+        let empty = WaitTimeStats::from_samples(Vec::new());
+        assert_eq!((empty.count, empty.p95), (0, 0.0));
+    }
+}
@@ -0,0 +1,6 @@
+mod password;
+mod token;
+
+pub use password::{dummy_password_hash, hash_password, temporary_password, validate_password, verify_password, PasswordMatch};
+pub(crate) use password::constant_time_eq;
+pub use token::{Claims, TokenKind, TokenPair, TokenSigner};
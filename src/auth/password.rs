@@ -0,0 +1,113 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+const MIN_PASSWORD_LENGTH: usize = 6;
+// 管理员重置时生成的临时密码字符集（去掉易混淆的 0/O、1/l/I）
+const TEMPORARY_PASSWORD_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnpqrstuvwxyz23456789";
+const TEMPORARY_PASSWORD_LENGTH: usize = 12;
+
+/// 密码校验结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordMatch {
+    Invalid,
+    Valid,
+    /// 与旧版无盐 SHA-256 哈希匹配，应在登录成功后升级为 Argon2id
+    Legacy,
+}
+
+/// 新密码的基本要求
+pub fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!("密码长度不能少于{}位", MIN_PASSWORD_LENGTH));
+    }
+    Ok(())
+}
+
+/// 使用 Argon2id 和随机盐生成 PHC 格式的密码哈希
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("密码哈希失败: {}", e))
+}
+
+/// 校验密码，兼容升级前保存的 SHA-256 十六进制哈希
+pub fn verify_password(password: &str, stored_hash: &str) -> PasswordMatch {
+    if stored_hash.starts_with('$') {
+        let Ok(parsed) = PasswordHash::new(stored_hash) else {
+            return PasswordMatch::Invalid;
+        };
+        return match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+            Ok(()) => PasswordMatch::Valid,
+            Err(_) => PasswordMatch::Invalid,
+        };
+    }
+
+    let legacy_hash = format!("{:x}", Sha256::digest(password.as_bytes()));
+    if constant_time_eq(legacy_hash.as_bytes(), stored_hash.as_bytes()) {
+        PasswordMatch::Legacy
+    } else {
+        PasswordMatch::Invalid
+    }
+}
+
+/// 用户名不存在时用于校验的 Argon2id 哈希，使登录耗时与用户存在时一致，无法据此探测用户名
+pub fn dummy_password_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash_password(&temporary_password()).unwrap_or_default())
+}
+
+/// 管理员重置密码时生成的随机临时密码
+pub fn temporary_password() -> String {
+    let mut bytes = [0u8; TEMPORARY_PASSWORD_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .iter()
+        .map(|b| TEMPORARY_PASSWORD_CHARS[*b as usize % TEMPORARY_PASSWORD_CHARS.len()] as char)
+        .collect()
+}
+
+/// 逐字节比较，耗时与内容无关
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let hash = hash_password("secret123").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert_ne!(hash, hash_password("secret123").unwrap()); // 每次使用不同的盐
+        assert_eq!(verify_password("secret123", &hash), PasswordMatch::Valid);
+        assert_eq!(verify_password("secret124", &hash), PasswordMatch::Invalid);
+    }
+
+    #[test]
+    fn test_legacy_sha256_hash() {
+        let legacy = format!("{:x}", Sha256::digest(b"secret123"));
+        assert_eq!(verify_password("secret123", &legacy), PasswordMatch::Legacy);
+        assert_eq!(verify_password("secret124", &legacy), PasswordMatch::Invalid);
+        assert_eq!(verify_password("secret123", "$not-a-phc-string"), PasswordMatch::Invalid);
+    }
+
+    #[test]
+    fn test_dummy_hash_rejects_passwords() {
+        assert!(dummy_password_hash().starts_with("$argon2id$"));
+        assert_eq!(verify_password("secret123", dummy_password_hash()), PasswordMatch::Invalid);
+    }
+
+    #[test]
+    fn test_temporary_password() {
+        let password = temporary_password();
+        assert_eq!(password.len(), TEMPORARY_PASSWORD_LENGTH);
+        assert!(validate_password(&password).is_ok());
+        assert!(validate_password("12345").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::models::Role;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// JWT 头部（HS256），所有令牌相同
+const HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+const DEFAULT_ACCESS_TTL_MINUTES: i64 = 30;
+const DEFAULT_REFRESH_TTL_DAYS: i64 = 7;
+
+/// 令牌类型：访问令牌用于调用接口，刷新令牌只用于换取新的令牌
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+}
+
+/// 令牌载荷
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: Uuid,       // 用户ID
+    pub kind: TokenKind,
+    pub role: Role,      // 签发时的角色，刷新令牌时重新读取
+    pub iat: i64,        // 签发时间（Unix 秒）
+    pub exp: i64,        // 过期时间（Unix 秒）
+}
+
+/// 登录或刷新时签发的一对令牌
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64, // 访问令牌有效期（秒）
+}
+
+/// HS256 签名的 JWT 令牌签发与校验
+#[derive(Clone)]
+pub struct TokenSigner {
+    secret: Vec<u8>,
+    access_ttl: Duration,
+    refresh_ttl: Duration,
+}
+
+impl std::fmt::Debug for TokenSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenSigner")
+            .field("access_ttl", &self.access_ttl)
+            .field("refresh_ttl", &self.refresh_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TokenSigner {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            access_ttl: Duration::minutes(DEFAULT_ACCESS_TTL_MINUTES),
+            refresh_ttl: Duration::days(DEFAULT_REFRESH_TTL_DAYS),
+        }
+    }
+
+    pub fn with_ttl(mut self, access_ttl: Duration, refresh_ttl: Duration) -> Self {
+        self.access_ttl = access_ttl;
+        self.refresh_ttl = refresh_ttl;
+        self
+    }
+
+    /// 从环境变量 AUTH_TOKEN_SECRET、ACCESS_TOKEN_TTL_MINUTES、REFRESH_TOKEN_TTL_DAYS 创建；
+    /// 未配置密钥时使用随机密钥，重启后已签发的令牌全部失效
+    pub fn from_env() -> Self {
+        let secret = match std::env::var("AUTH_TOKEN_SECRET") {
+            Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+            _ => {
+                println!("⚠️ 未设置 AUTH_TOKEN_SECRET，使用随机密钥，重启后需重新登录");
+                let mut secret = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut secret);
+                secret
+            }
+        };
+        let env_i64 = |name: &str, default: i64| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).filter(|v| *v > 0).unwrap_or(default)
+        };
+        Self::new(secret).with_ttl(
+            Duration::minutes(env_i64("ACCESS_TOKEN_TTL_MINUTES", DEFAULT_ACCESS_TTL_MINUTES)),
+            Duration::days(env_i64("REFRESH_TOKEN_TTL_DAYS", DEFAULT_REFRESH_TTL_DAYS)),
+        )
+    }
+
+    /// 为用户签发访问令牌和刷新令牌
+    pub fn issue(&self, user_id: Uuid, role: Role, now: DateTime<Utc>) -> TokenPair {
+        let claims = |kind, ttl: Duration| Claims {
+            sub: user_id,
+            kind,
+            role,
+            iat: now.timestamp(),
+            exp: (now + ttl).timestamp(),
+        };
+        TokenPair {
+            access_token: self.sign(&claims(TokenKind::Access, self.access_ttl)),
+            refresh_token: self.sign(&claims(TokenKind::Refresh, self.refresh_ttl)),
+            token_type: "Bearer",
+            expires_in: self.access_ttl.num_seconds(),
+        }
+    }
+
+    pub fn sign(&self, claims: &Claims) -> String {
+        let payload = serde_json::to_vec(claims).expect("令牌载荷序列化失败");
+        let signing_input = format!("{}.{}", URL_SAFE_NO_PAD.encode(HEADER), URL_SAFE_NO_PAD.encode(payload));
+        let signature = self.mac(&signing_input).finalize().into_bytes();
+        format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    /// 校验签名、类型和有效期
+    pub fn verify(&self, token: &str, kind: TokenKind, now: DateTime<Utc>) -> Result<Claims, String> {
+        let (signing_input, signature) = token.rsplit_once('.').ok_or("令牌格式错误")?;
+        let (header, payload) = signing_input.split_once('.').ok_or("令牌格式错误")?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| "令牌格式错误")?;
+        self.mac(signing_input)
+            .verify_slice(&signature)
+            .map_err(|_| "令牌签名无效")?;
+
+        let header = URL_SAFE_NO_PAD.decode(header).map_err(|_| "令牌格式错误")?;
+        if header != HEADER.as_bytes() {
+            return Err("不支持的令牌算法".to_string());
+        }
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| "令牌格式错误")?;
+        let claims: Claims = serde_json::from_slice(&payload).map_err(|_| "令牌格式错误")?;
+        if claims.kind != kind {
+            return Err("令牌类型错误".to_string());
+        }
+        if claims.exp <= now.timestamp() {
+            return Err("令牌已过期".to_string());
+        }
+        Ok(claims)
+    }
+
+    fn mac(&self, signing_input: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC 可接受任意长度的密钥");
+        mac.update(signing_input.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_verify() {
+        let signer = TokenSigner::new("test-secret");
+        let now = Utc::now();
+        let user_id = Uuid::new_v4();
+        let pair = signer.issue(user_id, Role::Driver, now);
+
+        let claims = signer.verify(&pair.access_token, TokenKind::Access, now).unwrap();
+        assert_eq!((claims.sub, claims.role), (user_id, Role::Driver));
+        assert_eq!(pair.expires_in, DEFAULT_ACCESS_TTL_MINUTES * 60);
+
+        // 刷新令牌不能当作访问令牌使用
+        assert!(signer.verify(&pair.refresh_token, TokenKind::Access, now).is_err());
+        assert!(signer.verify(&pair.refresh_token, TokenKind::Refresh, now).is_ok());
+
+        // 过期、篡改或换密钥签发的令牌无效
+        let later = now + Duration::minutes(DEFAULT_ACCESS_TTL_MINUTES);
+        assert_eq!(signer.verify(&pair.access_token, TokenKind::Access, later).unwrap_err(), "令牌已过期");
+        let (signing_input, signature) = pair.access_token.rsplit_once('.').unwrap();
+        let forged_claims = Claims { role: Role::Superadmin, ..claims };
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged_claims).unwrap());
+        let forged = format!("{}.{}.{}", signing_input.split_once('.').unwrap().0, forged_payload, signature);
+        assert_eq!(signer.verify(&forged, TokenKind::Access, now).unwrap_err(), "令牌签名无效");
+        assert!(TokenSigner::new("other").verify(&pair.access_token, TokenKind::Access, now).is_err());
+    }
+}
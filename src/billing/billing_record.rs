@@ -2,6 +2,13 @@ use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
+use crate::models::{
+    decimal_from_f64, from_local, round_money, station_timezone, to_station_local, zero_money, ChargingLineItem,
+    ChargingRecord, LineItemKind, Money, ENERGY_SCALE,
+};
+
+use super::AppliedDiscount;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BillingRecord {
     pub user_id: Uuid,
@@ -10,9 +17,28 @@ pub struct BillingRecord {
     pub charge_time: f64,       // 充电时长（小时）
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
-    pub electricity_fee: f64,   // 电费
-    pub service_fee: f64,       // 服务费
-    pub total_fee: f64,         // 总费用
+    pub electricity_fee: Money, // 电费
+    pub service_fee: Money,     // 服务费
+    pub idle_fee: Money,        // 超时占位费
+    pub discount_fee: Money,    // 优惠减免
+    pub total_fee: Money,       // 总费用（已扣除优惠）
+    pub service_rate: Option<Money>, // 按充电模式或充电桩设置的服务费率，为空表示按各时段的服务费率
+    pub segments: Vec<FeeSegment>, // 按电价时段拆分的计费明细
+    pub discounts: Vec<AppliedDiscount>, // 生效的优惠
+}
+
+/// 计费明细：充电过程中处于同一电价方案同一时段的一段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeSegment {
+    pub tariff_id: Uuid,
+    pub period_name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub energy_kwh: f64,        // 该段电量（度）
+    pub energy_rate: Money,     // 电价（元/度）
+    pub service_rate: Money,    // 服务费率（元/度）
+    pub electricity_fee: Money, // 该段电费（四舍五入到分）
+    pub service_fee: Money,     // 该段服务费（四舍五入到分）
 }
 
 impl BillingRecord {
@@ -23,11 +49,11 @@ impl BillingRecord {
         charge_time: f64,
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
-        electricity_fee: f64,
-        service_fee: f64,
+        electricity_fee: Money,
+        service_fee: Money,
     ) -> Self {
-        let total_fee = electricity_fee + service_fee;
-        
+        let total_fee = &electricity_fee + &service_fee;
+
         Self {
             user_id,
             pile_id,
@@ -37,7 +63,116 @@ impl BillingRecord {
             end_time,
             electricity_fee,
             service_fee,
+            idle_fee: zero_money(),
+            discount_fee: zero_money(),
             total_fee,
+            service_rate: None,
+            segments: Vec::new(),
+            discounts: Vec::new(),
+        }
+    }
+
+    /// 附上计费明细
+    pub fn with_segments(mut self, segments: Vec<FeeSegment>) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    /// 各时段改按统一的服务费率计价
+    pub fn with_service_rate(mut self, service_rate: Money) -> Self {
+        for segment in &mut self.segments {
+            segment.service_rate = service_rate.clone();
+            segment.price();
         }
+        self.service_fee = self.segments.iter().map(|s| &s.service_fee).fold(zero_money(), |a, b| a + b);
+        self.service_rate = Some(service_rate);
+        self.update_total();
+        self
     }
-} 
\ No newline at end of file
+
+    /// 计入超时占位费
+    pub fn with_idle_fee(mut self, idle_fee: Money) -> Self {
+        self.idle_fee = idle_fee;
+        self.update_total();
+        self
+    }
+
+    /// 计入优惠减免
+    pub fn with_discounts(mut self, discounts: Vec<AppliedDiscount>) -> Self {
+        self.discount_fee = discounts.iter().map(|d| d.total()).fold(zero_money(), |a, b| a + b);
+        self.discounts = discounts;
+        self.update_total();
+        self
+    }
+
+    fn update_total(&mut self) {
+        self.total_fee = &self.electricity_fee + &self.service_fee + &self.idle_fee - &self.discount_fee;
+    }
+
+    /// 详单明细：各电价时段在前，优惠减免在后（金额为负）
+    pub fn line_items(&self) -> Vec<ChargingLineItem> {
+        let discounts = self.discounts.iter().map(|discount| ChargingLineItem {
+            kind: LineItemKind::Discount,
+            period_name: discount.name.clone(),
+            start_time: to_station_local(self.start_time),
+            end_time: to_station_local(self.end_time),
+            energy_kwh: discount.energy_kwh,
+            energy_rate: zero_money(),
+            service_rate: zero_money(),
+            charging_fee: -discount.electricity_discount.clone(),
+            service_fee: -discount.service_discount.clone(),
+            subtotal: -discount.total(),
+        });
+        self.segments.iter().map(ChargingLineItem::from).chain(discounts).collect()
+    }
+}
+
+impl FeeSegment {
+    /// 按该段电量和费率计算电费和服务费（四舍五入到分）
+    pub fn price(&mut self) {
+        let energy = decimal_from_f64(self.energy_kwh, ENERGY_SCALE);
+        self.electricity_fee = round_money(&(&energy * &self.energy_rate));
+        self.service_fee = round_money(&(&energy * &self.service_rate));
+    }
+}
+
+/// 由已保存的充电详单还原账单（详单时间为充电站当地时间，不含分时段明细）
+impl From<&ChargingRecord> for BillingRecord {
+    fn from(record: &ChargingRecord) -> Self {
+        let timezone = station_timezone();
+        Self {
+            user_id: record.user_id,
+            pile_id: record.pile_id.clone(),
+            charge_amount: record.charging_amount,
+            charge_time: record.charging_time,
+            start_time: from_local(record.start_time, timezone),
+            end_time: from_local(record.end_time, timezone),
+            electricity_fee: record.charging_fee.clone(),
+            service_fee: record.service_fee.clone(),
+            idle_fee: record.idle_fee.clone(),
+            discount_fee: record.discount_fee.clone(),
+            total_fee: record.total_fee.clone(),
+            service_rate: record.service_rate.clone(),
+            segments: Vec::new(),
+            discounts: Vec::new(),
+        }
+    }
+}
+
+/// 计费明细保存为详单的分时段明细
+impl From<&FeeSegment> for ChargingLineItem {
+    fn from(segment: &FeeSegment) -> Self {
+        Self {
+            kind: LineItemKind::Period,
+            period_name: segment.period_name.clone(),
+            start_time: to_station_local(segment.start_time),
+            end_time: to_station_local(segment.end_time),
+            energy_kwh: segment.energy_kwh,
+            energy_rate: segment.energy_rate.clone(),
+            service_rate: segment.service_rate.clone(),
+            charging_fee: segment.electricity_fee.clone(),
+            service_fee: segment.service_fee.clone(),
+            subtotal: &segment.electricity_fee + &segment.service_fee,
+        }
+    }
+}
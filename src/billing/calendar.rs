@@ -0,0 +1,251 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::{Tariff, TariffPeriod};
+
+/// 日类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DayType {
+    Workday, // 工作日
+    Weekend, // 周末
+    Holiday, // 法定节假日
+}
+
+/// 季节：每年固定的月日区间（含首尾），开始晚于结束表示跨年，如冬季 11-01 至 02-28
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Season {
+    pub name: String,
+    pub start_month: u32,
+    pub start_day: u32,
+    pub end_month: u32,
+    pub end_day: u32,
+}
+
+impl Season {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        let day = (date.month(), date.day());
+        let start = (self.start_month, self.start_day);
+        let end = (self.end_month, self.end_day);
+        if start <= end {
+            start <= day && day <= end
+        } else {
+            day >= start || day <= end
+        }
+    }
+}
+
+/// 特殊日期：节假日，或周末调休为工作日
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub day_type: DayType,
+    #[serde(default)]
+    pub name: String, // 如"国庆节"、"国庆调休"
+}
+
+/// 时段布局：某一日类型（可限定季节）全天的电价时段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayLayout {
+    pub name: String,
+    pub day_type: DayType,
+    #[serde(default)]
+    pub season: Option<String>, // 为空表示不限季节
+    pub periods: Vec<TariffPeriod>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CalendarFile {
+    #[serde(default)]
+    seasons: Vec<Season>,
+    #[serde(default)]
+    days: Vec<CalendarDay>,
+    #[serde(default)]
+    layouts: Vec<DayLayout>,
+}
+
+/// 电价日历：按日类型和季节为每一天选择时段布局
+///
+/// 某天的日类型先查特殊日期，否则周六周日为周末、其余为工作日；布局优先匹配日类型和当天季节，
+/// 其次匹配不限季节的同日类型布局。没有匹配的布局时按电价方案计价；
+/// 有布局时布局只决定时段划分，生效电价方案中的同名时段仍按方案费率计价。
+#[derive(Debug, Clone, Default)]
+pub struct TariffCalendar {
+    seasons: Vec<Season>,
+    days: HashMap<NaiveDate, CalendarDay>,
+    layouts: Vec<(DayLayout, Tariff)>,
+}
+
+impl TariffCalendar {
+    pub fn new(seasons: Vec<Season>, days: Vec<CalendarDay>, layouts: Vec<DayLayout>) -> Result<Self, String> {
+        for season in &seasons {
+            for (month, day) in [(season.start_month, season.start_day), (season.end_month, season.end_day)] {
+                // 用闰年校验月日，允许 02-29
+                if NaiveDate::from_ymd_opt(2024, month, day).is_none() {
+                    return Err(format!("季节 {} 的日期 {:02}-{:02} 无效", season.name, month, day));
+                }
+            }
+        }
+
+        let mut layout_tariffs = Vec::new();
+        for layout in layouts {
+            if let Some(season) = &layout.season {
+                if !seasons.iter().any(|s| &s.name == season) {
+                    return Err(format!("布局 {} 引用了未定义的季节 {}", layout.name, season));
+                }
+            }
+            if layout_tariffs.iter().any(|(l, _): &(DayLayout, Tariff)| l.day_type == layout.day_type && l.season == layout.season) {
+                return Err(format!("布局 {} 与已有布局的日类型和季节重复", layout.name));
+            }
+
+            // 布局以不限生效时间的电价方案表示，计费明细中的方案ID即布局
+            let tariff = Tariff {
+                id: Uuid::new_v4(),
+                name: layout.name.clone(),
+                effective_from: NaiveDateTime::MIN,
+                effective_to: None,
+                periods: layout.periods.clone(),
+                created_at: NaiveDateTime::MIN,
+            };
+            tariff.validate().map_err(|e| format!("布局 {} 无效: {}", layout.name, e))?;
+            layout_tariffs.push((layout, tariff));
+        }
+
+        Ok(Self {
+            seasons,
+            days: days.into_iter().map(|d| (d.date, d)).collect(),
+            layouts: layout_tariffs,
+        })
+    }
+
+    /// 从 JSON 文件加载电价日历
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| format!("读取电价日历 {} 失败: {}", path.display(), e))?;
+        Self::from_json(&content)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let file: CalendarFile = serde_json::from_str(content).map_err(|e| format!("解析电价日历失败: {}", e))?;
+        Self::new(file.seasons, file.days, file.layouts)
+    }
+
+    /// 没有任何布局时日历不影响计价
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    /// 某天的日类型
+    pub fn day_type(&self, date: NaiveDate) -> DayType {
+        match self.days.get(&date) {
+            Some(day) => day.day_type,
+            None if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) => DayType::Weekend,
+            None => DayType::Workday,
+        }
+    }
+
+    /// 某天所在的季节，多个季节重叠时取先定义的
+    pub fn season(&self, date: NaiveDate) -> Option<&Season> {
+        self.seasons.iter().find(|s| s.contains(date))
+    }
+
+    /// 某天使用的时段布局
+    pub fn layout_for(&self, date: NaiveDate) -> Option<&Tariff> {
+        let day_type = self.day_type(date);
+        let season = self.season(date).map(|s| s.name.as_str());
+        self.layouts
+            .iter()
+            .find(|(l, _)| l.day_type == day_type && season.is_some() && l.season.as_deref() == season)
+            .or_else(|| self.layouts.iter().find(|(l, _)| l.day_type == day_type && l.season.is_none()))
+            .map(|(_, tariff)| tariff)
+    }
+
+    /// 某天的日历信息
+    pub fn describe(&self, date: NaiveDate) -> serde_json::Value {
+        serde_json::json!({
+            "date": date,
+            "day_type": self.day_type(date),
+            "day_name": self.days.get(&date).map(|d| d.name.as_str()),
+            "season": self.season(date).map(|s| s.name.as_str()),
+            "layout": self.layout_for(date),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn calendar() -> TariffCalendar {
+        TariffCalendar::from_json(
+            r#"{
+                "seasons": [
+                    {"name": "夏季", "start_month": 7, "start_day": 1, "end_month": 9, "end_day": 30},
+                    {"name": "冬季", "start_month": 12, "start_day": 1, "end_month": 2, "end_day": 29}
+                ],
+                "days": [
+                    {"date": "2024-10-01", "day_type": "Holiday", "name": "国庆节"},
+                    {"date": "2024-10-12", "day_type": "Workday", "name": "国庆调休"}
+                ],
+                "layouts": [
+                    {"name": "工作日", "day_type": "Workday", "periods": [
+                        {"name": "谷时", "start_time": "23:00:00", "end_time": "07:00:00", "energy_rate": 0.4, "service_rate": 0.8},
+                        {"name": "峰时", "start_time": "07:00:00", "end_time": "23:00:00", "energy_rate": 1.0, "service_rate": 0.8}
+                    ]},
+                    {"name": "夏季工作日", "day_type": "Workday", "season": "夏季", "periods": [
+                        {"name": "谷时", "start_time": "23:00:00", "end_time": "07:00:00", "energy_rate": 0.4, "service_rate": 0.8},
+                        {"name": "尖峰", "start_time": "07:00:00", "end_time": "23:00:00", "energy_rate": 1.3, "service_rate": 0.8}
+                    ]},
+                    {"name": "周末", "day_type": "Weekend", "periods": [
+                        {"name": "全天", "start_time": "00:00:00", "end_time": "00:00:00", "energy_rate": 0.6, "service_rate": 0.8}
+                    ]}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_day_types() {
+        let calendar = calendar();
+        assert_eq!(calendar.day_type(date(2024, 10, 8)), DayType::Workday);   // 周二
+        assert_eq!(calendar.day_type(date(2024, 10, 5)), DayType::Weekend);   // 周六
+        assert_eq!(calendar.day_type(date(2024, 10, 1)), DayType::Holiday);
+        assert_eq!(calendar.day_type(date(2024, 10, 12)), DayType::Workday);  // 周六调休
+    }
+
+    #[test]
+    fn test_seasons_and_layouts() {
+        let calendar = calendar();
+        assert_eq!(calendar.season(date(2024, 1, 15)).unwrap().name, "冬季");  // 跨年季节
+        assert_eq!(calendar.season(date(2024, 8, 1)).unwrap().name, "夏季");
+        assert!(calendar.season(date(2024, 4, 1)).is_none());
+
+        assert_eq!(calendar.layout_for(date(2024, 8, 1)).unwrap().name, "夏季工作日");
+        assert_eq!(calendar.layout_for(date(2024, 4, 1)).unwrap().name, "工作日");
+        // 冬季没有专门布局，使用不限季节的布局
+        assert_eq!(calendar.layout_for(date(2024, 1, 15)).unwrap().name, "工作日");
+        assert_eq!(calendar.layout_for(date(2024, 8, 3)).unwrap().name, "周末");
+        // 节假日没有布局，按电价方案计价
+        assert!(calendar.layout_for(date(2024, 10, 1)).is_none());
+    }
+
+    #[test]
+    fn test_rejects_invalid_layouts() {
+        let gap = r#"{"layouts": [{"name": "白天", "day_type": "Weekend", "periods": [
+            {"name": "白天", "start_time": "08:00:00", "end_time": "20:00:00", "energy_rate": 1.0, "service_rate": 0.8}
+        ]}]}"#;
+        assert!(TariffCalendar::from_json(gap).is_err());
+
+        let unknown_season = r#"{"layouts": [{"name": "夏季周末", "day_type": "Weekend", "season": "夏季", "periods": [
+            {"name": "全天", "start_time": "00:00:00", "end_time": "00:00:00", "energy_rate": 0.6, "service_rate": 0.8}
+        ]}]}"#;
+        assert!(TariffCalendar::from_json(unknown_season).is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime, Utc};
+use futures_util::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::models::{
+    receiver_stream, to_station_local, zero_money, ChargingRecord, ChargingRecordFilter, ChargingRecordQuery, Money,
+    RecordSortField, EXPORT_CHANNEL_SIZE,
+};
+
+/// 账单周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BillPeriod {
+    Daily,   // 日账单
+    Monthly, // 月账单
+}
+
+impl BillPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillPeriod::Daily => "Daily",
+            BillPeriod::Monthly => "Monthly",
+        }
+    }
+
+    /// 某一日期所在周期的第一天
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            BillPeriod::Daily => date,
+            BillPeriod::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// 周期结束日期（不含）
+    pub fn period_end(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            BillPeriod::Daily => start.succ_opt().unwrap_or(start),
+            BillPeriod::Monthly => start.checked_add_months(Months::new(1)).unwrap_or(start),
+        }
+    }
+
+    /// 账单号：B + 周期代码 + 周期日期 + 用户ID前8位，如 BD20240301-1A2B3C4D、BM202403-1A2B3C4D
+    pub fn bill_no(&self, start: NaiveDate, user_id: Uuid) -> String {
+        let (code, date) = match self {
+            BillPeriod::Daily => ('D', start.format("%Y%m%d")),
+            BillPeriod::Monthly => ('M', start.format("%Y%m")),
+        };
+        let user = user_id.simple().to_string()[..8].to_uppercase();
+        format!("B{}{}-{}", code, date, user)
+    }
+}
+
+impl FromStr for BillPeriod {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Daily" => Ok(BillPeriod::Daily),
+            "Monthly" => Ok(BillPeriod::Monthly),
+            _ => Err(format!("Invalid BillPeriod: {}", s)),
+        }
+    }
+}
+
+/// 账单：某用户在一个周期内全部充电详单的汇总
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsolidatedBill {
+    pub id: Uuid,
+    pub bill_no: String,              // 账单号
+    pub user_id: Uuid,
+    pub period: BillPeriod,
+    pub period_start: NaiveDate,      // 周期开始日期（含，充电站当地日期）
+    pub period_end: NaiveDate,        // 周期结束日期（不含）
+    pub record_count: i64,            // 详单数
+    pub total_energy: f64,            // 总充电量（度）
+    pub energy_fee: Money,            // 电费合计
+    pub service_fee: Money,           // 服务费合计
+    pub idle_fee: Money,              // 超时占位费合计
+    pub discount_fee: Money,          // 优惠减免合计
+    pub total_fee: Money,             // 总费用合计
+    pub record_ids: Vec<Uuid>,        // 包含的详单
+    pub created_at: NaiveDateTime,    // 生成时间（充电站当地时间）
+}
+
+impl ConsolidatedBill {
+    /// 汇总某用户在周期内的详单（详单按开始时间归属周期）
+    pub fn from_records(user_id: Uuid, period: BillPeriod, date: NaiveDate, records: &[ChargingRecord]) -> Self {
+        let period_start = period.period_start(date);
+        let sum = |fee: fn(&ChargingRecord) -> &Money| records.iter().map(fee).fold(zero_money(), |a, b| a + b);
+
+        Self {
+            id: Uuid::new_v4(),
+            bill_no: period.bill_no(period_start, user_id),
+            user_id,
+            period,
+            period_start,
+            period_end: period.period_end(period_start),
+            record_count: records.len() as i64,
+            total_energy: records.iter().map(|r| r.charging_amount).sum(),
+            energy_fee: sum(|r| &r.charging_fee),
+            service_fee: sum(|r| &r.service_fee),
+            idle_fee: sum(|r| &r.idle_fee),
+            discount_fee: sum(|r| &r.discount_fee),
+            total_fee: sum(|r| &r.total_fee),
+            record_ids: records.iter().map(|r| r.id).collect(),
+            created_at: to_station_local(Utc::now()),
+        }
+    }
+
+    /// 为周期内有充电详单的全部用户生成账单，同一用户同一周期重复生成时覆盖原账单
+    pub async fn generate(period: BillPeriod, date: NaiveDate, pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let period_start = period.period_start(date);
+        let query = ChargingRecordQuery {
+            filter: ChargingRecordFilter {
+                start_time: period_start.and_hms_opt(0, 0, 0),
+                end_time: period.period_end(period_start).and_hms_opt(0, 0, 0),
+                ..Default::default()
+            },
+            sort_by: RecordSortField::StartTime,
+            descending: false,
+            limit: u32::MAX,
+            offset: 0,
+        };
+
+        let mut by_user: BTreeMap<Uuid, Vec<ChargingRecord>> = BTreeMap::new();
+        for record in ChargingRecord::find(&query, pool).await? {
+            by_user.entry(record.user_id).or_default().push(record);
+        }
+
+        let mut bills = Vec::new();
+        for (user_id, records) in by_user {
+            let bill = Self::from_records(user_id, period, period_start, &records);
+            bills.push(bill.save(pool).await?);
+        }
+        println!("🧾 生成 {} {} 账单 {} 张", period_start, period.as_str(), bills.len());
+        Ok(bills)
+    }
+
+    /// 保存账单及其详单关联，账单号已存在时沿用原ID并替换内容
+    async fn save(mut self, pool: &sqlx::MySqlPool) -> Result<Self, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        if let Some(row) = sqlx::query("SELECT id FROM consolidated_bills WHERE bill_no = ? FOR UPDATE")
+            .bind(&self.bill_no)
+            .fetch_optional(&mut *tx)
+            .await?
+        {
+            let id_bytes: Vec<u8> = row.get("id");
+            self.id = Uuid::from_slice(&id_bytes)
+                .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO consolidated_bills (
+                id, bill_no, user_id, period, period_start, period_end, record_count, total_energy,
+                energy_fee, service_fee, idle_fee, discount_fee, total_fee, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                record_count = VALUES(record_count),
+                total_energy = VALUES(total_energy),
+                energy_fee = VALUES(energy_fee),
+                service_fee = VALUES(service_fee),
+                idle_fee = VALUES(idle_fee),
+                discount_fee = VALUES(discount_fee),
+                total_fee = VALUES(total_fee),
+                created_at = VALUES(created_at)
+            "#,
+        )
+        .bind(self.id.as_bytes().to_vec())
+        .bind(&self.bill_no)
+        .bind(self.user_id.as_bytes().to_vec())
+        .bind(self.period.as_str())
+        .bind(self.period_start)
+        .bind(self.period_end)
+        .bind(self.record_count)
+        .bind(self.total_energy)
+        .bind(&self.energy_fee)
+        .bind(&self.service_fee)
+        .bind(&self.idle_fee)
+        .bind(&self.discount_fee)
+        .bind(&self.total_fee)
+        .bind(self.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM consolidated_bill_records WHERE bill_id = ?")
+            .bind(self.id.as_bytes().to_vec())
+            .execute(&mut *tx)
+            .await?;
+        if !self.record_ids.is_empty() {
+            let mut query_builder = sqlx::QueryBuilder::new("INSERT INTO consolidated_bill_records (bill_id, record_id) ");
+            query_builder.push_values(&self.record_ids, |mut b, record_id| {
+                b.push_bind(self.id.as_bytes().to_vec()).push_bind(record_id.as_bytes().to_vec());
+            });
+            query_builder.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(self)
+    }
+
+    /// 按条件分页查询账单（不含详单），最新周期在前
+    pub async fn find(
+        user_id: Option<Uuid>,
+        period: Option<BillPeriod>,
+        limit: u32,
+        offset: u32,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let mut builder = sqlx::QueryBuilder::new(format!("SELECT {} FROM consolidated_bills WHERE 1 = 1", BILL_COLUMNS));
+        if let Some(user_id) = user_id {
+            builder.push(" AND user_id = ").push_bind(user_id.as_bytes().to_vec());
+        }
+        if let Some(period) = period {
+            builder.push(" AND period = ").push_bind(period.as_str());
+        }
+        builder
+            .push(" ORDER BY period_start DESC, bill_no LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let rows = builder.build().fetch_all(pool).await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// 逐行读取符合条件的全部账单（不含详单），最新周期在前，用于导出
+    pub fn stream(
+        user_id: Option<Uuid>,
+        period: Option<BillPeriod>,
+        pool: sqlx::MySqlPool,
+    ) -> BoxStream<'static, Result<Self, sqlx::Error>> {
+        let (tx, rx) = mpsc::channel(EXPORT_CHANNEL_SIZE);
+        tokio::spawn(async move {
+            let mut builder = sqlx::QueryBuilder::new(format!("SELECT {} FROM consolidated_bills WHERE 1 = 1", BILL_COLUMNS));
+            if let Some(user_id) = user_id {
+                builder.push(" AND user_id = ").push_bind(user_id.as_bytes().to_vec());
+            }
+            if let Some(period) = period {
+                builder.push(" AND period = ").push_bind(period.as_str());
+            }
+            builder.push(" ORDER BY period_start DESC, bill_no");
+
+            let mut rows = builder.build().fetch(&pool);
+            while let Some(row) = rows.next().await {
+                let bill = row.and_then(|row| Self::from_row(&row));
+                if tx.send(bill).await.is_err() {
+                    break;
+                }
+            }
+        });
+        receiver_stream(rx)
+    }
+
+    /// 根据账单号查询账单及其详单ID
+    pub async fn find_by_bill_no(bill_no: &str, pool: &sqlx::MySqlPool) -> Result<Option<Self>, sqlx::Error> {
+        let Some(row) = sqlx::query(&format!("SELECT {} FROM consolidated_bills WHERE bill_no = ?", BILL_COLUMNS))
+            .bind(bill_no)
+            .fetch_optional(pool)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let mut bill = Self::from_row(&row)?;
+
+        let rows = sqlx::query("SELECT record_id FROM consolidated_bill_records WHERE bill_id = ?")
+            .bind(bill.id.as_bytes().to_vec())
+            .fetch_all(pool)
+            .await?;
+        bill.record_ids = rows
+            .iter()
+            .map(|row| {
+                let bytes: Vec<u8> = row.get("record_id");
+                Uuid::from_slice(&bytes)
+                    .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Some(bill))
+    }
+
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let decode_uuid = |bytes: Vec<u8>| {
+            Uuid::from_slice(&bytes)
+                .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))
+        };
+        let period: String = row.get("period");
+
+        Ok(ConsolidatedBill {
+            id: decode_uuid(row.get("id"))?,
+            bill_no: row.get("bill_no"),
+            user_id: decode_uuid(row.get("user_id"))?,
+            period: period.parse().map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+            period_start: row.get("period_start"),
+            period_end: row.get("period_end"),
+            record_count: row.get("record_count"),
+            total_energy: row.get("total_energy"),
+            energy_fee: row.get("energy_fee"),
+            service_fee: row.get("service_fee"),
+            idle_fee: row.get("idle_fee"),
+            discount_fee: row.get("discount_fee"),
+            total_fee: row.get("total_fee"),
+            record_ids: Vec::new(),
+            created_at: row.get("created_at"),
+        })
+    }
+}
+
+const BILL_COLUMNS: &str = "id, bill_no, user_id, period, period_start, period_end, record_count, total_energy, \
+    energy_fee, service_fee, idle_fee, discount_fee, total_fee, created_at";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChargingMode, NewChargingRecord};
+    use chrono::TimeZone;
+
+    fn yuan(amount: &str) -> Money {
+        amount.parse().unwrap()
+    }
+
+    #[test]
+    fn test_period_bounds_and_bill_no() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 17).unwrap();
+        let user_id = Uuid::parse_str("1a2b3c4d-0000-0000-0000-000000000000").unwrap();
+
+        let start = BillPeriod::Monthly.period_start(date);
+        assert_eq!(start, NaiveDate::from_ymd_opt(2024, 2, 1).unwrap());
+        assert_eq!(BillPeriod::Monthly.period_end(start), NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(BillPeriod::Monthly.bill_no(start, user_id), "BM202402-1A2B3C4D");
+
+        assert_eq!(BillPeriod::Daily.period_end(date), NaiveDate::from_ymd_opt(2024, 2, 18).unwrap());
+        assert_eq!(BillPeriod::Daily.bill_no(date, user_id), "BD20240217-1A2B3C4D");
+    }
+
+    #[test]
+    fn test_from_records_aggregates_fees() {
+        let user_id = Uuid::new_v4();
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 2, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 3, 1, 3, 0, 0).unwrap();
+        let record = |pile_id: &str, mode, charging_amount, charging_fee, service_fee| {
+            ChargingRecord::new(NewChargingRecord {
+                user_id,
+                pile_id: pile_id.to_string(),
+                mode,
+                charging_amount,
+                charging_time: 1.0,
+                charging_fee: yuan(charging_fee),
+                service_fee: yuan(service_fee),
+                start_time: start,
+                end_time: end,
+            })
+        };
+        let records = vec![
+            record("F1", ChargingMode::Fast, 30.0, "30.00", "24.00").with_idle_fee(yuan("2.50")),
+            record("T1", ChargingMode::Slow, 7.0, "4.90", "5.60").with_discount(yuan("1.12")),
+        ];
+
+        let bill = ConsolidatedBill::from_records(user_id, BillPeriod::Monthly, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), &records);
+        assert_eq!(bill.record_count, 2);
+        assert!((bill.total_energy - 37.0).abs() < 1e-9);
+        assert_eq!(bill.energy_fee, yuan("34.90"));
+        assert_eq!(bill.service_fee, yuan("29.60"));
+        assert_eq!(bill.total_fee, yuan("65.88"));  // 34.90 + 29.60 + 2.50 - 1.12
+        assert_eq!(bill.record_ids, vec![records[0].id, records[1].id]);
+    }
+}
@@ -1,16 +1,24 @@
 use chrono::{DateTime, Duration, Utc};
 use uuid::Uuid;
 
+use crate::models::{decimal_from_f64, round_money, zero_money, ChargingMode, Money, RATE_SCALE};
+
 use super::{
-    TimeSlot,
+    AppliedDiscount,
+    Promotion,
+    PromotionContext,
+    ServiceRates,
+    TariffSchedule,
     BillingRecord,
-    SERVICE_RATE,
+    FeeSegment,
+    IDLE_FEE_RATE,
+    IDLE_GRACE_MINUTES,
 };
 
 pub struct FeeCalculator;
 
 impl FeeCalculator {
-    /// 计算充电费用
+    /// 按默认电价计算充电费用（假设电量在充电时段内均匀分布）
     pub fn calculate_fee(
         user_id: Uuid,
         pile_id: String,
@@ -18,39 +26,39 @@ impl FeeCalculator {
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
     ) -> BillingRecord {
-        // 计算充电时长（小时）
-        let duration = end_time - start_time;
-        let charge_time = duration.num_minutes() as f64 / 60.0;
+        Self::calculate_fee_from_curve(
+            user_id,
+            pile_id,
+            charge_amount,
+            start_time,
+            end_time,
+            &[],
+            &TariffSchedule::default(),
+        )
+    }
 
-        // 计算每个时段的电量占比
-        let mut current_time = start_time;
-        let mut electricity_fee = 0.0;
-        let mut remaining_amount = charge_amount;
-        let total_minutes = duration.num_minutes() as f64;
-        
-        while current_time < end_time && remaining_amount > 0.0 {
-            let time_slot = TimeSlot::from_time(&current_time);
-            let rate = time_slot.get_rate();
-            
-            // 计算当前时段的结束时间（每分钟计算一次）
-            let next_minute = current_time + Duration::minutes(1);
-            let period_end = if next_minute > end_time {
-                end_time
-            } else {
-                next_minute
-            };
-            
-            // 计算当前分钟的电量和费用
-            let period_ratio = 1.0 / total_minutes;  // 每分钟的比例
-            let period_amount = charge_amount * period_ratio;
-            electricity_fee += period_amount * rate;
-            
-            remaining_amount -= period_amount;
-            current_time = period_end;
-        }
+    /// 按实际电量曲线和电价表计算充电费用
+    ///
+    /// curve 为电表采样的 (时间, 本次累计电量) 点，相邻采样之间的电量在该区间内均摊；
+    /// 最后一个采样点到结束时间之间计入剩余电量。充电过程在电价时段和电价方案切换处拆分，
+    /// 每一段按当时生效的费率计价，账单附带各段明细。
+    pub fn calculate_fee_from_curve(
+        user_id: Uuid,
+        pile_id: String,
+        charge_amount: f64,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        curve: &[(DateTime<Utc>, f64)],
+        schedule: &TariffSchedule,
+    ) -> BillingRecord {
+        // 计算充电时长（小时，精确到秒）
+        let duration = end_time - start_time;
+        let charge_time = duration.num_seconds().max(0) as f64 / 3600.0;
 
-        // 计算服务费
-        let service_fee = charge_amount * SERVICE_RATE;
+        // 各时段金额已舍入到分，账单金额为明细之和
+        let segments = Self::split_segments(charge_amount, start_time, end_time, curve, schedule);
+        let electricity_fee = segments.iter().map(|s| &s.electricity_fee).fold(zero_money(), |a, b| a + b);
+        let service_fee = segments.iter().map(|s| &s.service_fee).fold(zero_money(), |a, b| a + b);
 
         // 生成账单记录
         BillingRecord::new(
@@ -63,6 +71,157 @@ impl FeeCalculator {
             electricity_fee,
             service_fee,
         )
+        .with_segments(segments)
+    }
+
+    /// 将充电过程在电价时段边界处拆分，返回各段的电量和费用
+    ///
+    /// 每个采样区间内的电量按时长（毫秒）比例分配到各段，区间最后一段取剩余电量，
+    /// 使各段电量之和等于充电量。开始时间不晚于结束时间时全部电量计入开始时刻所在时段。
+    pub fn split_segments(
+        charge_amount: f64,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        curve: &[(DateTime<Utc>, f64)],
+        schedule: &TariffSchedule,
+    ) -> Vec<FeeSegment> {
+        // 采样区间 (开始, 结束, 电量)
+        let mut intervals = Vec::new();
+        let (mut last_time, mut last_energy) = (start_time, 0.0);
+        for &(time, energy) in curve {
+            if time <= last_time || time >= end_time {
+                continue;
+            }
+            let energy = energy.min(charge_amount).max(last_energy);
+            intervals.push((last_time, time, energy - last_energy));
+            last_time = time;
+            last_energy = energy;
+        }
+        intervals.push((last_time, end_time.max(last_time), charge_amount - last_energy));
+
+        let mut segments: Vec<FeeSegment> = Vec::new();
+        for (interval_start, interval_end, energy) in intervals {
+            let total_ms = (interval_end - interval_start).num_milliseconds();
+            let mut piece_start = interval_start;
+            let mut remaining = energy;
+            loop {
+                let (tariff, period) = schedule.period_at(piece_start);
+                let piece_end = schedule.next_boundary_after(piece_start).min(interval_end);
+                let piece_energy = if piece_end >= interval_end || total_ms <= 0 {
+                    remaining
+                } else {
+                    energy * (piece_end - piece_start).num_milliseconds() as f64 / total_ms as f64
+                };
+                remaining -= piece_energy;
+
+                // 与上一段同属一个时段（如跨采样点）则合并
+                match segments.last_mut() {
+                    Some(last)
+                        if last.tariff_id == tariff.id
+                            && last.period_name == period.name
+                            && last.energy_rate == period.energy_rate
+                            && last.service_rate == period.service_rate
+                            && last.end_time == piece_start =>
+                    {
+                        last.end_time = piece_end;
+                        last.energy_kwh += piece_energy;
+                    }
+                    _ => segments.push(FeeSegment {
+                        tariff_id: tariff.id,
+                        period_name: period.name.clone(),
+                        start_time: piece_start,
+                        end_time: piece_end,
+                        energy_kwh: piece_energy,
+                        energy_rate: period.energy_rate.clone(),
+                        service_rate: period.service_rate.clone(),
+                        electricity_fee: zero_money(),
+                        service_fee: zero_money(),
+                    }),
+                }
+
+                if piece_end >= interval_end {
+                    break;
+                }
+                piece_start = piece_end;
+            }
+        }
+
+        for segment in &mut segments {
+            segment.price();
+        }
+        segments
+    }
+
+    /// 按实际使用的充电桩确定服务费率：充电桩单独设置的费率优先，其次为充电模式的费率，
+    /// 都未设置时保留各电价时段的服务费
+    pub fn apply_service_rates(record: BillingRecord, rates: &ServiceRates, mode: ChargingMode) -> BillingRecord {
+        match rates.rate_for(mode, &record.pile_id) {
+            Some(rate) => record.with_service_rate(rate),
+            None => record,
+        }
+    }
+
+    /// 按促销规则计算优惠，减免计入账单
+    ///
+    /// 规则按顺序生效，每条规则的减免不超过前面规则减免后剩余的电费和服务费。
+    pub fn apply_promotions(
+        record: BillingRecord,
+        promotions: &[Promotion],
+        context: &PromotionContext,
+    ) -> BillingRecord {
+        let mut energy_left = record.electricity_fee.clone();
+        let mut service_left = record.service_fee.clone();
+        let mut discounts = Vec::new();
+
+        for promotion in promotions.iter().filter(|p| p.applies_to(context)) {
+            let (energy_kwh, electricity, service) = promotion.evaluate(&record.segments);
+            let electricity_discount = electricity.min(energy_left.clone());
+            let service_discount = service.min(service_left.clone());
+            if electricity_discount == zero_money() && service_discount == zero_money() {
+                continue;
+            }
+            energy_left -= &electricity_discount;
+            service_left -= &service_discount;
+            discounts.push(AppliedDiscount {
+                promotion_id: promotion.id,
+                name: promotion.name.clone(),
+                energy_kwh,
+                electricity_discount,
+                service_discount,
+            });
+        }
+
+        record.with_discounts(discounts)
+    }
+
+    /// 预估充电费用：假设立即开始并以额定功率匀速充电，用于提交请求时的报价
+    ///
+    /// service_rate 为充电模式的服务费率，未设置时按电价时段的服务费。
+    pub fn estimate_fee(
+        charge_amount: f64,
+        power_kw: f64,
+        start_time: DateTime<Utc>,
+        schedule: &TariffSchedule,
+        service_rate: Option<Money>,
+    ) -> Money {
+        let hours = if power_kw > 0.0 { charge_amount / power_kw } else { 0.0 };
+        let end_time = start_time + Duration::milliseconds((hours * 3_600_000.0) as i64);
+        let record =
+            Self::calculate_fee_from_curve(Uuid::nil(), String::new(), charge_amount, start_time, end_time, &[], schedule);
+        match service_rate {
+            Some(rate) => record.with_service_rate(rate).total_fee,
+            None => record.total_fee,
+        }
+    }
+
+    /// 计算超时占位费：充电结束后超出免费时长的部分按分钟计费，不足一分钟按一分钟计
+    pub fn calculate_idle_fee(charge_end_time: DateTime<Utc>, unplug_time: DateTime<Utc>) -> Money {
+        let overstay = unplug_time - charge_end_time - Duration::minutes(IDLE_GRACE_MINUTES);
+        if overstay <= Duration::zero() {
+            return zero_money();
+        }
+        let minutes = (overstay.num_seconds() + 59) / 60;
+        round_money(&(Money::from(minutes) * decimal_from_f64(IDLE_FEE_RATE, RATE_SCALE)))
     }
 }
 
@@ -70,6 +229,18 @@ impl FeeCalculator {
 mod tests {
     use super::*;
     use chrono::TimeZone;
+    use chrono_tz::Asia::Shanghai;
+
+    // 北京时间
+    fn local(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> chrono::LocalResult<DateTime<Utc>> {
+        Shanghai
+            .with_ymd_and_hms(year, month, day, hour, min, sec)
+            .map(|t| t.with_timezone(&Utc))
+    }
+
+    fn yuan(amount: &str) -> Money {
+        amount.parse().unwrap()
+    }
 
     #[test]
     fn test_fee_calculation() {
@@ -77,8 +248,8 @@ mod tests {
         let pile_id = "A1".to_string();
         
         // 测试峰时段充电
-        let start_time = Utc.with_ymd_and_hms(2024, 3, 1, 11, 0, 0).unwrap();  // 峰时段
-        let end_time = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();    // 峰时段
+        let start_time = local(2024, 3, 1, 11, 0, 0).unwrap();  // 峰时段
+        let end_time = local(2024, 3, 1, 12, 0, 0).unwrap();    // 峰时段
         
         let record = FeeCalculator::calculate_fee(
             user_id,
@@ -89,13 +260,13 @@ mod tests {
         );
 
         // 峰时电价1.0元/度，服务费0.8元/度
-        assert_eq!(record.electricity_fee, 30.0);  // 30度 * 1.0元/度
-        assert_eq!(record.service_fee, 24.0);      // 30度 * 0.8元/度
-        assert_eq!(record.total_fee, 54.0);        // 30.0 + 24.0
+        assert_eq!(record.electricity_fee, yuan("30.00"));  // 30度 * 1.0元/度
+        assert_eq!(record.service_fee, yuan("24.00"));      // 30度 * 0.8元/度
+        assert_eq!(record.total_fee, yuan("54.00"));        // 30.0 + 24.0
         
         // 测试跨时段充电（峰时+平时）
-        let start_time = Utc.with_ymd_and_hms(2024, 3, 1, 14, 30, 0).unwrap();  // 峰时段
-        let end_time = Utc.with_ymd_and_hms(2024, 3, 1, 15, 30, 0).unwrap();    // 平时段
+        let start_time = local(2024, 3, 1, 14, 30, 0).unwrap();  // 峰时段
+        let end_time = local(2024, 3, 1, 15, 30, 0).unwrap();    // 平时段
         
         let record = FeeCalculator::calculate_fee(
             user_id,
@@ -106,8 +277,471 @@ mod tests {
         );
 
         // 半小时峰时(1.0元/度)，半小时平时(0.7元/度)
-        let expected_fee = 30.0 * 0.5 * 1.0 + 30.0 * 0.5 * 0.7;  // 25.5元
-        assert!((record.electricity_fee - expected_fee).abs() < 0.01);
-        assert_eq!(record.service_fee, 24.0);  // 30度 * 0.8元/度
+        assert_eq!(record.electricity_fee, yuan("25.50"));  // 15 * 1.0 + 15 * 0.7
+        assert_eq!(record.service_fee, yuan("24.00"));  // 30度 * 0.8元/度
+    }
+
+    #[test]
+    fn test_fee_from_curve() {
+        let user_id = Uuid::new_v4();
+        let start_time = local(2024, 3, 1, 14, 0, 0).unwrap();  // 峰时段
+        let end_time = local(2024, 3, 1, 16, 0, 0).unwrap();    // 平时段
+
+        // 峰时段内已充入25度，平时段只充入5度
+        let curve = vec![
+            (local(2024, 3, 1, 14, 30, 0).unwrap(), 15.0),
+            (local(2024, 3, 1, 15, 0, 0).unwrap(), 25.0),
+        ];
+        let record = FeeCalculator::calculate_fee_from_curve(
+            user_id,
+            "F1".to_string(),
+            30.0,
+            start_time,
+            end_time,
+            &curve,
+            &TariffSchedule::default(),
+        );
+        assert_eq!(record.electricity_fee, yuan("28.50"));  // 25 * 1.0 + 5 * 0.7
+        assert_eq!(record.service_fee, yuan("24.00"));
+
+        // 均匀分布时为 15 * 1.0 + 15 * 0.7
+        let even = FeeCalculator::calculate_fee(user_id, "F1".to_string(), 30.0, start_time, end_time);
+        assert_eq!(even.electricity_fee, yuan("25.50"));
+    }
+
+    #[test]
+    fn test_session_spanning_tariff_change() {
+        use crate::billing::{Tariff, TariffPeriod};
+
+        // 14:30 起切换为单一电价 0.5 元/度，服务费 0.6 元/度
+        let change = local(2024, 3, 1, 14, 30, 0).unwrap();
+        let flat = Tariff::new("单一电价".to_string(), change, None, vec![TariffPeriod::new("全天", 0, 0, yuan("0.5"), yuan("0.6"))]);
+        let schedule = TariffSchedule::new(vec![flat]);
+
+        let start_time = local(2024, 3, 1, 14, 0, 0).unwrap();
+        let end_time = local(2024, 3, 1, 15, 0, 0).unwrap();
+        let record = FeeCalculator::calculate_fee_from_curve(
+            Uuid::new_v4(),
+            "F1".to_string(),
+            30.0,
+            start_time,
+            end_time,
+            &[],
+            &schedule,
+        );
+
+        // 前半小时按默认峰时电价，后半小时按新方案
+        assert_eq!(record.electricity_fee, yuan("22.50"));  // 15 * 1.0 + 15 * 0.5
+        assert_eq!(record.service_fee, yuan("21.00"));      // 15 * 0.8 + 15 * 0.6
+    }
+
+    #[test]
+    fn test_session_crossing_local_midnight() {
+        // 北京时间 22:30 至次日 00:30：半小时平时，之后均为谷时
+        let start_time = local(2024, 3, 1, 22, 30, 0).unwrap();
+        let end_time = local(2024, 3, 2, 0, 30, 0).unwrap();
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F1".to_string(), 20.0, start_time, end_time);
+        assert_eq!(record.electricity_fee, yuan("9.50"));  // 5 * 0.7 + 15 * 0.4
+        assert_eq!(record.service_fee, yuan("16.00"));
+    }
+
+    #[test]
+    fn test_session_into_weekend_layout() {
+        use crate::billing::{DayLayout, DayType, TariffCalendar, TariffPeriod};
+
+        // 周末全天 0.6 元/度，工作日没有布局仍按默认电价
+        let weekend = DayLayout {
+            name: "周末".to_string(),
+            day_type: DayType::Weekend,
+            season: None,
+            periods: vec![TariffPeriod::new("全天", 0, 0, yuan("0.6"), yuan("0.8"))],
+        };
+        let calendar = TariffCalendar::new(Vec::new(), Vec::new(), vec![weekend]).unwrap();
+        let schedule = TariffSchedule::default().with_calendar(calendar);
+
+        // 2024-03-01 周五 22:00 至周六 02:00
+        let start_time = local(2024, 3, 1, 22, 0, 0).unwrap();
+        let end_time = local(2024, 3, 2, 2, 0, 0).unwrap();
+        let record = FeeCalculator::calculate_fee_from_curve(
+            Uuid::new_v4(),
+            "F1".to_string(),
+            40.0,
+            start_time,
+            end_time,
+            &[],
+            &schedule,
+        );
+
+        let names: Vec<_> = record.segments.iter().map(|s| s.period_name.as_str()).collect();
+        assert_eq!(names, ["平时", "谷时", "全天"]);
+        assert_eq!(record.segments[2].start_time, local(2024, 3, 2, 0, 0, 0).unwrap());
+        assert_eq!(record.electricity_fee, yuan("23.00"));  // 10 * 0.7 + 10 * 0.4 + 20 * 0.6
+    }
+
+    #[test]
+    fn test_calendar_day_priced_by_edited_tariff() {
+        use crate::billing::{DayLayout, DayType, Tariff, TariffCalendar, TariffPeriod};
+
+        // 周末布局：谷时 23:00-07:00，尖峰 07:00-23:00
+        let weekend = DayLayout {
+            name: "周末".to_string(),
+            day_type: DayType::Weekend,
+            season: None,
+            periods: vec![
+                TariffPeriod::new("谷时", 23, 7, yuan("0.4"), yuan("0.8")),
+                TariffPeriod::new("尖峰", 7, 23, yuan("1.3"), yuan("0.8")),
+            ],
+        };
+        let calendar = TariffCalendar::new(Vec::new(), Vec::new(), vec![weekend]).unwrap();
+        let mut tariff = Tariff::new(
+            "新方案".to_string(),
+            local(2024, 1, 1, 0, 0, 0).unwrap(),
+            None,
+            vec![
+                TariffPeriod::new("谷时", 22, 8, yuan("0.3"), yuan("0.5")),
+                TariffPeriod::new("峰时", 8, 22, yuan("0.9"), yuan("0.6")),
+            ],
+        );
+
+        // 2024-03-02 周六 05:00 至 09:00：布局划分为谷时两小时、尖峰两小时
+        let start_time = local(2024, 3, 2, 5, 0, 0).unwrap();
+        let end_time = local(2024, 3, 2, 9, 0, 0).unwrap();
+        let price = |tariff: &Tariff| {
+            let schedule = TariffSchedule::new(vec![tariff.clone()]).with_calendar(calendar.clone());
+            FeeCalculator::calculate_fee_from_curve(Uuid::new_v4(), "F1".to_string(), 40.0, start_time, end_time, &[], &schedule)
+        };
+
+        // 谷时按方案中同名时段的费率，方案没有的尖峰按布局费率
+        let record = price(&tariff);
+        let names: Vec<_> = record.segments.iter().map(|s| s.period_name.as_str()).collect();
+        assert_eq!(names, ["谷时", "尖峰"]);
+        assert_eq!(record.segments[0].tariff_id, tariff.id);
+        assert_eq!(record.electricity_fee, yuan("32.00"));  // 20 * 0.3 + 20 * 1.3
+        assert_eq!(record.service_fee, yuan("26.00"));      // 20 * 0.5 + 20 * 0.8
+
+        // 修改方案的谷时电价后，周末同样按新电价计价
+        tariff.periods[0].energy_rate = yuan("0.25");
+        assert_eq!(price(&tariff).electricity_fee, yuan("31.00"));  // 20 * 0.25 + 20 * 1.3
+    }
+
+    #[test]
+    fn test_session_across_dst_transition() {
+        use crate::billing::{Tariff, TariffPeriod};
+        use chrono_tz::America::New_York;
+
+        // 纽约充电站：当地 0-6 点为夜间电价
+        let tariff = Tariff::new(
+            "夜间电价".to_string(),
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            None,
+            vec![
+                TariffPeriod::new("夜间", 0, 6, yuan("0.4"), yuan("0.8")),
+                TariffPeriod::new("日间", 6, 0, yuan("1.0"), yuan("0.8")),
+            ],
+        );
+        let schedule = TariffSchedule::new(vec![tariff]).with_timezone(New_York);
+
+        // 2024-03-10 当地 00:00 EST 至 07:00 EDT，实际6小时，其中夜间只有5小时
+        let start_time = New_York.with_ymd_and_hms(2024, 3, 10, 0, 0, 0).unwrap().with_timezone(&Utc);
+        let end_time = New_York.with_ymd_and_hms(2024, 3, 10, 7, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(end_time - start_time, Duration::hours(6));
+
+        let record = FeeCalculator::calculate_fee_from_curve(
+            Uuid::new_v4(),
+            "F1".to_string(),
+            60.0,
+            start_time,
+            end_time,
+            &[],
+            &schedule,
+        );
+        assert_eq!(record.electricity_fee, yuan("30.00"));  // 50 * 0.4 + 10 * 1.0
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_idle_fee() {
+        let end_time = local(2024, 3, 1, 12, 0, 0).unwrap();
+
+        // 免费时长内拔枪不收费
+        assert_eq!(FeeCalculator::calculate_idle_fee(end_time, end_time), zero_money());
+        assert_eq!(FeeCalculator::calculate_idle_fee(end_time, end_time + Duration::minutes(15)), zero_money());
+
+        // 超出10分钟，0.5元/分钟
+        assert_eq!(FeeCalculator::calculate_idle_fee(end_time, end_time + Duration::minutes(25)), yuan("5.00"));
+        // 不足一分钟按一分钟计
+        assert_eq!(FeeCalculator::calculate_idle_fee(end_time, end_time + Duration::seconds(15 * 60 + 1)), yuan("0.50"));
+
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F1".to_string(), 30.0, end_time - Duration::hours(1), end_time)
+            .with_idle_fee(yuan("5.00"));
+        assert_eq!(record.idle_fee, yuan("5.00"));
+        assert_eq!(record.total_fee, yuan("59.00"));
+    }
+
+    #[test]
+    fn test_sub_minute_session() {
+        let start_time = local(2024, 3, 1, 11, 0, 0).unwrap();
+        let end_time = start_time + Duration::seconds(30);
+
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F1".to_string(), 0.2, start_time, end_time);
+        assert_eq!(record.electricity_fee, yuan("0.20"));
+    }
+
+    #[test]
+    fn test_pile_specific_service_rate() {
+        use crate::billing::{ServiceRate, ServiceRates};
+        use crate::models::ChargingMode;
+
+        let rates = ServiceRates::new(vec![
+            ServiceRate::new(ChargingMode::Fast, None, yuan("1.0")),
+            ServiceRate::new(ChargingMode::Fast, Some("F3".to_string()), yuan("1.5")),
+        ]);
+        let start_time = local(2024, 3, 1, 14, 0, 0).unwrap();
+        let end_time = local(2024, 3, 1, 15, 0, 0).unwrap();
+
+        // 快充桩 F1 按模式费率，峰时电费不变
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F1".to_string(), 30.0, start_time, end_time);
+        let record = FeeCalculator::apply_service_rates(record, &rates, ChargingMode::Fast);
+        assert_eq!(record.service_rate, Some(yuan("1.0")));
+        assert_eq!(record.electricity_fee, yuan("30.00"));
+        assert_eq!(record.service_fee, yuan("30.00"));
+        assert_eq!(record.total_fee, yuan("60.00"));
+
+        // F3 单独设置的费率优先
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F3".to_string(), 30.0, start_time, end_time);
+        let record = FeeCalculator::apply_service_rates(record, &rates, ChargingMode::Fast);
+        assert_eq!(record.service_fee, yuan("45.00"));
+        assert_eq!(record.segments[0].service_rate, yuan("1.5"));
+
+        // 慢充未设置费率，保留时段服务费
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "T1".to_string(), 7.0, start_time, end_time);
+        let record = FeeCalculator::apply_service_rates(record, &rates, ChargingMode::Slow);
+        assert_eq!(record.service_rate, None);
+        assert_eq!(record.service_fee, yuan("5.60"));
+    }
+
+    #[test]
+    fn test_estimate_fee() {
+        // 北京时间 14:00 快充 30 度：前 1 小时为峰时
+        let start_time = local(2024, 3, 1, 14, 0, 0).unwrap();
+        let quote = FeeCalculator::estimate_fee(30.0, 30.0, start_time, &TariffSchedule::default(), None);
+        assert_eq!(quote, yuan("54.00"));
+
+        // 慢充 14 度需要 2 小时，跨入平时
+        let quote = FeeCalculator::estimate_fee(14.0, 7.0, start_time, &TariffSchedule::default(), None);
+        assert_eq!(quote, yuan("23.10"));  // 7 * 1.0 + 7 * 0.7 + 14 * 0.8
+    }
+
+    #[test]
+    fn test_segment_breakdown() {
+        // 北京时间 14:30 至 18:30：峰时半小时、平时三小时、峰时半小时
+        let start_time = local(2024, 3, 1, 14, 30, 0).unwrap();
+        let end_time = local(2024, 3, 1, 18, 30, 0).unwrap();
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F1".to_string(), 40.0, start_time, end_time);
+
+        let names: Vec<_> = record.segments.iter().map(|s| s.period_name.as_str()).collect();
+        assert_eq!(names, ["峰时", "平时", "峰时"]);
+        assert_eq!(record.segments[0].end_time, local(2024, 3, 1, 15, 0, 0).unwrap());
+        assert_eq!(record.segments[2].start_time, local(2024, 3, 1, 18, 0, 0).unwrap());
+        assert!((record.segments[1].energy_kwh - 30.0).abs() < 1e-9);
+        assert_eq!(record.segments[1].electricity_fee, yuan("21.00"));
+        assert_eq!(record.charge_time, 4.0);
+    }
+
+    #[test]
+    fn test_multi_day_session() {
+        // 连续充电3天，峰、平、谷时各8小时
+        let start_time = local(2024, 3, 1, 0, 0, 0).unwrap();
+        let end_time = start_time + Duration::days(3);
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "S1".to_string(), 72.0, start_time, end_time);
+
+        // 每天6个时段，跨午夜的谷时合为一段，最后一天的谷时单独一段
+        assert_eq!(record.segments.len(), 3 * 6 + 1);
+        assert_eq!(record.electricity_fee, yuan("50.40"));  // 3 * 8 * (1.0 + 0.7 + 0.4)
+        assert_eq!(record.service_fee, yuan("57.60"));      // 72 * 0.8
+    }
+
+    fn context(user_segment: crate::billing::UserSegment, coupon_promotion_id: Option<Uuid>) -> PromotionContext {
+        PromotionContext {
+            mode: crate::models::ChargingMode::Fast,
+            user_segment,
+            coupon_promotion_id,
+        }
+    }
+
+    #[test]
+    fn test_valley_service_discount() {
+        use crate::billing::{Discount, FeeComponent, PromotionConditions, UserSegment};
+        use crate::models::LineItemKind;
+
+        // 谷时服务费八折
+        let promotion = Promotion::new(
+            "谷时服务费八折".to_string(),
+            PromotionConditions { period_names: vec!["谷时".to_string()], ..Default::default() },
+            FeeComponent::Service,
+            Discount::Percentage(20.0),
+            None,
+            false,
+        );
+
+        // 北京时间 22:00 至 24:00：平时、谷时各 10 度
+        let start_time = local(2024, 3, 1, 22, 0, 0).unwrap();
+        let end_time = local(2024, 3, 2, 0, 0, 0).unwrap();
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F1".to_string(), 20.0, start_time, end_time);
+        let record = FeeCalculator::apply_promotions(record, &[promotion], &context(UserSegment::Returning, None));
+
+        assert_eq!(record.discount_fee, yuan("1.60"));  // 10 * 0.8 * 20%
+        assert_eq!(record.total_fee, yuan("25.40"));    // 11.00 + 16.00 - 1.60
+
+        let items = record.line_items();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[2].kind, LineItemKind::Discount);
+        assert_eq!(items[2].service_fee, yuan("-1.60"));
+        let subtotal = items.iter().map(|i| &i.subtotal).fold(zero_money(), |a, b| a + b);
+        assert_eq!(subtotal, record.total_fee);
+    }
+
+    #[test]
+    fn test_first_charge_free_up_to_limit() {
+        use crate::billing::{Discount, FeeComponent, PromotionConditions, UserSegment};
+
+        let promotion = Promotion::new(
+            "首充免费".to_string(),
+            PromotionConditions { user_segment: Some(UserSegment::NewUser), ..Default::default() },
+            FeeComponent::Both,
+            Discount::Percentage(100.0),
+            Some(20.0),
+            false,
+        );
+
+        // 北京时间 14:30 至 15:30：峰时、平时各 15 度，前 20 度免费
+        let start_time = local(2024, 3, 1, 14, 30, 0).unwrap();
+        let end_time = local(2024, 3, 1, 15, 30, 0).unwrap();
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F1".to_string(), 30.0, start_time, end_time);
+        let total = record.total_fee.clone();
+
+        let first = FeeCalculator::apply_promotions(
+            record.clone(),
+            std::slice::from_ref(&promotion),
+            &context(UserSegment::NewUser, None),
+        );
+        assert_eq!(first.discount_fee, yuan("34.50"));  // 15 * (1.0 + 0.8) + 5 * (0.7 + 0.8)
+        assert_eq!(first.total_fee, &total - yuan("34.50"));
+        assert!((first.discounts[0].energy_kwh - 20.0).abs() < 1e-9);
+
+        let returning = FeeCalculator::apply_promotions(record, &[promotion], &context(UserSegment::Returning, None));
+        assert!(returning.discounts.is_empty());
+        assert_eq!(returning.total_fee, total);
+    }
+
+    #[test]
+    fn test_coupon_and_stacking_caps() {
+        use crate::billing::{Discount, FeeComponent, PromotionConditions, UserSegment};
+
+        let coupon = Promotion::new(
+            "立减100元".to_string(),
+            PromotionConditions::default(),
+            FeeComponent::Energy,
+            Discount::Fixed(yuan("100")),
+            None,
+            true,
+        );
+        let half_off = Promotion::new(
+            "电费五折".to_string(),
+            PromotionConditions::default(),
+            FeeComponent::Energy,
+            Discount::Percentage(50.0),
+            None,
+            false,
+        );
+        let promotions = [coupon.clone(), half_off];
+
+        // 北京时间 11:00 至 12:00 峰时 30 度：电费 30 元
+        let start_time = local(2024, 3, 1, 11, 0, 0).unwrap();
+        let end_time = local(2024, 3, 1, 12, 0, 0).unwrap();
+        let record = FeeCalculator::calculate_fee(Uuid::new_v4(), "F1".to_string(), 30.0, start_time, end_time);
+
+        // 未核销优惠券时只有五折
+        let without = FeeCalculator::apply_promotions(record.clone(), &promotions, &context(UserSegment::Returning, None));
+        assert_eq!(without.discount_fee, yuan("15.00"));
+
+        // 优惠券减免不超过电费，之后的规则没有可减免的电费
+        let with = FeeCalculator::apply_promotions(record, &promotions, &context(UserSegment::Returning, Some(coupon.id)));
+        assert_eq!(with.discounts.len(), 1);
+        assert_eq!(with.discount_fee, yuan("30.00"));
+        assert_eq!(with.total_fee, yuan("24.00"));
+    }
+
+    mod properties {
+        use super::*;
+        use crate::billing::{Tariff, TariffPeriod};
+        use proptest::prelude::*;
+
+        fn schedule_with_change(change_offset: i64) -> TariffSchedule {
+            let change = local(2024, 3, 1, 0, 0, 0).unwrap() + Duration::seconds(change_offset);
+            let tariff = Tariff::new(
+                "新方案".to_string(),
+                change,
+                None,
+                vec![
+                    TariffPeriod::new("夜间", 22, 8, yuan("0.3"), yuan("0.5")),
+                    TariffPeriod::new("日间", 8, 22, yuan("0.9"), yuan("0.6")),
+                ],
+            );
+            TariffSchedule::new(vec![tariff])
+        }
+
+        proptest! {
+            #[test]
+            fn segment_energies_sum_to_amount(
+                start_offset in 0i64..3 * 86_400,
+                duration_ms in 0i64..5 * 86_400_000,
+                change_offset in 0i64..6 * 86_400,
+                amount in 0.0f64..500.0,
+                mut samples in prop::collection::vec((0.0f64..1.0, 0.0f64..1.0), 0..8),
+            ) {
+                let schedule = schedule_with_change(change_offset);
+                let start_time = local(2024, 3, 1, 0, 0, 0).unwrap() + Duration::seconds(start_offset);
+                let end_time = start_time + Duration::milliseconds(duration_ms);
+
+                // 随机单调递增的电量曲线
+                samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let mut energies: Vec<f64> = samples.iter().map(|s| s.1 * amount).collect();
+                energies.sort_by(f64::total_cmp);
+                let curve: Vec<_> = samples
+                    .iter()
+                    .zip(energies)
+                    .map(|(s, e)| (start_time + Duration::milliseconds((s.0 * duration_ms as f64) as i64), e))
+                    .collect();
+
+                let record = FeeCalculator::calculate_fee_from_curve(
+                    Uuid::new_v4(), "F1".to_string(), amount, start_time, end_time, &curve, &schedule,
+                );
+                let segments = &record.segments;
+                prop_assert!(!segments.is_empty());
+
+                let energy: f64 = segments.iter().map(|s| s.energy_kwh).sum();
+                prop_assert!((energy - amount).abs() < 1e-6, "电量之和 {} != {}", energy, amount);
+
+                // 账单金额与明细逐项相加完全一致
+                let fee: Money = segments.iter().map(|s| &s.electricity_fee + &s.service_fee).sum();
+                prop_assert_eq!(fee, &record.electricity_fee + &record.service_fee);
+
+                // 各段首尾相接覆盖整个充电过程，且每段内费率不变
+                prop_assert_eq!(segments[0].start_time, start_time);
+                prop_assert_eq!(segments[segments.len() - 1].end_time, end_time);
+                for pair in segments.windows(2) {
+                    prop_assert_eq!(pair[0].end_time, pair[1].start_time);
+                }
+                for segment in segments {
+                    prop_assert!(segment.energy_kwh >= -1e-9);
+                    if segment.end_time > segment.start_time {
+                        let last = segment.end_time - Duration::milliseconds(1);
+                        let rates = (segment.energy_rate.clone(), segment.service_rate.clone());
+                        prop_assert_eq!(schedule.rates_at(segment.start_time), rates.clone());
+                        prop_assert_eq!(schedule.rates_at(last), rates);
+                    }
+                }
+            }
+        }
+    }
+}
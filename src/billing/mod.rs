@@ -1,13 +1,29 @@
 mod fee_calculator;
 mod billing_record;
 mod time_slot;
+mod tariff;
+mod calendar;
+mod service_rate;
+mod promotion;
+mod consolidated_bill;
 
 pub use fee_calculator::FeeCalculator;
-pub use billing_record::BillingRecord;
+pub use billing_record::{BillingRecord, FeeSegment};
 pub use time_slot::TimeSlot;
+pub use tariff::{Tariff, TariffPeriod, TariffSchedule};
+pub use calendar::{CalendarDay, DayLayout, DayType, Season, TariffCalendar};
+pub use service_rate::{ServiceRate, ServiceRates};
+pub use promotion::{
+    AppliedDiscount, Coupon, Discount, FeeComponent, Promotion, PromotionConditions, PromotionContext, UserSegment,
+};
+pub use consolidated_bill::{BillPeriod, ConsolidatedBill};
 
-// 电费费率（元/度）
+// 默认电价方案的费率（元/度），数据库中没有生效的电价方案时使用
 pub const PEAK_RATE: f64 = 1.0;    // 峰时
 pub const FLAT_RATE: f64 = 0.7;    // 平时
 pub const VALLEY_RATE: f64 = 0.4;  // 谷时
-pub const SERVICE_RATE: f64 = 0.8;  // 服务费 
\ No newline at end of file
+pub const SERVICE_RATE: f64 = 0.8;  // 服务费
+
+// 超时占位费
+pub const IDLE_FEE_RATE: f64 = 0.5;      // 超出免费时长后每分钟收费（元/分钟）
+pub const IDLE_GRACE_MINUTES: i64 = 15;  // 充电结束后的免费占位时长（分钟） 
\ No newline at end of file
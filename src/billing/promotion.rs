@@ -0,0 +1,444 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::FeeSegment;
+use crate::models::{decimal_from_f64, round_money, to_station_local, zero_money, ChargingMode, Money, ENERGY_SCALE, RATE_SCALE};
+
+/// 优惠作用的费用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeeComponent {
+    Energy,  // 电费
+    Service, // 服务费
+    Both,    // 电费和服务费
+}
+
+impl FeeComponent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeeComponent::Energy => "Energy",
+            FeeComponent::Service => "Service",
+            FeeComponent::Both => "Both",
+        }
+    }
+
+    fn includes_energy(&self) -> bool {
+        matches!(self, FeeComponent::Energy | FeeComponent::Both)
+    }
+
+    fn includes_service(&self) -> bool {
+        matches!(self, FeeComponent::Service | FeeComponent::Both)
+    }
+}
+
+impl FromStr for FeeComponent {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Energy" => Ok(FeeComponent::Energy),
+            "Service" => Ok(FeeComponent::Service),
+            "Both" => Ok(FeeComponent::Both),
+            _ => Err(format!("Invalid FeeComponent: {}", s)),
+        }
+    }
+}
+
+/// 优惠方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Discount {
+    Percentage(f64), // 按比例减免（百分数，如 20 表示八折）
+    Fixed(Money),    // 固定金额减免（元），不超过适用费用
+}
+
+/// 用户分群
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UserSegment {
+    NewUser,   // 首次充电
+    Returning, // 已有充电记录
+}
+
+impl UserSegment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserSegment::NewUser => "NewUser",
+            UserSegment::Returning => "Returning",
+        }
+    }
+}
+
+impl FromStr for UserSegment {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "NewUser" => Ok(UserSegment::NewUser),
+            "Returning" => Ok(UserSegment::Returning),
+            _ => Err(format!("Invalid UserSegment: {}", s)),
+        }
+    }
+}
+
+/// 优惠条件，未设置的条件不限制；时间为充电站当地时间
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromotionConditions {
+    pub mode: Option<ChargingMode>,         // 充电模式
+    #[serde(default)]
+    pub period_names: Vec<String>,          // 电价时段名称，如 ["谷时"]，为空表示全部时段
+    pub user_segment: Option<UserSegment>,  // 用户分群
+    pub valid_from: Option<NaiveDateTime>,  // 生效时间（含）
+    pub valid_to: Option<NaiveDateTime>,    // 失效时间（不含）
+}
+
+/// 促销规则：满足条件的计费明细按优惠方式减免
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Promotion {
+    pub id: Uuid,
+    pub name: String,
+    pub conditions: PromotionConditions,
+    pub component: FeeComponent,
+    pub discount: Discount,
+    pub max_energy_kwh: Option<f64>, // 只减免前若干度电的费用
+    pub requires_coupon: bool,       // 需凭优惠券使用
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+/// 计算优惠所需的充电信息
+#[derive(Debug, Clone)]
+pub struct PromotionContext {
+    pub mode: ChargingMode,
+    pub user_segment: UserSegment,
+    pub coupon_promotion_id: Option<Uuid>, // 本次核销的优惠券所属促销
+}
+
+/// 一条已生效的优惠
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedDiscount {
+    pub promotion_id: Uuid,
+    pub name: String,
+    pub energy_kwh: f64,            // 享受优惠的电量（度）
+    pub electricity_discount: Money, // 电费减免
+    pub service_discount: Money,     // 服务费减免
+}
+
+impl AppliedDiscount {
+    pub fn total(&self) -> Money {
+        &self.electricity_discount + &self.service_discount
+    }
+}
+
+impl Promotion {
+    pub fn new(
+        name: String,
+        conditions: PromotionConditions,
+        component: FeeComponent,
+        discount: Discount,
+        max_energy_kwh: Option<f64>,
+        requires_coupon: bool,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            conditions,
+            component,
+            discount,
+            max_energy_kwh,
+            requires_coupon,
+            active: true,
+            created_at: to_station_local(Utc::now()),
+        }
+    }
+
+    /// 校验促销规则
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("促销名称不能为空".to_string());
+        }
+        match &self.discount {
+            Discount::Percentage(percent) if !(0.0..=100.0).contains(percent) => {
+                return Err("优惠比例必须在0到100之间".to_string());
+            }
+            Discount::Fixed(amount) if *amount < zero_money() => {
+                return Err("优惠金额不能为负".to_string());
+            }
+            _ => {}
+        }
+        if self.max_energy_kwh.is_some_and(|kwh| kwh <= 0.0) {
+            return Err("优惠电量上限必须大于0".to_string());
+        }
+        if let (Some(from), Some(to)) = (self.conditions.valid_from, self.conditions.valid_to) {
+            if to <= from {
+                return Err("失效时间必须晚于生效时间".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// 判断本次充电是否满足促销的整体条件（模式、用户分群、优惠券）
+    pub fn applies_to(&self, context: &PromotionContext) -> bool {
+        self.active
+            && self.conditions.mode.is_none_or(|mode| mode == context.mode)
+            && self.conditions.user_segment.is_none_or(|segment| segment == context.user_segment)
+            && (!self.requires_coupon || context.coupon_promotion_id == Some(self.id))
+    }
+
+    /// 判断某段计费明细是否满足时段和日期条件
+    fn covers(&self, segment: &FeeSegment) -> bool {
+        let start = to_station_local(segment.start_time);
+        (self.conditions.period_names.is_empty() || self.conditions.period_names.contains(&segment.period_name))
+            && self.conditions.valid_from.is_none_or(|from| start >= from)
+            && self.conditions.valid_to.is_none_or(|to| start < to)
+    }
+
+    /// 计算本促销对各段明细的减免，返回 (优惠电量, 电费减免, 服务费减免)
+    ///
+    /// 设置了电量上限时按时间顺序只计前若干度电，跨上限的一段按单价重新计价。
+    pub fn evaluate(&self, segments: &[FeeSegment]) -> (f64, Money, Money) {
+        let mut energy_left = self.max_energy_kwh.unwrap_or(f64::INFINITY);
+        let (mut energy, mut energy_base, mut service_base) = (0.0, zero_money(), zero_money());
+
+        for segment in segments.iter().filter(|s| self.covers(s)) {
+            if energy_left <= 0.0 {
+                break;
+            }
+            let used = segment.energy_kwh.min(energy_left);
+            energy_left -= used;
+            energy += used;
+            if used >= segment.energy_kwh {
+                energy_base += &segment.electricity_fee;
+                service_base += &segment.service_fee;
+            } else {
+                let used = decimal_from_f64(used, ENERGY_SCALE);
+                energy_base += round_money(&(&used * &segment.energy_rate));
+                service_base += round_money(&(&used * &segment.service_rate));
+            }
+        }
+
+        if !self.component.includes_energy() {
+            energy_base = zero_money();
+        }
+        if !self.component.includes_service() {
+            service_base = zero_money();
+        }
+
+        let (electricity_discount, service_discount) = match &self.discount {
+            Discount::Percentage(percent) => {
+                let ratio = decimal_from_f64(*percent, RATE_SCALE) / Money::from(100);
+                (round_money(&(&energy_base * &ratio)), round_money(&(&service_base * &ratio)))
+            }
+            // 固定金额先减电费，不足部分再减服务费
+            Discount::Fixed(amount) => {
+                let electricity = amount.clone().min(energy_base);
+                let service = (amount - &electricity).min(service_base);
+                (electricity, service)
+            }
+        };
+        (energy, electricity_discount, service_discount)
+    }
+
+    /// 保存促销规则
+    pub async fn insert(&self, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        let (discount_type, discount_value) = match &self.discount {
+            Discount::Percentage(percent) => ("Percentage", decimal_from_f64(*percent, RATE_SCALE)),
+            Discount::Fixed(amount) => ("Fixed", amount.clone()),
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO promotions (
+                id, name, mode, period_names, user_segment, valid_from, valid_to, component,
+                discount_type, discount_value, max_energy_kwh, requires_coupon, active, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(self.id.as_bytes().to_vec())
+        .bind(&self.name)
+        .bind(self.conditions.mode.map(String::from))
+        .bind(self.conditions.period_names.join(","))
+        .bind(self.conditions.user_segment.map(|s| s.as_str()))
+        .bind(self.conditions.valid_from)
+        .bind(self.conditions.valid_to)
+        .bind(self.component.as_str())
+        .bind(discount_type)
+        .bind(discount_value)
+        .bind(self.max_energy_kwh)
+        .bind(self.requires_coupon)
+        .bind(self.active)
+        .bind(self.created_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 启用或停用促销
+    pub async fn set_active(id: Uuid, active: bool, pool: &sqlx::MySqlPool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE promotions SET active = ? WHERE id = ?")
+            .bind(active)
+            .bind(id.as_bytes().to_vec())
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 获取全部促销规则，按创建时间排序
+    pub async fn find_all(pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, mode, period_names, user_segment, valid_from, valid_to, component,
+                   discount_type, discount_value, max_energy_kwh, requires_coupon, active, created_at
+            FROM promotions
+            ORDER BY created_at ASC
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let decode = |e: String| sqlx::Error::Decode(e.into());
+        let id_bytes: Vec<u8> = row.get("id");
+        let id = Uuid::from_slice(&id_bytes).map_err(|e| decode(format!("Failed to decode UUID: {}", e)))?;
+
+        let period_names: String = row.get("period_names");
+        let discount_value: Money = row.get("discount_value");
+        let discount_type: String = row.get("discount_type");
+        let discount = match discount_type.as_str() {
+            "Percentage" => Discount::Percentage(discount_value.to_string().parse().unwrap_or(0.0)),
+            "Fixed" => Discount::Fixed(discount_value),
+            other => return Err(decode(format!("Invalid discount type: {}", other))),
+        };
+
+        Ok(Promotion {
+            id,
+            name: row.get("name"),
+            conditions: PromotionConditions {
+                mode: row.get::<Option<String>, _>("mode").map(|m| m.parse()).transpose().map_err(decode)?,
+                period_names: period_names
+                    .split(',')
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                user_segment: row
+                    .get::<Option<String>, _>("user_segment")
+                    .map(|s| s.parse())
+                    .transpose()
+                    .map_err(decode)?,
+                valid_from: row.get("valid_from"),
+                valid_to: row.get("valid_to"),
+            },
+            component: row.get::<String, _>("component").parse().map_err(decode)?,
+            discount,
+            max_energy_kwh: row.get("max_energy_kwh"),
+            requires_coupon: row.get("requires_coupon"),
+            active: row.get("active"),
+            created_at: row.get("created_at"),
+        })
+    }
+}
+
+/// 一次性优惠券，核销后不能再次使用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Coupon {
+    pub code: String,
+    pub promotion_id: Uuid,
+    pub redeemed_by: Option<Uuid>,        // 核销用户
+    pub record_id: Option<Uuid>,          // 核销的充电详单
+    pub redeemed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Coupon {
+    /// 为促销生成若干张优惠券
+    pub fn generate(promotion_id: Uuid, count: usize) -> Vec<Self> {
+        let now = to_station_local(Utc::now());
+        (0..count)
+            .map(|_| Coupon {
+                code: Uuid::new_v4().simple().to_string()[..10].to_uppercase(),
+                promotion_id,
+                redeemed_by: None,
+                record_id: None,
+                redeemed_at: None,
+                created_at: now,
+            })
+            .collect()
+    }
+
+    /// 批量保存优惠券
+    pub async fn insert_batch(coupons: &[Coupon], pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        if coupons.is_empty() {
+            return Ok(());
+        }
+        let mut query_builder = sqlx::QueryBuilder::new("INSERT INTO coupons (code, promotion_id, created_at) ");
+        query_builder.push_values(coupons, |mut b, coupon| {
+            b.push_bind(&coupon.code)
+                .push_bind(coupon.promotion_id.as_bytes().to_vec())
+                .push_bind(coupon.created_at);
+        });
+        query_builder.build().execute(pool).await?;
+        Ok(())
+    }
+
+    /// 根据券码查询优惠券
+    pub async fn find(code: &str, pool: &sqlx::MySqlPool) -> Result<Option<Self>, sqlx::Error> {
+        let row = sqlx::query(&format!("SELECT {} FROM coupons WHERE code = ?", COUPON_COLUMNS))
+            .bind(code)
+            .fetch_optional(pool)
+            .await?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    /// 查询促销下的全部优惠券
+    pub async fn find_by_promotion(promotion_id: Uuid, pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM coupons WHERE promotion_id = ? ORDER BY created_at, code",
+            COUPON_COLUMNS
+        ))
+        .bind(promotion_id.as_bytes().to_vec())
+        .fetch_all(pool)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// 在写入充电详单的事务中核销优惠券；券码不存在或已被使用时返回 false
+    pub async fn redeem(
+        code: &str,
+        user_id: Uuid,
+        record_id: Uuid,
+        tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE coupons
+            SET redeemed_by = ?, record_id = ?, redeemed_at = ?
+            WHERE code = ? AND redeemed_by IS NULL
+            "#,
+        )
+        .bind(user_id.as_bytes().to_vec())
+        .bind(record_id.as_bytes().to_vec())
+        .bind(to_station_local(Utc::now()))
+        .bind(code)
+        .execute(&mut **tx)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let decode_uuid = |bytes: Vec<u8>| {
+            Uuid::from_slice(&bytes)
+                .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))
+        };
+        Ok(Coupon {
+            code: row.get("code"),
+            promotion_id: decode_uuid(row.get("promotion_id"))?,
+            redeemed_by: row.get::<Option<Vec<u8>>, _>("redeemed_by").map(decode_uuid).transpose()?,
+            record_id: row.get::<Option<Vec<u8>>, _>("record_id").map(decode_uuid).transpose()?,
+            redeemed_at: row.get("redeemed_at"),
+            created_at: row.get("created_at"),
+        })
+    }
+}
+
+const COUPON_COLUMNS: &str = "code, promotion_id, redeemed_by, record_id, redeemed_at, created_at";
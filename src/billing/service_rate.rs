@@ -0,0 +1,140 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::models::{deserialize_rate, round_rate, to_station_local, zero_money, ChargingMode, Money};
+
+/// 服务费率：按充电模式设置，可针对单个充电桩单独设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceRate {
+    pub mode: ChargingMode,
+    pub pile_id: Option<String>, // 为空表示该模式的全部充电桩
+    #[serde(deserialize_with = "deserialize_rate")]
+    pub rate: Money,             // 服务费（元/度）
+    pub updated_at: NaiveDateTime,
+}
+
+impl ServiceRate {
+    pub fn new(mode: ChargingMode, pile_id: Option<String>, rate: Money) -> Self {
+        Self {
+            mode,
+            pile_id: pile_id.filter(|p| !p.trim().is_empty()),
+            rate: round_rate(&rate),
+            updated_at: to_station_local(Utc::now()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rate < zero_money() {
+            return Err("服务费率不能为负".to_string());
+        }
+        Ok(())
+    }
+
+    /// 新增或修改服务费率
+    pub async fn upsert(&self, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO service_rates (mode, pile_id, rate, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE rate = VALUES(rate), updated_at = VALUES(updated_at)
+            "#,
+        )
+        .bind(self.mode.to_string())
+        .bind(self.pile_id.as_deref().unwrap_or(""))
+        .bind(&self.rate)
+        .bind(self.updated_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 删除服务费率，删除后回落到模式费率或电价时段的服务费
+    pub async fn delete(mode: ChargingMode, pile_id: Option<&str>, pool: &sqlx::MySqlPool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM service_rates WHERE mode = ? AND pile_id = ?")
+            .bind(mode.to_string())
+            .bind(pile_id.unwrap_or(""))
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn find_all(pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query("SELECT mode, pile_id, rate, updated_at FROM service_rates ORDER BY mode, pile_id")
+            .fetch_all(pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let mode: String = row.get("mode");
+                let pile_id: String = row.get("pile_id");
+                Ok(ServiceRate {
+                    mode: mode.parse().map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+                    pile_id: Some(pile_id).filter(|p| !p.is_empty()),
+                    rate: row.get("rate"),
+                    updated_at: row.get("updated_at"),
+                })
+            })
+            .collect()
+    }
+}
+
+/// 服务费率表：充电桩单独设置的费率优先，其次为充电模式的费率
+#[derive(Debug, Clone, Default)]
+pub struct ServiceRates {
+    rates: Vec<ServiceRate>,
+}
+
+impl ServiceRates {
+    pub fn new(rates: Vec<ServiceRate>) -> Self {
+        Self { rates }
+    }
+
+    /// 从数据库加载服务费率表
+    pub async fn load(pool: &sqlx::MySqlPool) -> Result<Self, sqlx::Error> {
+        Ok(Self::new(ServiceRate::find_all(pool).await?))
+    }
+
+    pub fn rates(&self) -> &[ServiceRate] {
+        &self.rates
+    }
+
+    /// 某一充电桩适用的服务费率，没有设置时返回 None（按电价时段的服务费计价）
+    pub fn rate_for(&self, mode: ChargingMode, pile_id: &str) -> Option<Money> {
+        let by_pile = self
+            .rates
+            .iter()
+            .find(|r| r.mode == mode && r.pile_id.as_deref() == Some(pile_id));
+        by_pile
+            .or_else(|| self.rates.iter().find(|r| r.mode == mode && r.pile_id.is_none()))
+            .map(|r| r.rate.clone())
+    }
+
+    /// 某一充电模式适用的服务费率（不指定充电桩，用于报价）
+    pub fn rate_for_mode(&self, mode: ChargingMode) -> Option<Money> {
+        self.rates.iter().find(|r| r.mode == mode && r.pile_id.is_none()).map(|r| r.rate.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pile_rate_overrides_mode_rate() {
+        let rate = |value: &str| value.parse::<Money>().unwrap();
+        let rates = ServiceRates::new(vec![
+            ServiceRate::new(ChargingMode::Fast, None, rate("1.0")),
+            ServiceRate::new(ChargingMode::Fast, Some("F3".to_string()), rate("1.5")),
+            ServiceRate::new(ChargingMode::Slow, None, rate("0.6")),
+        ]);
+
+        assert_eq!(rates.rate_for(ChargingMode::Fast, "F1"), Some(rate("1.0")));
+        assert_eq!(rates.rate_for(ChargingMode::Fast, "F3"), Some(rate("1.5")));
+        assert_eq!(rates.rate_for(ChargingMode::Slow, "T1"), Some(rate("0.6")));
+        assert_eq!(rates.rate_for_mode(ChargingMode::Fast), Some(rate("1.0")));
+
+        // 未设置时按电价时段计价
+        assert_eq!(ServiceRates::default().rate_for(ChargingMode::Fast, "F1"), None);
+        assert!(ServiceRate::new(ChargingMode::Slow, None, rate("-0.1")).validate().is_err());
+    }
+}
@@ -0,0 +1,514 @@
+use chrono::{DateTime, Duration, NaiveDateTime, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+use super::{TariffCalendar, FLAT_RATE, PEAK_RATE, SERVICE_RATE, VALLEY_RATE};
+use crate::models::{decimal_from_f64, deserialize_rate, from_local, round_rate, station_timezone, zero_money, Money, RATE_SCALE};
+
+/// 电价时段（起止时刻为充电站当地时间）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TariffPeriod {
+    pub name: String,          // 时段名称，如"峰时"
+    pub start_time: NaiveTime, // 开始时刻（含）
+    pub end_time: NaiveTime,   // 结束时刻（不含），不晚于开始时刻表示跨零点
+    #[serde(deserialize_with = "deserialize_rate")]
+    pub energy_rate: Money,    // 电费（元/度）
+    #[serde(deserialize_with = "deserialize_rate")]
+    pub service_rate: Money,   // 服务费（元/度）
+}
+
+impl TariffPeriod {
+    pub fn new(name: &str, start_hour: u32, end_hour: u32, energy_rate: Money, service_rate: Money) -> Self {
+        Self {
+            name: name.to_string(),
+            start_time: NaiveTime::from_hms_opt(start_hour % 24, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(end_hour % 24, 0, 0).unwrap(),
+            energy_rate: round_rate(&energy_rate),
+            service_rate: round_rate(&service_rate),
+        }
+    }
+
+    /// 判断某一时刻是否落在该时段内
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start_time < self.end_time {
+            self.start_time <= time && time < self.end_time
+        } else {
+            // 跨零点（开始与结束相同表示全天）
+            time >= self.start_time || time < self.end_time
+        }
+    }
+}
+
+/// 电价方案：一组覆盖全天的时段及其生效区间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tariff {
+    pub id: Uuid,
+    pub name: String,
+    pub effective_from: NaiveDateTime,       // 生效时间（含）
+    pub effective_to: Option<NaiveDateTime>, // 失效时间（不含），为空表示长期有效
+    pub periods: Vec<TariffPeriod>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Tariff {
+    pub fn new(
+        name: String,
+        effective_from: DateTime<Utc>,
+        effective_to: Option<DateTime<Utc>>,
+        periods: Vec<TariffPeriod>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            effective_from: effective_from.naive_utc(),
+            effective_to: effective_to.map(|t| t.naive_utc()),
+            periods,
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    /// 默认电价：峰时 10:00-15:00、18:00-21:00，平时 7:00-10:00、15:00-18:00、21:00-23:00，谷时 23:00-次日7:00
+    pub fn default_tariff() -> Self {
+        let period = |name, start_hour, end_hour, energy_rate| {
+            let rate = |value| decimal_from_f64(value, RATE_SCALE);
+            TariffPeriod::new(name, start_hour, end_hour, rate(energy_rate), rate(SERVICE_RATE))
+        };
+        let periods = vec![
+            period("谷时", 23, 7, VALLEY_RATE),
+            period("平时", 7, 10, FLAT_RATE),
+            period("峰时", 10, 15, PEAK_RATE),
+            period("平时", 15, 18, FLAT_RATE),
+            period("峰时", 18, 21, PEAK_RATE),
+            period("平时", 21, 23, FLAT_RATE),
+        ];
+        Self {
+            id: Uuid::nil(),
+            name: "默认电价".to_string(),
+            effective_from: NaiveDateTime::MIN,
+            effective_to: None,
+            periods,
+            created_at: NaiveDateTime::MIN,
+        }
+    }
+
+    /// 校验电价方案：时段必须不重叠地覆盖全天，费率非负，失效时间晚于生效时间
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("电价方案名称不能为空".to_string());
+        }
+        if self.effective_to.is_some_and(|to| to <= self.effective_from) {
+            return Err("失效时间必须晚于生效时间".to_string());
+        }
+        if self.periods.is_empty() {
+            return Err("电价方案至少需要一个时段".to_string());
+        }
+        for period in &self.periods {
+            if period.energy_rate < zero_money() || period.service_rate < zero_money() {
+                return Err(format!("时段 {} 的费率不能为负", period.name));
+            }
+        }
+
+        // 逐秒检查覆盖情况
+        for second in 0..86_400 {
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(second, 0).unwrap();
+            let count = self.periods.iter().filter(|p| p.contains(time)).count();
+            if count == 0 {
+                return Err(format!("时刻 {} 未被任何时段覆盖", time));
+            }
+            if count > 1 {
+                return Err(format!("时刻 {} 被多个时段覆盖", time));
+            }
+        }
+        Ok(())
+    }
+
+    /// 判断该方案在某一时刻是否生效
+    pub fn is_effective_at(&self, time: DateTime<Utc>) -> bool {
+        let time = time.naive_utc();
+        self.effective_from <= time && self.effective_to.is_none_or(|to| time < to)
+    }
+
+    /// 获取当地某一时刻所在的时段
+    pub fn period_at(&self, local_time: NaiveTime) -> Option<&TariffPeriod> {
+        let local_time = local_time.with_nanosecond(0).unwrap();
+        self.periods.iter().find(|p| p.contains(local_time))
+    }
+
+    /// 保存电价方案及其时段
+    pub async fn insert(&self, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO tariffs (id, name, effective_from, effective_to, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(self.id.as_bytes().to_vec())
+        .bind(&self.name)
+        .bind(self.effective_from)
+        .bind(self.effective_to)
+        .bind(self.created_at)
+        .execute(&mut *tx)
+        .await?;
+        Self::insert_periods(self.id, &self.periods, &mut tx).await?;
+        tx.commit().await
+    }
+
+    /// 更新电价方案，时段整体替换
+    pub async fn update(&self, pool: &sqlx::MySqlPool) -> Result<bool, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE tariffs
+            SET name = ?, effective_from = ?, effective_to = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&self.name)
+        .bind(self.effective_from)
+        .bind(self.effective_to)
+        .bind(self.id.as_bytes().to_vec())
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            let exists = sqlx::query("SELECT id FROM tariffs WHERE id = ?")
+                .bind(self.id.as_bytes().to_vec())
+                .fetch_optional(&mut *tx)
+                .await?
+                .is_some();
+            if !exists {
+                return Ok(false);
+            }
+        }
+
+        sqlx::query("DELETE FROM tariff_periods WHERE tariff_id = ?")
+            .bind(self.id.as_bytes().to_vec())
+            .execute(&mut *tx)
+            .await?;
+        Self::insert_periods(self.id, &self.periods, &mut tx).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// 删除电价方案
+    pub async fn delete(id: Uuid, pool: &sqlx::MySqlPool) -> Result<bool, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM tariff_periods WHERE tariff_id = ?")
+            .bind(id.as_bytes().to_vec())
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM tariffs WHERE id = ?")
+            .bind(id.as_bytes().to_vec())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 获取全部电价方案，按生效时间排序
+    pub async fn find_all(pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, effective_from, effective_to, created_at
+            FROM tariffs
+            ORDER BY effective_from ASC
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let period_rows = sqlx::query(
+            r#"
+            SELECT tariff_id, name, start_time, end_time, energy_rate, service_rate
+            FROM tariff_periods
+            ORDER BY tariff_id, start_time
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut tariffs = Vec::new();
+        for row in rows {
+            let id_bytes: Vec<u8> = row.get("id");
+            let id = Uuid::from_slice(&id_bytes).map_err(|e| {
+                sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
+            })?;
+
+            let periods = period_rows
+                .iter()
+                .filter(|p| p.get::<Vec<u8>, _>("tariff_id") == id_bytes)
+                .map(|p| TariffPeriod {
+                    name: p.get("name"),
+                    start_time: p.get("start_time"),
+                    end_time: p.get("end_time"),
+                    energy_rate: p.get("energy_rate"),
+                    service_rate: p.get("service_rate"),
+                })
+                .collect();
+
+            tariffs.push(Tariff {
+                id,
+                name: row.get("name"),
+                effective_from: row.get("effective_from"),
+                effective_to: row.get("effective_to"),
+                periods,
+                created_at: row.get("created_at"),
+            });
+        }
+
+        Ok(tariffs)
+    }
+
+    async fn insert_periods(
+        tariff_id: Uuid,
+        periods: &[TariffPeriod],
+        tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+    ) -> Result<(), sqlx::Error> {
+        for period in periods {
+            sqlx::query(
+                r#"
+                INSERT INTO tariff_periods (tariff_id, name, start_time, end_time, energy_rate, service_rate)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(tariff_id.as_bytes().to_vec())
+            .bind(&period.name)
+            .bind(period.start_time)
+            .bind(period.end_time)
+            .bind(&period.energy_rate)
+            .bind(&period.service_rate)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// 电价表：按生效时间选择电价方案，没有方案生效时使用默认电价；时段按充电站当地时间划分
+///
+/// 设置了电价日历时，当天的日类型有对应布局则按布局计价，优先于电价方案。
+#[derive(Debug, Clone)]
+pub struct TariffSchedule {
+    tariffs: Vec<Tariff>,
+    default_tariff: Tariff,
+    calendar: TariffCalendar,
+    timezone: Tz,
+}
+
+impl Default for TariffSchedule {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl TariffSchedule {
+    pub fn new(mut tariffs: Vec<Tariff>) -> Self {
+        tariffs.sort_by_key(|t| t.effective_from);
+        Self {
+            tariffs,
+            default_tariff: Tariff::default_tariff(),
+            calendar: TariffCalendar::default(),
+            timezone: station_timezone(),
+        }
+    }
+
+    /// 指定划分时段所用的时区
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// 指定电价日历
+    pub fn with_calendar(mut self, calendar: TariffCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    pub fn calendar(&self) -> &TariffCalendar {
+        &self.calendar
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    /// 从数据库加载电价表
+    pub async fn load(pool: &sqlx::MySqlPool) -> Result<Self, sqlx::Error> {
+        Ok(Self::new(Tariff::find_all(pool).await?))
+    }
+
+    pub fn tariffs(&self) -> &[Tariff] {
+        &self.tariffs
+    }
+
+    /// 获取某一时刻生效的电价方案，多个方案同时生效时以生效时间最晚的为准
+    pub fn tariff_at(&self, time: DateTime<Utc>) -> &Tariff {
+        self.effective_tariff_at(time).unwrap_or(&self.default_tariff)
+    }
+
+    /// 某一时刻生效的数据库电价方案，没有时为 None（按默认电价）
+    fn effective_tariff_at(&self, time: DateTime<Utc>) -> Option<&Tariff> {
+        self.tariffs.iter().rev().find(|t| t.is_effective_at(time))
+    }
+
+    /// 获取某一时刻所在的电价方案和时段，方案未覆盖该时刻时按默认电价
+    ///
+    /// 当天有日历布局时由布局决定该时刻属于哪个时段；生效的电价方案中有同名时段时按方案的费率计价，
+    /// 否则（或没有生效的方案时）按布局中该时段的费率。
+    pub fn period_at(&self, time: DateTime<Utc>) -> (&Tariff, &TariffPeriod) {
+        let local = time.with_timezone(&self.timezone);
+        let local_time = local.time();
+        if let Some(layout) = self.calendar.layout_for(local.date_naive()) {
+            if let Some(period) = layout.period_at(local_time) {
+                let priced = self.effective_tariff_at(time).and_then(|tariff| {
+                    tariff.periods.iter().find(|p| p.name == period.name).map(|p| (tariff, p))
+                });
+                return priced.unwrap_or((layout, period));
+            }
+        }
+
+        let tariff = self.tariff_at(time);
+        match tariff.period_at(local_time) {
+            Some(period) => (tariff, period),
+            None => (
+                &self.default_tariff,
+                self.default_tariff
+                    .period_at(local_time)
+                    .expect("默认电价覆盖全天"),
+            ),
+        }
+    }
+
+    /// 获取某一时刻的 (电费, 服务费) 费率（元/度）
+    pub fn rates_at(&self, time: DateTime<Utc>) -> (Money, Money) {
+        let (_, period) = self.period_at(time);
+        (period.energy_rate.clone(), period.service_rate.clone())
+    }
+
+    /// 某一时刻之后最近的计价边界：当地时段切换、电价方案切换或日历上日类型切换（当地零点）
+    pub fn next_boundary_after(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let tariff = self.tariff_at(time);
+        let date = time.with_timezone(&self.timezone).date_naive();
+        let next_date = date.succ_opt().unwrap_or(date);
+        let mut next = self.next_change_after(time);
+        if !self.calendar.is_empty() {
+            let midnight = from_local(next_date.and_time(NaiveTime::MIN), self.timezone);
+            if midnight > time && next.is_none_or(|n| midnight < n) {
+                next = Some(midnight);
+            }
+        }
+
+        // 各时段的起点在当天或次日必有一个晚于该时刻
+        for day in [date, next_date] {
+            let periods = self.calendar.layout_for(day).map_or(&tariff.periods, |layout| &layout.periods);
+            for period in periods.iter().chain(&self.default_tariff.periods) {
+                let boundary = from_local(day.and_time(period.start_time), self.timezone);
+                if boundary > time && next.is_none_or(|n| boundary < n) {
+                    next = Some(boundary);
+                }
+            }
+        }
+        next.unwrap_or(time + Duration::days(1))
+    }
+
+    /// 某一时刻之后最近一次电价方案切换的时间
+    pub fn next_change_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let naive = time.naive_utc();
+        self.tariffs
+            .iter()
+            .flat_map(|t| std::iter::once(t.effective_from).chain(t.effective_to))
+            .filter(|&t| t > naive)
+            .min()
+            .map(|t| t.and_utc())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::billing::TimeSlot;
+    use chrono::{Duration, TimeZone};
+    use chrono_tz::Asia::Shanghai;
+
+    fn rate(value: &str) -> Money {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_default_tariff_matches_time_slots() {
+        let tariff = Tariff::default_tariff();
+        assert!(tariff.validate().is_ok());
+        let schedule = TariffSchedule::default().with_timezone(Shanghai);
+        for hour in 0..24 {
+            let time = Utc.with_ymd_and_hms(2024, 3, 1, hour, 30, 0).unwrap();
+            let (energy_rate, service_rate) = schedule.rates_at(time);
+            assert_eq!(energy_rate, decimal_from_f64(TimeSlot::from_time_in(&time, Shanghai).get_rate(), RATE_SCALE));
+            assert_eq!(service_rate.to_string(), "0.8000");
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_gaps_and_overlaps() {
+        let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let gap = Tariff::new(
+            "缺时段".to_string(),
+            from,
+            None,
+            vec![TariffPeriod::new("白天", 8, 20, rate("1.0"), rate("0.8"))],
+        );
+        assert!(gap.validate().is_err());
+
+        let overlap = Tariff::new(
+            "重叠".to_string(),
+            from,
+            None,
+            vec![
+                TariffPeriod::new("全天", 0, 0, rate("0.5"), rate("0.8")),
+                TariffPeriod::new("峰时", 10, 12, rate("1.0"), rate("0.8")),
+            ],
+        );
+        assert!(overlap.validate().is_err());
+
+        let flat = Tariff::new("单一电价".to_string(), from, None, vec![TariffPeriod::new("全天", 0, 0, rate("0.5"), rate("0.6"))]);
+        assert!(flat.validate().is_ok());
+
+        let reversed = Tariff::new(
+            "失效早于生效".to_string(),
+            from,
+            Some(from - Duration::days(1)),
+            vec![TariffPeriod::new("全天", 0, 0, rate("0.5"), rate("0.6"))],
+        );
+        assert!(reversed.validate().is_err());
+    }
+
+    #[test]
+    fn test_schedule_picks_tariff_in_force() {
+        let change = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let summer = Tariff::new(
+            "夏季电价".to_string(),
+            change,
+            Some(change + Duration::days(92)),
+            vec![TariffPeriod::new("全天", 0, 0, rate("0.5"), rate("0.6"))],
+        );
+        let schedule = TariffSchedule::new(vec![summer]).with_timezone(Shanghai);
+
+        // 生效前使用默认电价（北京时间19点为峰时）
+        let before = change - Duration::hours(13);
+        assert_eq!(schedule.rates_at(before), (rate("1.0"), rate("0.8")));
+        assert_eq!(schedule.tariff_at(before).name, "默认电价");
+
+        let during = change + Duration::hours(11);
+        assert_eq!(schedule.rates_at(during), (rate("0.5"), rate("0.6")));
+        assert_eq!(schedule.tariff_at(during).name, "夏季电价");
+
+        // 失效后回到默认电价
+        let after = change + Duration::days(92) + Duration::hours(11);
+        assert_eq!(schedule.rates_at(after), (rate("1.0"), rate("0.8")));
+
+        assert_eq!(schedule.next_change_after(before), Some(change));
+        assert_eq!(schedule.next_change_after(during), Some(change + Duration::days(92)));
+        assert_eq!(schedule.next_change_after(after), None);
+    }
+}
@@ -1,6 +1,9 @@
 use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::Tz;
 use serde::{Serialize, Deserialize};
 
+use crate::models::station_timezone;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TimeSlot {
     Peak,    // 峰时：10:00-15:00，18:00-21:00
@@ -9,9 +12,14 @@ pub enum TimeSlot {
 }
 
 impl TimeSlot {
-    /// 判断给定时间属于哪个时段
+    /// 判断给定时间属于哪个时段（按充电站当地时间）
     pub fn from_time(time: &DateTime<Utc>) -> Self {
-        let hour = time.hour();
+        Self::from_time_in(time, station_timezone())
+    }
+
+    /// 按指定时区的当地时间判断时段
+    pub fn from_time_in(time: &DateTime<Utc>, tz: Tz) -> Self {
+        let hour = time.with_timezone(&tz).hour();
         
         match hour {
             // 峰时段
@@ -44,30 +52,37 @@ impl TimeSlot {
 mod tests {
     use super::*;
     use chrono::TimeZone;
+    use chrono_tz::Asia::Shanghai;
     use crate::billing::{PEAK_RATE, FLAT_RATE, VALLEY_RATE};
 
+    fn slot_at(year: i32, month: u32, day: u32, hour: u32, min: u32) -> TimeSlot {
+        let time = Shanghai.with_ymd_and_hms(year, month, day, hour, min, 0).unwrap();
+        TimeSlot::from_time_in(&time.with_timezone(&Utc), Shanghai)
+    }
+
     #[test]
     fn test_time_slot_classification() {
-        // 测试峰时段
-        let peak_time1 = Utc.with_ymd_and_hms(2024, 3, 1, 11, 0, 0).unwrap();
-        assert_eq!(TimeSlot::from_time(&peak_time1), TimeSlot::Peak);
-        
-        let peak_time2 = Utc.with_ymd_and_hms(2024, 3, 1, 19, 30, 0).unwrap();
-        assert_eq!(TimeSlot::from_time(&peak_time2), TimeSlot::Peak);
+        // 测试峰时段（当地时间）
+        assert_eq!(slot_at(2024, 3, 1, 11, 0), TimeSlot::Peak);
+        assert_eq!(slot_at(2024, 3, 1, 19, 30), TimeSlot::Peak);
 
         // 测试平时段
-        let flat_time1 = Utc.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap();
-        assert_eq!(TimeSlot::from_time(&flat_time1), TimeSlot::Flat);
-        
-        let flat_time2 = Utc.with_ymd_and_hms(2024, 3, 1, 16, 30, 0).unwrap();
-        assert_eq!(TimeSlot::from_time(&flat_time2), TimeSlot::Flat);
+        assert_eq!(slot_at(2024, 3, 1, 8, 0), TimeSlot::Flat);
+        assert_eq!(slot_at(2024, 3, 1, 16, 30), TimeSlot::Flat);
 
         // 测试谷时段
-        let valley_time1 = Utc.with_ymd_and_hms(2024, 3, 1, 23, 30, 0).unwrap();
-        assert_eq!(TimeSlot::from_time(&valley_time1), TimeSlot::Valley);
-        
-        let valley_time2 = Utc.with_ymd_and_hms(2024, 3, 1, 5, 0, 0).unwrap();
-        assert_eq!(TimeSlot::from_time(&valley_time2), TimeSlot::Valley);
+        assert_eq!(slot_at(2024, 3, 1, 23, 30), TimeSlot::Valley);
+        assert_eq!(slot_at(2024, 3, 1, 5, 0), TimeSlot::Valley);
+    }
+
+    #[test]
+    fn test_classification_uses_local_time() {
+        // UTC 11:00 为北京时间 19:00，属于峰时
+        let evening = Utc.with_ymd_and_hms(2024, 3, 1, 11, 0, 0).unwrap();
+        assert_eq!(TimeSlot::from_time_in(&evening, Shanghai), TimeSlot::Peak);
+        // UTC 15:30 为北京时间 23:30，属于谷时
+        let night = Utc.with_ymd_and_hms(2024, 3, 1, 15, 30, 0).unwrap();
+        assert_eq!(TimeSlot::from_time_in(&night, Shanghai), TimeSlot::Valley);
     }
 
     #[test]
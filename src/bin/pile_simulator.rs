@@ -0,0 +1,433 @@
+//! 模拟充电桩：以 OCPP 1.6-J 接入中央系统，并通过 HTTP 接口提交充电请求、上报故障与恢复，
+//! 用于在单机上对调度与计费做并发压测。
+//!
+//! 后端接口需要登录，模拟器须以运营人员（operator）账号运行：上报故障/恢复需要充电桩控制权限，
+//! 查询全部充电请求需要调度器控制权限。
+//!
+//! 限制：所有模拟车辆的充电请求都以这一个账号提交，因此不会覆盖按用户区分的规则
+//! （每个用户的排队与取消、钱包余额与未结算请求占用、按用户分群的促销），这些规则需另行测试。
+//!
+//! 用法：SIMULATOR_PASSWORD=****** cargo run --bin pile_simulator -- --username operator --piles 5 --meter-interval-ms 500 --fault-rate 0.01
+
+use charging_station::models::{FAST_CHARGING_POWER, SLOW_CHARGING_POWER};
+use charging_station::ocpp::messages::ChargePointStatus;
+use charging_station::ocpp::{ChargePoint, ChargePointCredentials};
+use rand::Rng;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time;
+
+const USAGE: &str = "用法：SIMULATOR_PASSWORD=****** pile_simulator --username operator [--piles 5] [--meter-interval-ms 1000] \
+[--fault-rate 0.005] [--ocpp-url ws://127.0.0.1:9000] [--api-url http://127.0.0.1:8080] [--repair-secs 10] \
+[--request-interval-ms 2000] [--dwell-ms 0] [--speedup 30] [--duration-secs N]
+--password 仅在未设置 SIMULATOR_PASSWORD 时使用。
+所有充电请求都以该账号提交，不会覆盖按用户区分的排队、钱包和促销规则。";
+
+/// 模拟参数
+#[derive(Debug, Clone)]
+struct SimConfig {
+    ocpp_url: String,          // 中央系统地址
+    api_url: String,           // 后端HTTP地址
+    username: String,          // 登录后端的运营人员账号
+    password: String,          // 优先取环境变量 SIMULATOR_PASSWORD，未设置时取 --password
+    piles: usize,              // 模拟的充电桩数量
+    meter_interval: Duration,  // 电表上报间隔
+    fault_rate: f64,           // 每次上报时发生故障的概率
+    repair_time: Duration,     // 故障修复耗时
+    request_interval: Duration, // 提交充电请求的间隔
+    dwell_time: Duration,      // 充电结束后车辆拔枪前的停留时间
+    speedup: f64,              // 电量累计的时间加速倍数（与后端时间系统一致）
+    duration: Option<Duration>, // 运行时长，缺省一直运行
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            ocpp_url: "ws://127.0.0.1:9000".to_string(),
+            api_url: "http://127.0.0.1:8080".to_string(),
+            username: String::new(),
+            password: String::new(),
+            piles: 5,
+            meter_interval: Duration::from_millis(1000),
+            fault_rate: 0.005,
+            repair_time: Duration::from_secs(10),
+            request_interval: Duration::from_millis(2000),
+            dwell_time: Duration::ZERO,
+            speedup: 30.0,
+            duration: None,
+        }
+    }
+}
+
+impl SimConfig {
+    fn from_args() -> Result<Self, String> {
+        let mut config = SimConfig::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("参数 {} 缺少取值", flag))?;
+            match flag.as_str() {
+                "--ocpp-url" => config.ocpp_url = value,
+                "--api-url" => config.api_url = value,
+                "--username" => config.username = value,
+                "--password" => config.password = value,
+                "--piles" => config.piles = parse_arg(&flag, &value)?,
+                "--meter-interval-ms" => config.meter_interval = Duration::from_millis(parse_arg(&flag, &value)?),
+                "--fault-rate" => config.fault_rate = parse_arg(&flag, &value)?,
+                "--repair-secs" => config.repair_time = Duration::from_secs(parse_arg(&flag, &value)?),
+                "--request-interval-ms" => {
+                    config.request_interval = Duration::from_millis(parse_arg(&flag, &value)?)
+                }
+                "--dwell-ms" => config.dwell_time = Duration::from_millis(parse_arg(&flag, &value)?),
+                "--speedup" => config.speedup = parse_arg(&flag, &value)?,
+                "--duration-secs" => config.duration = Some(Duration::from_secs(parse_arg(&flag, &value)?)),
+                _ => return Err(format!("未知参数: {}\n{}", flag, USAGE)),
+            }
+        }
+        // 密码优先从环境变量读取，避免出现在命令行和进程列表中
+        if let Some(password) = std::env::var("SIMULATOR_PASSWORD").ok().filter(|p| !p.is_empty()) {
+            config.password = password;
+        }
+        if config.username.is_empty() || config.password.is_empty() {
+            return Err(format!("必须通过 --username 和环境变量 SIMULATOR_PASSWORD 指定运营人员账号\n{}", USAGE));
+        }
+        if !(0.0..=1.0).contains(&config.fault_rate) {
+            return Err("--fault-rate 必须在 0 到 1 之间".to_string());
+        }
+        Ok(config)
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("参数 {} 的取值无效: {}", flag, value))
+}
+
+/// 访问令牌和刷新令牌
+#[derive(Debug, Clone)]
+struct Tokens {
+    access_token: String,
+    refresh_token: String,
+}
+
+/// 后端HTTP接口客户端：每个请求携带访问令牌，返回 401 时刷新令牌后重试一次
+struct ApiClient {
+    client: reqwest::Client,
+    api_url: String,
+    credentials: Value,
+    tokens: RwLock<Tokens>,
+}
+
+impl ApiClient {
+    /// 以配置的账号登录后端
+    async fn login(config: &SimConfig) -> Result<Self, String> {
+        let client = reqwest::Client::new();
+        let credentials = json!({ "username": config.username, "password": config.password });
+        let tokens = request_tokens(&client, &format!("{}/login", config.api_url), &credentials).await?;
+        println!("🔑 已以 {} 登录后端", config.username);
+        Ok(Self {
+            client,
+            api_url: config.api_url.clone(),
+            credentials,
+            tokens: RwLock::new(tokens),
+        })
+    }
+
+    async fn get(&self, path: &str) -> Result<reqwest::Response, String> {
+        self.send(Method::GET, path, None).await
+    }
+
+    async fn post(&self, path: &str, body: Option<&Value>) -> Result<reqwest::Response, String> {
+        self.send(Method::POST, path, body).await
+    }
+
+    async fn send(&self, method: Method, path: &str, body: Option<&Value>) -> Result<reqwest::Response, String> {
+        let mut retried = false;
+        loop {
+            let access_token = self.tokens.read().await.access_token.clone();
+            let mut request = self
+                .client
+                .request(method.clone(), format!("{}{}", self.api_url, path))
+                .bearer_auth(&access_token);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let response = request.send().await.map_err(|e| e.to_string())?;
+            if response.status() != StatusCode::UNAUTHORIZED || retried {
+                return Ok(response);
+            }
+            self.refresh(&access_token).await?;
+            retried = true;
+        }
+    }
+
+    /// 用刷新令牌换取新令牌，刷新令牌也失效时重新登录；其他任务已换过令牌时直接使用新令牌
+    async fn refresh(&self, expired: &str) -> Result<(), String> {
+        let mut tokens = self.tokens.write().await;
+        if tokens.access_token != expired {
+            return Ok(());
+        }
+        let body = json!({ "refresh_token": tokens.refresh_token });
+        *tokens = match request_tokens(&self.client, &format!("{}/refresh", self.api_url), &body).await {
+            Ok(refreshed) => refreshed,
+            Err(e) => {
+                println!("⚠️ 刷新令牌失败: {}，重新登录", e);
+                request_tokens(&self.client, &format!("{}/login", self.api_url), &self.credentials).await?
+            }
+        };
+        println!("🔑 访问令牌已刷新");
+        Ok(())
+    }
+}
+
+/// 调用登录或刷新接口，取出返回的令牌
+async fn request_tokens(client: &reqwest::Client, url: &str, body: &Value) -> Result<Tokens, String> {
+    let response = client.post(url).json(body).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let response: Value = response.json().await.map_err(|e| format!("{}: {}", status, e))?;
+    if !status.is_success() {
+        return Err(format!("{}: {}", status, response["message"].as_str().unwrap_or_default()));
+    }
+    let token = |name: &str| {
+        response["data"][name]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("响应中缺少 {}", name))
+    };
+    Ok(Tokens {
+        access_token: token("access_token")?,
+        refresh_token: token("refresh_token")?,
+    })
+}
+
+/// 模拟统计
+#[derive(Default)]
+struct SimStats {
+    requests_submitted: AtomicUsize,
+    requests_rejected: AtomicUsize,
+    sessions_completed: AtomicUsize,
+    faults: AtomicUsize,
+    energy_wh: AtomicUsize,
+}
+
+#[tokio::main]
+async fn main() {
+    let config = match SimConfig::from_args() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let api = match ApiClient::login(&config).await {
+        Ok(api) => Arc::new(api),
+        Err(e) => {
+            eprintln!("登录后端失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let stats = Arc::new(SimStats::default());
+
+    // 从后端获取充电桩列表
+    let piles = match fetch_piles(&api).await {
+        Ok(piles) => piles,
+        Err(e) => {
+            eprintln!("获取充电桩列表失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let piles: Vec<(String, String)> = piles.into_iter().take(config.piles).collect();
+    println!("🚗 模拟 {} 个充电桩: {:?}", piles.len(), piles);
+
+    for (number, mode) in piles {
+        let config = config.clone();
+        let api = api.clone();
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = run_pile(&config, &api, &stats, &number, &mode).await {
+                    println!("⚠️ 模拟充电桩 {} 异常: {}，稍后重连", number, e);
+                }
+                time::sleep(Duration::from_secs(1)).await;
+            }
+        });
+    }
+
+    {
+        let config = config.clone();
+        let api = api.clone();
+        let stats = stats.clone();
+        tokio::spawn(async move { submit_requests(&config, &api, &stats).await });
+    }
+
+    match config.duration {
+        Some(duration) => time::sleep(duration).await,
+        None => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+
+    println!(
+        "📊 提交请求 {} 个（被拒 {} 个），完成充电 {} 次，故障 {} 次，累计电量 {:.1} 度",
+        stats.requests_submitted.load(Ordering::SeqCst),
+        stats.requests_rejected.load(Ordering::SeqCst),
+        stats.sessions_completed.load(Ordering::SeqCst),
+        stats.faults.load(Ordering::SeqCst),
+        stats.energy_wh.load(Ordering::SeqCst) as f64 / 1000.0
+    );
+}
+
+/// 获取 (充电桩编号, 模式) 列表
+async fn fetch_piles(api: &ApiClient) -> Result<Vec<(String, String)>, String> {
+    let response = api.get("/api/scheduler/piles").await?;
+    if !response.status().is_success() {
+        return Err(response.status().to_string());
+    }
+    let piles: Vec<Value> = response.json().await.map_err(|e| e.to_string())?;
+
+    let mut piles: Vec<(String, String)> = piles
+        .iter()
+        .filter_map(|p| Some((p["number"].as_str()?.to_string(), p["mode"].as_str()?.to_string())))
+        .collect();
+    piles.sort();
+    Ok(piles)
+}
+
+/// 查询排队号码对应的请求充电量（度），需要调度器控制权限
+async fn fetch_requested_amount(api: &ApiClient, queue_number: &str) -> Option<f64> {
+    let response: Value = api.get("/api/charging-requests").await.ok()?.json().await.ok()?;
+    response["data"]
+        .as_array()?
+        .iter()
+        .find(|r| r["queue_number"] == queue_number)?["amount"]
+        .as_f64()
+}
+
+/// 定期提交随机充电请求（记在登录账号名下）
+async fn submit_requests(config: &SimConfig, api: &ApiClient, stats: &SimStats) {
+    let mut interval = time::interval(config.request_interval);
+    loop {
+        interval.tick().await;
+        let (mode, amount) = {
+            let mut rng = rand::thread_rng();
+            let mode = if rng.gen_bool(0.5) { "Fast" } else { "Slow" };
+            (mode, rng.gen_range(5..=40) as f64)
+        };
+        let body = json!({ "mode": mode, "amount": amount });
+        let result = api.post("/api/charging-requests", Some(&body)).await;
+        match result {
+            Ok(response) if response.status().is_success() => {
+                stats.requests_submitted.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(_) => {
+                // 等候区已满等情况
+                stats.requests_rejected.fetch_add(1, Ordering::SeqCst);
+            }
+            Err(e) => println!("⚠️ 提交充电请求失败: {}", e),
+        }
+    }
+}
+
+/// 运行一个模拟充电桩直到连接断开
+async fn run_pile(
+    config: &SimConfig,
+    api: &ApiClient,
+    stats: &SimStats,
+    number: &str,
+    mode: &str,
+) -> Result<(), String> {
+    let power_kw = if mode == "Fast" {
+        FAST_CHARGING_POWER
+    } else {
+        SLOW_CHARGING_POWER
+    };
+    // 中央系统配置了充电桩密钥时，模拟器从同一环境变量 OCPP_PILE_SECRETS 取各桩的密钥
+    let credentials = ChargePointCredentials::from_env();
+    let mut charge_point = ChargePoint::connect(&config.ocpp_url, number, credentials.secret(number)).await?;
+    charge_point.boot_notification("Simulator", mode).await?;
+    charge_point.status_notification(ChargePointStatus::Available).await?;
+
+    let mut meter_wh: i32 = 0;
+    loop {
+        // 等待中央系统叫号
+        let call = match time::timeout(config.meter_interval * 10, charge_point.next_call()).await {
+            Ok(call) => call?,
+            Err(_) => {
+                charge_point.heartbeat().await?;
+                continue;
+            }
+        };
+        let (unique_id, action, payload) = call;
+        if action != "RemoteStartTransaction" {
+            charge_point.reply(&unique_id, json!({ "status": "Rejected" })).await?;
+            continue;
+        }
+        charge_point.reply(&unique_id, json!({ "status": "Accepted" })).await?;
+
+        let id_tag = payload["idTag"].as_str().unwrap_or_default().to_string();
+        let target_wh = fetch_requested_amount(api, &id_tag)
+            .await
+            .unwrap_or(10.0)
+            * 1000.0;
+
+        charge_point.status_notification(ChargePointStatus::Charging).await?;
+        let start = charge_point.start_transaction(&id_tag, meter_wh).await?;
+        let meter_start = meter_wh;
+
+        // 按上报间隔累计电量，可能随机发生故障
+        let step_wh = power_kw * 1000.0 * config.meter_interval.as_secs_f64() / 3600.0 * config.speedup;
+        let mut faulted = false;
+        while ((meter_wh - meter_start) as f64) < target_wh {
+            time::sleep(config.meter_interval).await;
+            let delivered = (meter_wh - meter_start) as f64;
+            meter_wh += step_wh.min(target_wh - delivered).ceil() as i32;
+            charge_point
+                .meter_values(start.transaction_id, meter_wh, power_kw * 1000.0)
+                .await?;
+
+            if rand::thread_rng().gen_bool(config.fault_rate) {
+                faulted = true;
+                break;
+            }
+        }
+
+        if faulted {
+            // 先上报故障，使排队车辆回到等候区，再结束当前事务按已充电量结算
+            stats.faults.fetch_add(1, Ordering::SeqCst);
+            println!("💥 模拟充电桩 {} 发生故障", number);
+            charge_point.status_notification(ChargePointStatus::Faulted).await?;
+            post_pile_event(api, number, "fault").await;
+        }
+
+        charge_point.stop_transaction(start.transaction_id, meter_wh).await?;
+        stats.energy_wh.fetch_add((meter_wh - meter_start) as usize, Ordering::SeqCst);
+
+        if faulted {
+            time::sleep(config.repair_time).await;
+
+            post_pile_event(api, number, "recovery").await;
+            charge_point.status_notification(ChargePointStatus::Available).await?;
+            println!("🔧 模拟充电桩 {} 已恢复", number);
+        } else {
+            // 车辆停留一段时间后拔枪
+            stats.sessions_completed.fetch_add(1, Ordering::SeqCst);
+            charge_point.status_notification(ChargePointStatus::Finishing).await?;
+            time::sleep(config.dwell_time).await;
+            charge_point.status_notification(ChargePointStatus::Available).await?;
+        }
+    }
+}
+
+/// 调用后端的充电桩故障/恢复接口，需要充电桩控制权限
+async fn post_pile_event(api: &ApiClient, number: &str, event: &str) {
+    let path = format!("/api/scheduler/piles/{}/{}", number, event);
+    match api.post(&path, None).await {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => println!("⚠️ 上报充电桩 {} {} 失败: {}", number, event, response.status()),
+        Err(e) => println!("⚠️ 上报充电桩 {} {} 失败: {}", number, event, e),
+    }
+}
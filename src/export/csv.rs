@@ -0,0 +1,65 @@
+use super::Cell;
+
+/// CSV 写出：UTF-8 带 BOM（便于 Excel 识别中文），行尾 CRLF
+#[derive(Debug, Default)]
+pub struct CsvWriter;
+
+impl CsvWriter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn begin(&mut self, headers: &[&str]) -> Vec<u8> {
+        let mut out = "\u{feff}".as_bytes().to_vec();
+        out.extend(Self::line(headers.iter().map(|h| escape(h))));
+        out
+    }
+
+    pub fn row(&mut self, cells: &[Cell]) -> Vec<u8> {
+        Self::line(cells.iter().map(|cell| match cell {
+            Cell::Text(text) => escape(&neutralize_formula(text)),
+            Cell::Number(number) => number.clone(),
+        }))
+    }
+
+    fn line(fields: impl Iterator<Item = String>) -> Vec<u8> {
+        let mut line = fields.collect::<Vec<_>>().join(",");
+        line.push_str("\r\n");
+        line.into_bytes()
+    }
+}
+
+/// 含逗号、引号或换行的字段加引号，引号加倍
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// 以公式字符开头的文本前加单引号，防止在表格软件中被当作公式执行
+fn neutralize_formula(text: &str) -> String {
+    if text.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaping() {
+        let mut writer = CsvWriter::new();
+        let row = writer.row(&[
+            Cell::text("say \"hi\""),
+            Cell::text("=SUM(A1)"),
+            Cell::Number("-1.12".to_string()),
+            Cell::text("两行\n文本"),
+        ]);
+        assert_eq!(String::from_utf8(row).unwrap(), "\"say \"\"hi\"\"\",'=SUM(A1),-1.12,\"两行\n文本\"\r\n");
+    }
+}
@@ -0,0 +1,237 @@
+mod csv;
+mod rows;
+mod xlsx;
+
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
+use std::str::FromStr;
+
+use crate::models::{decimal_from_f64, Money};
+
+pub use self::csv::CsvWriter;
+pub use self::xlsx::XlsxWriter;
+
+// 每次最多合并多少行已读出的数据写入一个响应块
+const EXPORT_BATCH_ROWS: usize = 256;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" | "CSV" => Ok(ExportFormat::Csv),
+            "xlsx" | "XLSX" => Ok(ExportFormat::Xlsx),
+            _ => Err(format!("不支持的导出格式: {}", s)),
+        }
+    }
+}
+
+/// 表头语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Locale {
+    /// 按 lang 参数或 Accept-Language 请求头选择，默认中文
+    pub fn negotiate(lang: Option<&str>, accept_language: Option<&str>) -> Self {
+        let preferred = lang.or(accept_language).unwrap_or("zh").trim().to_ascii_lowercase();
+        if preferred.starts_with("en") {
+            Locale::En
+        } else {
+            Locale::Zh
+        }
+    }
+}
+
+/// 导出列：各语言的表头
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    pub zh: &'static str,
+    pub en: &'static str,
+}
+
+impl Column {
+    pub const fn new(zh: &'static str, en: &'static str) -> Self {
+        Self { zh, en }
+    }
+
+    pub fn label(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::Zh => self.zh,
+            Locale::En => self.en,
+        }
+    }
+}
+
+/// 单元格：文本或数值（数值以十进制字符串保存，避免浮点误差）
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Number(String),
+}
+
+impl Cell {
+    pub fn text(value: impl ToString) -> Self {
+        Cell::Text(value.to_string())
+    }
+
+    pub fn money(value: &Money) -> Self {
+        Cell::Number(value.to_string())
+    }
+
+    /// 按指定小数位数输出浮点数
+    pub fn decimal(value: f64, scale: i64) -> Self {
+        Cell::Number(decimal_from_f64(value, scale).to_string())
+    }
+
+    pub fn integer(value: i64) -> Self {
+        Cell::Number(value.to_string())
+    }
+}
+
+/// 可导出的数据行
+pub trait ExportRow {
+    /// 表名，用作工作表名称
+    const TITLE: Column;
+    /// 下载文件名（不含扩展名）
+    const FILE_STEM: &'static str;
+
+    fn columns() -> &'static [Column];
+    fn cells(&self) -> Vec<Cell>;
+}
+
+/// 按格式写出表头、数据行和结尾
+pub enum ExportWriter {
+    Csv(CsvWriter),
+    Xlsx(XlsxWriter),
+}
+
+impl ExportWriter {
+    pub fn new(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Csv => ExportWriter::Csv(CsvWriter::new()),
+            ExportFormat::Xlsx => ExportWriter::Xlsx(XlsxWriter::new()),
+        }
+    }
+
+    pub fn begin(&mut self, sheet_name: &str, headers: &[&str]) -> Vec<u8> {
+        match self {
+            ExportWriter::Csv(writer) => writer.begin(headers),
+            ExportWriter::Xlsx(writer) => writer.begin(sheet_name, headers),
+        }
+    }
+
+    pub fn row(&mut self, cells: &[Cell]) -> Vec<u8> {
+        match self {
+            ExportWriter::Csv(writer) => writer.row(cells),
+            ExportWriter::Xlsx(writer) => writer.row(cells),
+        }
+    }
+
+    pub fn finish(&mut self) -> Vec<u8> {
+        match self {
+            ExportWriter::Csv(_) => Vec::new(),
+            ExportWriter::Xlsx(writer) => writer.finish(),
+        }
+    }
+}
+
+/// 将逐行读出的数据编码为导出文件的字节流：先输出表头，之后每批已到达的数据行输出一块，最后输出结尾
+///
+/// 读取出错时输出错误并结束，客户端收到的文件不完整。
+pub fn encode<T>(
+    format: ExportFormat,
+    locale: Locale,
+    rows: BoxStream<'static, Result<T, sqlx::Error>>,
+) -> impl Stream<Item = Result<Vec<u8>, sqlx::Error>>
+where
+    T: ExportRow + Send + 'static,
+{
+    let mut writer = ExportWriter::new(format);
+    let headers: Vec<&str> = T::columns().iter().map(|c| c.label(locale)).collect();
+    let header = writer.begin(T::TITLE.label(locale), &headers);
+
+    let body = stream::unfold(Some((writer, rows.ready_chunks(EXPORT_BATCH_ROWS))), |state| async move {
+        let (mut writer, mut rows) = state?;
+        match rows.next().await {
+            Some(batch) => {
+                let mut chunk = Vec::new();
+                for row in batch {
+                    match row {
+                        Ok(row) => chunk.extend(writer.row(&row.cells())),
+                        Err(e) => return Some((Err(e), None)),
+                    }
+                }
+                Some((Ok(chunk), Some((writer, rows))))
+            }
+            None => Some((Ok(writer.finish()), None)),
+        }
+    });
+    stream::once(async move { Ok(header) }).chain(body)
+}
+
+/// 将已在内存中的数据行（如报表）转为导出所需的行流
+pub fn from_rows<T: Send + 'static>(rows: Vec<T>) -> BoxStream<'static, Result<T, sqlx::Error>> {
+    stream::iter(rows.into_iter().map(Ok)).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sample(&'static str, f64);
+
+    impl ExportRow for Sample {
+        const TITLE: Column = Column::new("示例", "Sample");
+        const FILE_STEM: &'static str = "sample";
+
+        fn columns() -> &'static [Column] {
+            const COLUMNS: &[Column] = &[Column::new("名称", "Name"), Column::new("电量", "Energy")];
+            COLUMNS
+        }
+
+        fn cells(&self) -> Vec<Cell> {
+            vec![Cell::text(self.0), Cell::decimal(self.1, 3)]
+        }
+    }
+
+    #[test]
+    fn test_locale_negotiation() {
+        assert_eq!(Locale::negotiate(None, None), Locale::Zh);
+        assert_eq!(Locale::negotiate(None, Some("en-US,en;q=0.9")), Locale::En);
+        assert_eq!(Locale::negotiate(Some("zh"), Some("en-US")), Locale::Zh);
+    }
+
+    #[tokio::test]
+    async fn test_encode_csv_stream() {
+        let rows = from_rows(vec![Sample("F1", 30.0), Sample("快充, 2号", 7.5)]);
+        let chunks: Vec<_> = encode(ExportFormat::Csv, Locale::En, rows).collect().await;
+        let bytes: Vec<u8> = chunks.into_iter().flat_map(Result::unwrap).collect();
+        let text = String::from_utf8(bytes).unwrap();
+        assert_eq!(text, "\u{feff}Name,Energy\r\nF1,30.000\r\n\"快充, 2号\",7.500\r\n");
+    }
+}
@@ -0,0 +1,133 @@
+use super::{Cell, Column, ExportRow};
+use crate::billing::ConsolidatedBill;
+use crate::models::{ChargingRecord, ENERGY_SCALE};
+use crate::report::PileReportRow;
+
+// 充电时长保留的小数位数（小时）
+const HOURS_SCALE: i64 = 4;
+
+impl ExportRow for ChargingRecord {
+    const TITLE: Column = Column::new("充电详单", "Charging records");
+    const FILE_STEM: &'static str = "charging_records";
+
+    fn columns() -> &'static [Column] {
+        const COLUMNS: &[Column] = &[
+            Column::new("详单编号", "Record ID"),
+            Column::new("用户ID", "User ID"),
+            Column::new("充电桩", "Pile"),
+            Column::new("充电模式", "Mode"),
+            Column::new("充电量（度）", "Energy (kWh)"),
+            Column::new("充电时长（小时）", "Duration (h)"),
+            Column::new("电费（元）", "Energy fee (CNY)"),
+            Column::new("服务费（元）", "Service fee (CNY)"),
+            Column::new("服务费率（元/度）", "Service rate (CNY/kWh)"),
+            Column::new("占位费（元）", "Idle fee (CNY)"),
+            Column::new("优惠（元）", "Discount (CNY)"),
+            Column::new("总费用（元）", "Total (CNY)"),
+            Column::new("开始时间", "Start time"),
+            Column::new("结束时间", "End time"),
+            Column::new("生成时间", "Created at"),
+        ];
+        COLUMNS
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::text(self.id),
+            Cell::text(self.user_id),
+            Cell::text(&self.pile_id),
+            Cell::text(self.mode.to_string()),
+            Cell::decimal(self.charging_amount, ENERGY_SCALE),
+            Cell::decimal(self.charging_time, HOURS_SCALE),
+            Cell::money(&self.charging_fee),
+            Cell::money(&self.service_fee),
+            self.service_rate.as_ref().map_or(Cell::text(""), |rate| Cell::Number(rate.to_string())),
+            Cell::money(&self.idle_fee),
+            Cell::money(&self.discount_fee),
+            Cell::money(&self.total_fee),
+            Cell::text(self.start_time),
+            Cell::text(self.end_time),
+            Cell::text(self.created_at),
+        ]
+    }
+}
+
+impl ExportRow for ConsolidatedBill {
+    const TITLE: Column = Column::new("账单", "Bills");
+    const FILE_STEM: &'static str = "bills";
+
+    fn columns() -> &'static [Column] {
+        const COLUMNS: &[Column] = &[
+            Column::new("账单号", "Bill No."),
+            Column::new("用户ID", "User ID"),
+            Column::new("账单周期", "Period"),
+            Column::new("开始日期", "Period start"),
+            Column::new("结束日期（不含）", "Period end (exclusive)"),
+            Column::new("详单数", "Records"),
+            Column::new("总电量（度）", "Energy (kWh)"),
+            Column::new("电费（元）", "Energy fee (CNY)"),
+            Column::new("服务费（元）", "Service fee (CNY)"),
+            Column::new("占位费（元）", "Idle fee (CNY)"),
+            Column::new("优惠（元）", "Discount (CNY)"),
+            Column::new("总费用（元）", "Total (CNY)"),
+            Column::new("生成时间", "Created at"),
+        ];
+        COLUMNS
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::text(&self.bill_no),
+            Cell::text(self.user_id),
+            Cell::text(self.period.as_str()),
+            Cell::text(self.period_start),
+            Cell::text(self.period_end),
+            Cell::integer(self.record_count),
+            Cell::decimal(self.total_energy, ENERGY_SCALE),
+            Cell::money(&self.energy_fee),
+            Cell::money(&self.service_fee),
+            Cell::money(&self.idle_fee),
+            Cell::money(&self.discount_fee),
+            Cell::money(&self.total_fee),
+            Cell::text(self.created_at),
+        ]
+    }
+}
+
+impl ExportRow for PileReportRow {
+    const TITLE: Column = Column::new("充电桩运营报表", "Pile report");
+    const FILE_STEM: &'static str = "pile_report";
+
+    fn columns() -> &'static [Column] {
+        const COLUMNS: &[Column] = &[
+            Column::new("充电桩", "Pile"),
+            Column::new("充电次数", "Charges"),
+            Column::new("充电总时长（小时）", "Duration (h)"),
+            Column::new("总电量（度）", "Energy (kWh)"),
+            Column::new("电费（元）", "Energy fee (CNY)"),
+            Column::new("服务费（元）", "Service fee (CNY)"),
+            Column::new("占位费（元）", "Idle fee (CNY)"),
+            Column::new("优惠（元）", "Discount (CNY)"),
+            Column::new("总费用（元）", "Total (CNY)"),
+            Column::new("退款（元）", "Refunds (CNY)"),
+            Column::new("实收（元）", "Net (CNY)"),
+        ];
+        COLUMNS
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::text(&self.pile_id),
+            Cell::integer(self.charge_count),
+            Cell::decimal(self.charging_time, HOURS_SCALE),
+            Cell::decimal(self.charging_amount, ENERGY_SCALE),
+            Cell::money(&self.charging_fee),
+            Cell::money(&self.service_fee),
+            Cell::money(&self.idle_fee),
+            Cell::money(&self.discount_fee),
+            Cell::money(&self.total_fee),
+            Cell::money(&self.adjustment_fee),
+            Cell::money(&self.net_fee),
+        ]
+    }
+}
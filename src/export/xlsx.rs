@@ -0,0 +1,320 @@
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+use super::Cell;
+
+// XLSX 为 zip 包；工作表数据边生成边压缩输出，各条目的 CRC 和大小写在数据之后的描述符中
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+
+const SHEET_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#;
+
+const SHEET_END: &str = "</sheetData></worksheet>";
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP_VERSION: u16 = 20;
+const ZIP_FLAGS: u16 = 0x0808; // 数据后附描述符 + 文件名为 UTF-8
+const DEFLATE: u16 = 8;
+const DOS_DATE: u16 = (1 << 5) | 1; // 1980-01-01
+
+/// 已写完的 zip 条目，用于生成中央目录
+struct ZipEntry {
+    name: &'static str,
+    crc32: u32,
+    compressed_size: u32,
+    uncompressed_size: u32,
+    offset: u32,
+}
+
+/// 正在写入的 zip 条目
+struct OpenEntry {
+    name: &'static str,
+    offset: u32,
+    encoder: DeflateEncoder<Vec<u8>>,
+    hasher: crc32fast::Hasher,
+    compressed_size: u32,
+    uncompressed_size: u32,
+}
+
+/// 流式 XLSX 写出：单个工作表，首行为表头，文本使用内联字符串（单个文件不超过 4GB）
+pub struct XlsxWriter {
+    offset: u32,
+    entries: Vec<ZipEntry>,
+    current: Option<OpenEntry>,
+}
+
+impl Default for XlsxWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl XlsxWriter {
+    pub fn new() -> Self {
+        Self {
+            offset: 0,
+            entries: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// 写出包结构和工作表开头（含表头行）
+    pub fn begin(&mut self, sheet_name: &str, headers: &[&str]) -> Vec<u8> {
+        let workbook = format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            escape_xml(&sheet_name_safe(sheet_name))
+        );
+
+        let mut out = Vec::new();
+        for (name, content) in [
+            ("[Content_Types].xml", CONTENT_TYPES),
+            ("_rels/.rels", ROOT_RELS),
+            ("xl/workbook.xml", workbook.as_str()),
+            ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS),
+        ] {
+            out.extend(self.open_entry(name));
+            out.extend(self.write_entry(content.as_bytes()));
+            out.extend(self.close_entry());
+        }
+
+        out.extend(self.open_entry("xl/worksheets/sheet1.xml"));
+        out.extend(self.write_entry(SHEET_START.as_bytes()));
+        let header_cells: Vec<Cell> = headers.iter().map(Cell::text).collect();
+        out.extend(self.row(&header_cells));
+        out
+    }
+
+    /// 写出一行，返回本次新增的压缩数据
+    pub fn row(&mut self, cells: &[Cell]) -> Vec<u8> {
+        let mut xml = String::from("<row>");
+        for cell in cells {
+            match cell {
+                Cell::Text(text) => {
+                    xml.push_str(r#"<c t="inlineStr"><is><t xml:space="preserve">"#);
+                    xml.push_str(&escape_xml(text));
+                    xml.push_str("</t></is></c>");
+                }
+                Cell::Number(number) => {
+                    xml.push_str("<c><v>");
+                    xml.push_str(number);
+                    xml.push_str("</v></c>");
+                }
+            }
+        }
+        xml.push_str("</row>");
+        self.write_entry(xml.as_bytes())
+    }
+
+    /// 结束工作表并写出中央目录
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = self.write_entry(SHEET_END.as_bytes());
+        out.extend(self.close_entry());
+
+        let directory_offset = self.offset;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            put_u32(&mut directory, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut directory, ZIP_VERSION); // 创建版本
+            put_u16(&mut directory, ZIP_VERSION); // 解压所需版本
+            put_u16(&mut directory, ZIP_FLAGS);
+            put_u16(&mut directory, DEFLATE);
+            put_u16(&mut directory, 0); // 修改时间
+            put_u16(&mut directory, DOS_DATE);
+            put_u32(&mut directory, entry.crc32);
+            put_u32(&mut directory, entry.compressed_size);
+            put_u32(&mut directory, entry.uncompressed_size);
+            put_u16(&mut directory, entry.name.len() as u16);
+            put_u16(&mut directory, 0); // 扩展字段长度
+            put_u16(&mut directory, 0); // 注释长度
+            put_u16(&mut directory, 0); // 起始磁盘
+            put_u16(&mut directory, 0); // 内部属性
+            put_u32(&mut directory, 0); // 外部属性
+            put_u32(&mut directory, entry.offset);
+            directory.extend(entry.name.as_bytes());
+        }
+
+        let directory_size = directory.len() as u32;
+        put_u32(&mut directory, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut directory, 0); // 当前磁盘
+        put_u16(&mut directory, 0); // 中央目录起始磁盘
+        put_u16(&mut directory, self.entries.len() as u16);
+        put_u16(&mut directory, self.entries.len() as u16);
+        put_u32(&mut directory, directory_size);
+        put_u32(&mut directory, directory_offset);
+        put_u16(&mut directory, 0); // 注释长度
+
+        self.offset += directory.len() as u32;
+        out.extend(directory);
+        out
+    }
+
+    fn open_entry(&mut self, name: &'static str) -> Vec<u8> {
+        let mut header = Vec::new();
+        put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut header, ZIP_VERSION);
+        put_u16(&mut header, ZIP_FLAGS);
+        put_u16(&mut header, DEFLATE);
+        put_u16(&mut header, 0);
+        put_u16(&mut header, DOS_DATE);
+        put_u32(&mut header, 0); // CRC 和大小写在描述符中
+        put_u32(&mut header, 0);
+        put_u32(&mut header, 0);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 0);
+        header.extend(name.as_bytes());
+
+        self.current = Some(OpenEntry {
+            name,
+            offset: self.offset,
+            encoder: DeflateEncoder::new(Vec::new(), Compression::default()),
+            hasher: crc32fast::Hasher::new(),
+            compressed_size: 0,
+            uncompressed_size: 0,
+        });
+        self.offset += header.len() as u32;
+        header
+    }
+
+    fn write_entry(&mut self, data: &[u8]) -> Vec<u8> {
+        let Some(entry) = self.current.as_mut() else {
+            return Vec::new();
+        };
+        entry.hasher.update(data);
+        entry.uncompressed_size += data.len() as u32;
+        // 写入内存缓冲不会失败
+        entry.encoder.write_all(data).expect("写入压缩缓冲失败");
+        let compressed = std::mem::take(entry.encoder.get_mut());
+        entry.compressed_size += compressed.len() as u32;
+        self.offset += compressed.len() as u32;
+        compressed
+    }
+
+    fn close_entry(&mut self) -> Vec<u8> {
+        let Some(entry) = self.current.take() else {
+            return Vec::new();
+        };
+        let mut out = entry.encoder.finish().expect("写入压缩缓冲失败");
+        let compressed_size = entry.compressed_size + out.len() as u32;
+        let crc32 = entry.hasher.finalize();
+
+        put_u32(&mut out, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut out, crc32);
+        put_u32(&mut out, compressed_size);
+        put_u32(&mut out, entry.uncompressed_size);
+
+        self.offset += out.len() as u32;
+        self.entries.push(ZipEntry {
+            name: entry.name,
+            crc32,
+            compressed_size,
+            uncompressed_size: entry.uncompressed_size,
+            offset: entry.offset,
+        });
+        out
+    }
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_le_bytes());
+}
+
+/// 转义 XML 特殊字符，去掉 XML 不允许的控制字符
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 工作表名称不能含 []:*?/\ 且不超过 31 个字符
+fn sheet_name_safe(name: &str) -> String {
+    let name: String = name.chars().filter(|c| !"[]:*?/\\".contains(*c)).take(31).collect();
+    if name.is_empty() {
+        "Sheet1".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    fn u16_at(bytes: &[u8], at: usize) -> usize {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]]) as usize
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_zip_structure() {
+        let mut writer = XlsxWriter::new();
+        let mut bytes = writer.begin("充电详单", &["充电桩", "电量"]);
+        bytes.extend(writer.row(&[Cell::text("F1 <快充>"), Cell::Number("30.000".to_string())]));
+        bytes.extend(writer.finish());
+
+        // 中央目录记录的各条目可按偏移找到并解压，CRC 一致
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        let entry_count = u16_at(&bytes, end + 10);
+        let directory_offset = u32_at(&bytes, end + 16) as usize;
+        assert_eq!(entry_count, 5);
+        assert_eq!(directory_offset + u32_at(&bytes, end + 12) as usize, end);
+
+        let mut at = directory_offset;
+        let mut sheet = String::new();
+        for _ in 0..entry_count {
+            assert_eq!(u32_at(&bytes, at), CENTRAL_HEADER_SIGNATURE);
+            let crc32 = u32_at(&bytes, at + 16);
+            let compressed_size = u32_at(&bytes, at + 20) as usize;
+            let name_len = u16_at(&bytes, at + 28);
+            let local = u32_at(&bytes, at + 42) as usize;
+            let name = std::str::from_utf8(&bytes[at + 46..at + 46 + name_len]).unwrap().to_string();
+
+            assert_eq!(u32_at(&bytes, local), LOCAL_HEADER_SIGNATURE);
+            let data_start = local + 30 + u16_at(&bytes, local + 26);
+            let mut content = String::new();
+            DeflateDecoder::new(&bytes[data_start..data_start + compressed_size])
+                .read_to_string(&mut content)
+                .unwrap();
+            assert_eq!(crc32fast::hash(content.as_bytes()), crc32);
+            if name == "xl/worksheets/sheet1.xml" {
+                sheet = content;
+            }
+            at += 46 + name_len;
+        }
+
+        assert!(sheet.contains("<t xml:space=\"preserve\">F1 &lt;快充&gt;</t>"));
+        assert!(sheet.contains("<c><v>30.000</v></c>"));
+        assert!(sheet.ends_with("</sheetData></worksheet>"));
+    }
+}
@@ -1,6 +1,13 @@
 pub mod models;
 pub mod scheduler;
 pub mod billing;
+pub mod ocpp;
+pub mod payment;
+pub mod report;
+pub mod analytics;
+pub mod export;
+pub mod metrics;
+pub mod auth;
 
 use std::sync::atomic::{AtomicUsize, Ordering};
 
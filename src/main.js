@@ -1,4 +1,5 @@
 import { createApp } from 'vue'
+import axios from 'axios'
 import App from './App.vue'
 import router from './router'
 
@@ -27,6 +28,40 @@ echarts.use([
   LegendComponent
 ]);
 
+// 请求时附带访问令牌
+axios.interceptors.request.use(config => {
+  const token = localStorage.getItem('access_token');
+  if (token) {
+    config.headers.Authorization = `Bearer ${token}`;
+  }
+  return config;
+});
+
+// 访问令牌过期时用刷新令牌换取新令牌并重试一次，失败则回到登录页
+axios.interceptors.response.use(undefined, async error => {
+  const { config, response } = error;
+  const refreshToken = localStorage.getItem('refresh_token');
+  if (response?.status !== 401 || config._retried || /\/(login|refresh)$/.test(config.url)) {
+    return Promise.reject(error);
+  }
+  if (refreshToken) {
+    try {
+      const { data } = await axios.post('http://localhost:8080/refresh', { refresh_token: refreshToken });
+      localStorage.setItem('access_token', data.data.access_token);
+      localStorage.setItem('refresh_token', data.data.refresh_token);
+      config._retried = true;
+      return axios(config);
+    } catch (e) {
+      // 刷新令牌也已失效
+    }
+  }
+  localStorage.removeItem('user');
+  localStorage.removeItem('access_token');
+  localStorage.removeItem('refresh_token');
+  router.push('/');
+  return Promise.reject(error);
+});
+
 const app = createApp(App)
 app.use(router)
 app.use(ElementPlus)
@@ -1,8 +1,7 @@
 mod db;
-mod models;
 mod routes;
 
-use actix_web::{App, HttpServer, web};
+use actix_web::{middleware, App, HttpServer, web};
 use dotenv::dotenv;
 use actix_cors::Cors;
 use routes::user::user_routes;
@@ -12,7 +11,25 @@ use env_logger;
 use routes::scheduler_api;
 use routes::billing_api;
 use routes::charging_record_api;
+use routes::tariff_api;
+use routes::service_rate_api;
+use routes::wallet_api;
+use routes::payment_api;
+use routes::promotion_api;
+use routes::adjustment_api;
+use routes::consolidated_bill_api;
+use routes::report_api;
+use routes::export_api;
+use routes::analytics_api;
+use routes::metrics_api;
+use routes::audit_api::{self, TrustedProxies};
+use routes::auth::require_auth;
+use charging_station::auth::TokenSigner;
 use charging_station::scheduler::init_global_scheduler_with_db;
+use charging_station::ocpp::{CentralSystem, ChargePointCredentials};
+use charging_station::billing::TariffCalendar;
+use charging_station::payment::{MockPaymentProvider, PaymentService};
+use std::env;
 use std::sync::Arc;
 
 #[actix_web::main]
@@ -27,22 +44,81 @@ async fn main() -> std::io::Result<()> {
     let scheduler = init_global_scheduler_with_db(Arc::new(db_pool.clone()));
     scheduler.start().await.expect("Failed to start scheduler");
 
+    // 可选的超时占位模式：充电结束后车辆拔枪前充电桩保持占用并收取占位费
+    if env::var("OVERSTAY_ENABLED").is_ok_and(|v| v == "1" || v == "true") {
+        scheduler.queue_manager.set_overstay_enabled(true).await;
+    }
+
+    // 可选的电价日历：节假日、周末和季节使用各自的时段布局
+    if let Ok(path) = env::var("TARIFF_CALENDAR_FILE") {
+        match TariffCalendar::from_file(&path) {
+            Ok(calendar) => scheduler.queue_manager.set_tariff_calendar(calendar).await,
+            Err(e) => println!("⚠️ {}，不使用电价日历", e),
+        }
+    }
+
+    // 可选的支付渠道：设置后提交请求时预授权、充电完成后扣款，否则从预付费钱包扣费
+    if let Ok(provider) = env::var("PAYMENT_PROVIDER") {
+        match provider.as_str() {
+            "mock" => {
+                let secret = env::var("PAYMENT_WEBHOOK_SECRET").unwrap_or_else(|_| "mock-secret".to_string());
+                let payment_service = Arc::new(PaymentService::new(Arc::new(MockPaymentProvider::new(&secret))));
+                payment_service.set_db_pool(Arc::new(db_pool.clone())).await;
+                scheduler.queue_manager.set_payment_service(payment_service).await;
+            }
+            other => println!("⚠️ 不支持的支付渠道 {}，使用预付费钱包", other),
+        }
+    }
+
+    // 启动OCPP中央系统，供真实充电桩接入
+    // 充电桩以 HTTP Basic 认证接入，密钥来自环境变量 OCPP_PILE_SECRETS
+    let central_system = Arc::new(
+        CentralSystem::new(scheduler.queue_manager.clone()).with_credentials(ChargePointCredentials::from_env()),
+    );
+    scheduler.queue_manager.set_central_system(central_system.clone()).await;
+    let ocpp_addr = env::var("OCPP_BIND_ADDR").unwrap_or_else(|_| "127.0.0.1:9000".to_string());
+    central_system
+        .start(&ocpp_addr)
+        .await
+        .expect("Failed to start OCPP central system");
+
+    // 登录令牌签发：密钥和有效期来自环境变量
+    let token_signer = TokenSigner::from_env();
+    // 审计日志的来源 IP 只信任这些反向代理转发的 X-Forwarded-For
+    let trusted_proxies = TrustedProxies::from_env();
+
     HttpServer::new(move || {
         App::new()
+            // 认证在 CORS 之内，预检请求由 CORS 直接响应
+            .wrap(middleware::from_fn(require_auth))
             .wrap(Cors::default()
                 .allow_any_origin()
                 .allow_any_method()
                 .allow_any_header())
             .app_data(web::Data::new(db_pool.clone()))
             .app_data(web::Data::new(scheduler.clone()))
+            .app_data(web::Data::new(token_signer.clone()))
+            .app_data(web::Data::new(trusted_proxies.clone()))
             .configure(user_routes)
             .configure(pile_routes)
+            .configure(metrics_api::config)
             .service(
                 web::scope("/api")
                     .configure(charging_request_routes)
                     .configure(scheduler_api::config)
                     .configure(billing_api::config)
                     .configure(charging_record_api::config)
+                    .configure(tariff_api::config)
+                    .configure(service_rate_api::config)
+                    .configure(wallet_api::config)
+                    .configure(payment_api::config)
+                    .configure(promotion_api::config)
+                    .configure(adjustment_api::config)
+                    .configure(consolidated_bill_api::config)
+                    .configure(report_api::config)
+                    .configure(export_api::config)
+                    .configure(analytics_api::config)
+                    .configure(audit_api::config)
             )
     })
     .bind(("127.0.0.1", 8080))?
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard};
+
+/// 指标类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 标签值转义（Prometheus 文本格式）
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// 一组同名的计数器或仪表，按一个标签的取值区分；无标签时只有一个取值
+#[derive(Debug)]
+pub struct Family {
+    name: &'static str,
+    help: &'static str,
+    kind: Kind,
+    label: Option<&'static str>,
+    values: Mutex<BTreeMap<String, f64>>,
+}
+
+impl Family {
+    pub const fn counter(name: &'static str, help: &'static str, label: Option<&'static str>) -> Self {
+        Self { name, help, kind: Kind::Counter, label, values: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub const fn gauge(name: &'static str, help: &'static str, label: Option<&'static str>) -> Self {
+        Self { name, help, kind: Kind::Gauge, label, values: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// 计数器加一
+    pub fn inc(&self, label_value: &str) {
+        self.inc_by(label_value, 1.0);
+    }
+
+    /// 计数器累加（计数器只增不减，负数忽略）
+    pub fn inc_by(&self, label_value: &str, amount: f64) {
+        if self.kind == Kind::Counter && amount < 0.0 {
+            return;
+        }
+        *lock(&self.values).entry(label_value.to_string()).or_insert(0.0) += amount;
+    }
+
+    /// 设置仪表的当前值
+    pub fn set(&self, label_value: &str, value: f64) {
+        lock(&self.values).insert(label_value.to_string(), value);
+    }
+
+    /// 整体替换仪表的取值，已不存在的标签值随之移除
+    pub fn replace(&self, values: impl IntoIterator<Item = (String, f64)>) {
+        *lock(&self.values) = values.into_iter().collect();
+    }
+
+    pub fn get(&self, label_value: &str) -> f64 {
+        lock(&self.values).get(label_value).copied().unwrap_or(0.0)
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, self.kind.as_str());
+        let values = lock(&self.values);
+        match self.label {
+            Some(label) => {
+                for (label_value, value) in values.iter() {
+                    let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", self.name, label, escape_label(label_value), value);
+                }
+            }
+            None => {
+                let _ = writeln!(out, "{} {}", self.name, values.values().sum::<f64>());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HistogramState {
+    counts: Vec<u64>, // 各桶（含 +Inf）的非累计计数
+    sum: f64,
+    count: u64,
+}
+
+/// 直方图（无标签）
+#[derive(Debug)]
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    buckets: &'static [f64],
+    state: Mutex<HistogramState>,
+}
+
+impl Histogram {
+    pub const fn new(name: &'static str, help: &'static str, buckets: &'static [f64]) -> Self {
+        Self {
+            name,
+            help,
+            buckets,
+            state: Mutex::new(HistogramState { counts: Vec::new(), sum: 0.0, count: 0 }),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        let mut state = lock(&self.state);
+        if state.counts.is_empty() {
+            state.counts = vec![0; self.buckets.len() + 1];
+        }
+        let index = self.buckets.iter().position(|&bound| value <= bound).unwrap_or(self.buckets.len());
+        state.counts[index] += 1;
+        state.sum += value;
+        state.count += 1;
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} histogram", self.name);
+        let state = lock(&self.state);
+        let mut cumulative = 0;
+        for (i, bound) in self.buckets.iter().map(|b| b.to_string()).chain(["+Inf".to_string()]).enumerate() {
+            cumulative += state.counts.get(i).copied().unwrap_or(0);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", self.name, bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_sum {}", self.name, state.sum);
+        let _ = writeln!(out, "{}_count {}", self.name, state.count);
+    }
+}
+
+pub static WAITING_AREA_LENGTH: Family =
+    Family::gauge("chargesys_waiting_area_length", "等候区排队车辆数", Some("mode"));
+pub static PILE_QUEUE_LENGTH: Family =
+    Family::gauge("chargesys_pile_queue_length", "充电桩队列中等待的车辆数", Some("pile"));
+pub static PILES_BY_STATUS: Family = Family::gauge("chargesys_piles", "各状态的充电桩数", Some("status"));
+pub static SESSIONS_STARTED: Family =
+    Family::counter("chargesys_sessions_started_total", "开始的充电次数", Some("mode"));
+pub static SESSIONS_COMPLETED: Family =
+    Family::counter("chargesys_sessions_completed_total", "结算完成的充电次数", Some("mode"));
+pub static ENERGY_DELIVERED: Family =
+    Family::counter("chargesys_energy_delivered_kwh_total", "已结算的充电量（度）", Some("mode"));
+pub static REVENUE: Family = Family::counter("chargesys_revenue_yuan_total", "已结算的充电费用（元）", Some("mode"));
+pub static DB_ERRORS: Family = Family::counter("chargesys_db_errors_total", "数据库操作失败次数", Some("source"));
+
+pub static DISPATCH_LATENCY: Histogram = Histogram::new(
+    "chargesys_dispatch_latency_seconds",
+    "提交请求到调度至充电桩队列的时长（系统时间，秒）",
+    &[60.0, 300.0, 600.0, 1800.0, 3600.0, 7200.0, 14400.0],
+);
+pub static TICK_DURATION: Histogram = Histogram::new(
+    "chargesys_tick_duration_seconds",
+    "调度器每次 tick 的耗时（秒）",
+    &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0],
+);
+
+/// 记录一次数据库操作失败，source 为出错的模块或接口
+pub fn db_error(source: &str) {
+    DB_ERRORS.inc(source);
+}
+
+/// 以 Prometheus 文本格式输出全部指标
+pub fn render() -> String {
+    let mut out = String::new();
+    for family in [
+        &WAITING_AREA_LENGTH,
+        &PILE_QUEUE_LENGTH,
+        &PILES_BY_STATUS,
+        &SESSIONS_STARTED,
+        &SESSIONS_COMPLETED,
+        &ENERGY_DELIVERED,
+        &REVENUE,
+        &DB_ERRORS,
+    ] {
+        family.render(&mut out);
+    }
+    DISPATCH_LATENCY.render(&mut out);
+    TICK_DURATION.render(&mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_text_format() {
+        let family = Family::counter("test_total", "测试计数", Some("mode"));
+        family.inc("Fast");
+        family.inc_by("Fast", 2.5);
+        family.inc_by("Slow", -1.0);
+        family.inc("say \"hi\"");
+        let mut out = String::new();
+        family.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP test_total 测试计数\n# TYPE test_total counter\ntest_total{mode=\"Fast\"} 3.5\ntest_total{mode=\"say \\\"hi\\\"\"} 1\n"
+        );
+
+        let histogram = Histogram::new("test_seconds", "测试耗时", &[0.1, 1.0]);
+        histogram.observe(0.05);
+        histogram.observe(0.5);
+        histogram.observe(3.0);
+        let mut out = String::new();
+        histogram.render(&mut out);
+        assert!(out.contains("test_seconds_bucket{le=\"0.1\"} 1\n"));
+        assert!(out.contains("test_seconds_bucket{le=\"1\"} 2\n"));
+        assert!(out.contains("test_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.ends_with("test_seconds_sum 3.55\ntest_seconds_count 3\n"));
+    }
+}
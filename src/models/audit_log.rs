@@ -0,0 +1,301 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::{to_station_local, Role};
+
+/// 审计的管理操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    PileStart,
+    PileShutdown,
+    PileFault,
+    PileRecovery,
+    PileUnplug,
+    SchedulerStart,
+    SchedulerStop,
+    TariffCreate,
+    TariffUpdate,
+    TariffDelete,
+    ServiceRateSet,
+    ServiceRateDelete,
+    PromotionCreate,
+    PromotionUpdate,
+    CouponCreate,
+    BillGenerate,
+    Refund,
+    RequestUpdate, // 管理人员修改其他用户的充电请求（改模式即重新排队分配）
+    RequestCancel, // 管理人员取消其他用户的充电请求
+    WalletTopUp,   // 人工充值（不经支付渠道）
+    PasswordReset,
+    RoleAssign,
+}
+
+impl AuditAction {
+    const ALL: [AuditAction; 22] = [
+        AuditAction::PileStart,
+        AuditAction::PileShutdown,
+        AuditAction::PileFault,
+        AuditAction::PileRecovery,
+        AuditAction::PileUnplug,
+        AuditAction::SchedulerStart,
+        AuditAction::SchedulerStop,
+        AuditAction::TariffCreate,
+        AuditAction::TariffUpdate,
+        AuditAction::TariffDelete,
+        AuditAction::ServiceRateSet,
+        AuditAction::ServiceRateDelete,
+        AuditAction::PromotionCreate,
+        AuditAction::PromotionUpdate,
+        AuditAction::CouponCreate,
+        AuditAction::BillGenerate,
+        AuditAction::Refund,
+        AuditAction::RequestUpdate,
+        AuditAction::RequestCancel,
+        AuditAction::WalletTopUp,
+        AuditAction::PasswordReset,
+        AuditAction::RoleAssign,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::PileStart => "pile_start",
+            AuditAction::PileShutdown => "pile_shutdown",
+            AuditAction::PileFault => "pile_fault",
+            AuditAction::PileRecovery => "pile_recovery",
+            AuditAction::PileUnplug => "pile_unplug",
+            AuditAction::SchedulerStart => "scheduler_start",
+            AuditAction::SchedulerStop => "scheduler_stop",
+            AuditAction::TariffCreate => "tariff_create",
+            AuditAction::TariffUpdate => "tariff_update",
+            AuditAction::TariffDelete => "tariff_delete",
+            AuditAction::ServiceRateSet => "service_rate_set",
+            AuditAction::ServiceRateDelete => "service_rate_delete",
+            AuditAction::PromotionCreate => "promotion_create",
+            AuditAction::PromotionUpdate => "promotion_update",
+            AuditAction::CouponCreate => "coupon_create",
+            AuditAction::BillGenerate => "bill_generate",
+            AuditAction::Refund => "refund",
+            AuditAction::RequestUpdate => "request_update",
+            AuditAction::RequestCancel => "request_cancel",
+            AuditAction::WalletTopUp => "wallet_top_up",
+            AuditAction::PasswordReset => "password_reset",
+            AuditAction::RoleAssign => "role_assign",
+        }
+    }
+
+    /// 操作对象的类型，target_id 为充电桩编号、电价方案ID、充电详单ID等
+    pub fn target_type(&self) -> &'static str {
+        match self {
+            AuditAction::PileStart
+            | AuditAction::PileShutdown
+            | AuditAction::PileFault
+            | AuditAction::PileRecovery
+            | AuditAction::PileUnplug => "pile",
+            AuditAction::SchedulerStart | AuditAction::SchedulerStop => "scheduler",
+            AuditAction::TariffCreate | AuditAction::TariffUpdate | AuditAction::TariffDelete => "tariff",
+            AuditAction::ServiceRateSet | AuditAction::ServiceRateDelete => "service_rate",
+            AuditAction::PromotionCreate | AuditAction::PromotionUpdate | AuditAction::CouponCreate => "promotion",
+            AuditAction::BillGenerate => "bill_period",
+            AuditAction::Refund => "charging_record",
+            AuditAction::RequestUpdate | AuditAction::RequestCancel => "charging_request",
+            AuditAction::WalletTopUp | AuditAction::PasswordReset | AuditAction::RoleAssign => "user",
+        }
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.as_str() == s)
+            .ok_or_else(|| format!("不支持的审计操作: {}", s))
+    }
+}
+
+/// 管理操作审计日志（只追加，不修改不删除）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub actor_id: Uuid,            // 操作人
+    pub actor_role: Role,          // 操作时的角色
+    pub action: AuditAction,
+    pub target_type: String,
+    pub target_id: String,
+    pub before_value: Option<Value>, // 操作前的值
+    pub after_value: Option<Value>,  // 操作后的值
+    pub source_ip: Option<String>,
+    pub created_at: NaiveDateTime, // 充电站当地时间
+}
+
+/// 审计日志查询条件，时间为充电站当地时间
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogFilter {
+    pub actor_id: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub start_time: Option<NaiveDateTime>, // 不早于该时间
+    pub end_time: Option<NaiveDateTime>,   // 早于该时间
+}
+
+impl AuditLogFilter {
+    fn push_where(&self, builder: &mut sqlx::QueryBuilder<'_, sqlx::MySql>) {
+        builder.push(" WHERE 1 = 1");
+        if let Some(actor_id) = self.actor_id {
+            builder.push(" AND actor_id = ").push_bind(actor_id.as_bytes().to_vec());
+        }
+        if let Some(action) = self.action {
+            builder.push(" AND action = ").push_bind(action.as_str());
+        }
+        if let Some(target_type) = &self.target_type {
+            builder.push(" AND target_type = ").push_bind(target_type.clone());
+        }
+        if let Some(target_id) = &self.target_id {
+            builder.push(" AND target_id = ").push_bind(target_id.clone());
+        }
+        if let Some(start_time) = self.start_time {
+            builder.push(" AND created_at >= ").push_bind(start_time);
+        }
+        if let Some(end_time) = self.end_time {
+            builder.push(" AND created_at < ").push_bind(end_time);
+        }
+    }
+}
+
+impl AuditLog {
+    pub fn new(actor_id: Uuid, actor_role: Role, action: AuditAction, target_id: impl ToString) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            actor_id,
+            actor_role,
+            action,
+            target_type: action.target_type().to_string(),
+            target_id: target_id.to_string(),
+            before_value: None,
+            after_value: None,
+            source_ip: None,
+            created_at: to_station_local(Utc::now()),
+        }
+    }
+
+    /// 记录操作前的值，序列化为 null 时不记录
+    pub fn with_before(mut self, value: impl Serialize) -> Self {
+        self.before_value = serde_json::to_value(value).ok().filter(|v| !v.is_null());
+        self
+    }
+
+    pub fn with_after(mut self, value: impl Serialize) -> Self {
+        self.after_value = serde_json::to_value(value).ok().filter(|v| !v.is_null());
+        self
+    }
+
+    pub fn with_source_ip(mut self, source_ip: Option<String>) -> Self {
+        self.source_ip = source_ip;
+        self
+    }
+
+    pub async fn insert(&self, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_logs (id, actor_id, actor_role, action, target_type, target_id, before_value, after_value, source_ip, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(self.id.as_bytes().to_vec())
+        .bind(self.actor_id.as_bytes().to_vec())
+        .bind(self.actor_role.as_str())
+        .bind(self.action.as_str())
+        .bind(&self.target_type)
+        .bind(&self.target_id)
+        .bind(self.before_value.as_ref().map(Value::to_string))
+        .bind(self.after_value.as_ref().map(Value::to_string))
+        .bind(&self.source_ip)
+        .bind(self.created_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 按条件分页查询，最新的在前
+    pub async fn find(
+        filter: &AuditLogFilter,
+        limit: u32,
+        offset: u32,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let mut builder = sqlx::QueryBuilder::new(format!("SELECT {} FROM audit_logs", AUDIT_LOG_COLUMNS));
+        filter.push_where(&mut builder);
+        builder
+            .push(" ORDER BY created_at DESC, id LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let rows = builder.build().fetch_all(pool).await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let decode_uuid = |bytes: Vec<u8>| {
+            Uuid::from_slice(&bytes)
+                .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))
+        };
+        let decode_json = |text: Option<String>| {
+            text.map(|text| serde_json::from_str(&text))
+                .transpose()
+                .map_err(|e| sqlx::Error::Decode(format!("Failed to decode JSON: {}", e).into()))
+        };
+        Ok(AuditLog {
+            id: decode_uuid(row.get("id"))?,
+            actor_id: decode_uuid(row.get("actor_id"))?,
+            actor_role: Role::from_str(row.get("actor_role")).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            action: AuditAction::from_str(row.get("action")).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            target_type: row.get("target_type"),
+            target_id: row.get("target_id"),
+            before_value: decode_json(row.get("before_value"))?,
+            after_value: decode_json(row.get("after_value"))?,
+            source_ip: row.get("source_ip"),
+            created_at: row.get("created_at"),
+        })
+    }
+}
+
+const AUDIT_LOG_COLUMNS: &str =
+    "id, actor_id, actor_role, action, target_type, target_id, before_value, after_value, source_ip, created_at";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_round_trip() {
+        for action in AuditAction::ALL {
+            assert_eq!(action.as_str().parse::<AuditAction>(), Ok(action));
+            assert_eq!(serde_json::to_value(action).unwrap(), Value::from(action.as_str()));
+        }
+        assert!("pile_explode".parse::<AuditAction>().is_err());
+    }
+
+    #[test]
+    fn test_new_entry() {
+        let actor_id = Uuid::new_v4();
+        let entry = AuditLog::new(actor_id, Role::Operator, AuditAction::PileShutdown, "T2")
+            .with_before(serde_json::json!({ "status": "Available" }))
+            .with_after(serde_json::json!({ "status": "Shutdown" }))
+            .with_source_ip(Some("10.0.0.8".to_string()));
+        assert_eq!((entry.target_type.as_str(), entry.target_id.as_str()), ("pile", "T2"));
+        assert_eq!(entry.before_value.unwrap()["status"], "Available");
+        assert_eq!(entry.after_value.unwrap()["status"], "Shutdown");
+
+        // 没有操作前的值（如新建电价方案）时不记录 null
+        let entry = AuditLog::new(actor_id, Role::Finance, AuditAction::TariffCreate, 3).with_before(None::<i32>);
+        assert!(entry.before_value.is_none());
+    }
+}
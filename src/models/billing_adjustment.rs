@@ -0,0 +1,201 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+use uuid::Uuid;
+
+use super::{round_money, to_station_local, zero_money, Money, Wallet, WalletTransactionKind};
+
+/// 账单调整：管理员对某条充电详单的退款（只追加，不修改不删除，原详单保持不变）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BillingAdjustment {
+    pub id: Uuid,
+    pub record_id: Uuid,            // 调整的充电详单
+    pub user_id: Uuid,              // 详单所属用户
+    pub amount: Money,              // 退款金额（元）
+    pub reason: String,             // 调整原因
+    pub admin_id: Uuid,             // 操作管理员
+    pub created_at: NaiveDateTime,  // 充电站当地时间
+}
+
+impl BillingAdjustment {
+    /// 创建账单调整：校验操作人为管理员、累计退款不超过详单总费用，退款存入用户钱包
+    pub async fn create(
+        record_id: Uuid,
+        amount: &Money,
+        reason: &str,
+        admin_id: Uuid,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Self, AdjustmentError> {
+        let amount = round_money(amount);
+        if amount <= zero_money() {
+            return Err(AdjustmentError::Invalid("退款金额必须大于0".to_string()));
+        }
+        if reason.trim().is_empty() {
+            return Err(AdjustmentError::Invalid("调整原因不能为空".to_string()));
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let is_admin = sqlx::query("SELECT is_admin FROM users WHERE id = ?")
+            .bind(admin_id.as_bytes().to_vec())
+            .fetch_optional(&mut *tx)
+            .await?
+            .is_some_and(|row| row.get::<bool, _>("is_admin"));
+        if !is_admin {
+            return Err(AdjustmentError::Forbidden(format!("用户 {} 不是管理员", admin_id)));
+        }
+
+        // 锁定详单，保证并发调整时累计金额不超限
+        let record = sqlx::query("SELECT user_id, total_fee FROM charging_records WHERE id = ? FOR UPDATE")
+            .bind(record_id.as_bytes().to_vec())
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AdjustmentError::NotFound(format!("充电详单 {} 不存在", record_id)))?;
+        let user_id_bytes: Vec<u8> = record.get("user_id");
+        let user_id = Uuid::from_slice(&user_id_bytes)
+            .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))?;
+        let total_fee: Money = record.get("total_fee");
+
+        let adjusted: Money = sqlx::query(
+            "SELECT CAST(COALESCE(SUM(amount), 0) AS DECIMAL(14, 2)) AS adjusted FROM billing_adjustments WHERE record_id = ?",
+        )
+        .bind(record_id.as_bytes().to_vec())
+        .fetch_one(&mut *tx)
+        .await?
+        .get("adjusted");
+        if &adjusted + &amount > total_fee {
+            return Err(AdjustmentError::Invalid(format!(
+                "累计退款不能超过详单总费用 {}元（已退 {}元）",
+                total_fee, adjusted
+            )));
+        }
+
+        let adjustment = BillingAdjustment {
+            id: Uuid::new_v4(),
+            record_id,
+            user_id,
+            amount,
+            reason: reason.trim().to_string(),
+            admin_id,
+            created_at: to_station_local(Utc::now()),
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO billing_adjustments (id, record_id, user_id, amount, reason, admin_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(adjustment.id.as_bytes().to_vec())
+        .bind(record_id.as_bytes().to_vec())
+        .bind(user_id_bytes)
+        .bind(&adjustment.amount)
+        .bind(&adjustment.reason)
+        .bind(admin_id.as_bytes().to_vec())
+        .bind(adjustment.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        Wallet::apply(
+            &mut tx,
+            user_id,
+            WalletTransactionKind::Credit,
+            adjustment.amount.clone(),
+            Some(record_id),
+            "账单调整退款",
+        )
+        .await?;
+        tx.commit().await?;
+
+        println!(
+            "↩️ 管理员 {} 对详单 {} 退款 {}元，原因: {}",
+            admin_id, record_id, adjustment.amount, adjustment.reason
+        );
+        Ok(adjustment)
+    }
+
+    /// 查询某条详单的全部调整
+    pub async fn find_by_record_id(record_id: Uuid, pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM billing_adjustments WHERE record_id = ? ORDER BY created_at",
+            ADJUSTMENT_COLUMNS
+        ))
+        .bind(record_id.as_bytes().to_vec())
+        .fetch_all(pool)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// 查询用户的全部调整，最新的在前
+    pub async fn find_by_user_id(user_id: Uuid, pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM billing_adjustments WHERE user_id = ? ORDER BY created_at DESC",
+            ADJUSTMENT_COLUMNS
+        ))
+        .bind(user_id.as_bytes().to_vec())
+        .fetch_all(pool)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// 查询多条详单的累计调整金额，返回 (详单ID, 金额)
+    pub async fn totals_by_record_ids(
+        record_ids: &[Uuid],
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<(Uuid, Money)>, sqlx::Error> {
+        if record_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query_builder = sqlx::QueryBuilder::new(
+            "SELECT record_id, CAST(SUM(amount) AS DECIMAL(14, 2)) AS amount FROM billing_adjustments WHERE record_id IN (",
+        );
+        let mut separated = query_builder.separated(", ");
+        for id in record_ids {
+            separated.push_bind(id.as_bytes().to_vec());
+        }
+        query_builder.push(") GROUP BY record_id");
+
+        let rows = query_builder.build().fetch_all(pool).await?;
+        rows.iter()
+            .map(|row| {
+                let record_id_bytes: Vec<u8> = row.get("record_id");
+                let record_id = Uuid::from_slice(&record_id_bytes).map_err(|e| {
+                    sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
+                })?;
+                Ok((record_id, row.get("amount")))
+            })
+            .collect()
+    }
+
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let decode_uuid = |bytes: Vec<u8>| {
+            Uuid::from_slice(&bytes)
+                .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))
+        };
+        Ok(BillingAdjustment {
+            id: decode_uuid(row.get("id"))?,
+            record_id: decode_uuid(row.get("record_id"))?,
+            user_id: decode_uuid(row.get("user_id"))?,
+            amount: row.get("amount"),
+            reason: row.get("reason"),
+            admin_id: decode_uuid(row.get("admin_id"))?,
+            created_at: row.get("created_at"),
+        })
+    }
+}
+
+const ADJUSTMENT_COLUMNS: &str = "id, record_id, user_id, amount, reason, admin_id, created_at";
+
+/// 创建账单调整失败的原因
+#[derive(Debug, thiserror::Error)]
+pub enum AdjustmentError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("数据库错误: {0}")]
+    Database(#[from] sqlx::Error),
+}
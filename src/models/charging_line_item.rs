@@ -0,0 +1,139 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::Money;
+
+/// 明细类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineItemKind {
+    #[default]
+    Period,   // 电价时段
+    Discount, // 优惠减免（金额为负）
+}
+
+impl LineItemKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineItemKind::Period => "Period",
+            LineItemKind::Discount => "Discount",
+        }
+    }
+}
+
+impl FromStr for LineItemKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Period" => Ok(LineItemKind::Period),
+            "Discount" => Ok(LineItemKind::Discount),
+            _ => Err(format!("Invalid LineItemKind: {}", s)),
+        }
+    }
+}
+
+/// 充电详单明细：每个电价时段的电量、单价和小计，以及优惠减免
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargingLineItem {
+    #[serde(default)]
+    pub kind: LineItemKind,
+    pub period_name: String,        // 电价时段名称（优惠减免为促销名称）
+    pub start_time: NaiveDateTime,  // 时段内开始充电时间（充电站当地时间）
+    pub end_time: NaiveDateTime,    // 时段内结束充电时间（充电站当地时间）
+    pub energy_kwh: f64,            // 时段内充电量（度）
+    pub energy_rate: Money,         // 电价（元/度）
+    pub service_rate: Money,        // 服务费单价（元/度）
+    pub charging_fee: Money,        // 电费小计
+    pub service_fee: Money,         // 服务费小计
+    pub subtotal: Money,            // 小计
+}
+
+impl ChargingLineItem {
+    /// 在事务中保存某条详单的明细
+    pub async fn insert_for_record(
+        record_id: Uuid,
+        items: &[ChargingLineItem],
+        tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+    ) -> Result<(), sqlx::Error> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder = sqlx::QueryBuilder::new(
+            r#"
+            INSERT INTO charging_record_items (
+                record_id, seq, kind, period_name, start_time, end_time, energy_kwh,
+                energy_rate, service_rate, charging_fee, service_fee, subtotal
+            )
+            "#,
+        );
+        query_builder.push_values(items.iter().enumerate(), |mut b, (seq, item)| {
+            b.push_bind(record_id.as_bytes().to_vec())
+                .push_bind(seq as i32)
+                .push_bind(item.kind.as_str())
+                .push_bind(&item.period_name)
+                .push_bind(item.start_time)
+                .push_bind(item.end_time)
+                .push_bind(item.energy_kwh)
+                .push_bind(&item.energy_rate)
+                .push_bind(&item.service_rate)
+                .push_bind(&item.charging_fee)
+                .push_bind(&item.service_fee)
+                .push_bind(&item.subtotal);
+        });
+        query_builder.build().execute(&mut **tx).await?;
+        Ok(())
+    }
+
+    /// 查询多条详单的明细，返回 (详单ID, 明细)，同一详单内按时间顺序排列
+    pub async fn find_by_record_ids(
+        record_ids: &[Uuid],
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<(Uuid, ChargingLineItem)>, sqlx::Error> {
+        if record_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query_builder = sqlx::QueryBuilder::new(
+            r#"
+            SELECT record_id, kind, period_name, start_time, end_time, energy_kwh,
+                   energy_rate, service_rate, charging_fee, service_fee, subtotal
+            FROM charging_record_items
+            WHERE record_id IN (
+            "#,
+        );
+        let mut separated = query_builder.separated(", ");
+        for id in record_ids {
+            separated.push_bind(id.as_bytes().to_vec());
+        }
+        query_builder.push(") ORDER BY record_id, seq");
+
+        let rows = query_builder.build().fetch_all(pool).await?;
+        rows.iter()
+            .map(|row| {
+                let record_id_bytes: Vec<u8> = row.get("record_id");
+                let record_id = Uuid::from_slice(&record_id_bytes).map_err(|e| {
+                    sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
+                })?;
+                let kind: String = row.get("kind");
+                Ok((
+                    record_id,
+                    ChargingLineItem {
+                        kind: kind.parse().map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+                        period_name: row.get("period_name"),
+                        start_time: row.get("start_time"),
+                        end_time: row.get("end_time"),
+                        energy_kwh: row.get("energy_kwh"),
+                        energy_rate: row.get("energy_rate"),
+                        service_rate: row.get("service_rate"),
+                        charging_fee: row.get("charging_fee"),
+                        service_fee: row.get("service_fee"),
+                        subtotal: row.get("subtotal"),
+                    },
+                ))
+            })
+            .collect()
+    }
+}
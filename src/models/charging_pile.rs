@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use super::{FAST_CHARGING_POWER, SLOW_CHARGING_POWER};
+use super::{decimal_from_f64, zero_money, Money, FAST_CHARGING_POWER, MONEY_SCALE, SLOW_CHARGING_POWER};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::MySqlPool;
@@ -67,8 +67,8 @@ pub struct ChargingPile {
     pub total_charge_count: i32,                           // 累计充电次数
     pub total_charge_time: f64,                            // 累计充电时长（小时）
     pub total_charge_amount: f64,                          // 累计充电量（度）
-    pub total_charging_fee: f64,                           // 累计充电费用
-    pub total_service_fee: f64,                            // 累计服务费用
+    pub total_charging_fee: Money,                         // 累计充电费用
+    pub total_service_fee: Money,                          // 累计服务费用
     pub started_at: Option<chrono::DateTime<chrono::Utc>>, // 启动时间
 }
 
@@ -102,8 +102,8 @@ impl ChargingPile {
             total_charge_count: 0,
             total_charge_time: 0.0,
             total_charge_amount: 0.0,
-            total_charging_fee: 0.0,
-            total_service_fee: 0.0,
+            total_charging_fee: zero_money(),
+            total_service_fee: zero_money(),
             started_at: None,
         }
     }
@@ -175,8 +175,8 @@ impl ChargingPile {
                 self.total_charge_count += 1;
                 self.total_charge_time += charge_time;
                 self.total_charge_amount += charge_amount;
-                self.total_charging_fee += charge_amount * self.get_power();
-                self.total_service_fee += charge_amount * self.get_power() * charge_time;
+                self.total_charging_fee += &decimal_from_f64(charge_amount * self.get_power(), MONEY_SCALE);
+                self.total_service_fee += &decimal_from_f64(charge_amount * self.get_power() * charge_time, MONEY_SCALE);
                 Ok(())
             }
             _ => Err("充电桩不在充电状态".to_string()),
@@ -1,8 +1,16 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use super::ChargingMode;
+use crate::billing::Coupon;
+use super::{
+    to_station_local, zero_money, BillingAdjustment, ChargingLineItem, ChargingMode, Money, Wallet,
+    WalletTransactionKind,
+};
+use sqlx::mysql::MySqlRow;
 use sqlx::Row;
+use std::str::FromStr;
 use chrono::NaiveDateTime;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use tokio::sync::mpsc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChargingRecord {
@@ -12,155 +20,204 @@ pub struct ChargingRecord {
     pub mode: ChargingMode,         // 充电模式
     pub charging_amount: f64,       // 充电量（度）
     pub charging_time: f64,         // 充电时长（小时）
-    pub charging_fee: f64,          // 充电费用
-    pub service_fee: f64,           // 服务费用
-    pub total_fee: f64,             // 总费用
-    pub start_time: NaiveDateTime,  // 开始时间
-    pub end_time: NaiveDateTime,    // 结束时间
-    pub created_at: NaiveDateTime,  // 详单生成时间
+    pub charging_fee: Money,         // 充电费用
+    pub service_fee: Money,          // 服务费用
+    #[serde(default)]
+    pub service_rate: Option<Money>, // 按充电模式或充电桩设置的服务费率（元/度），为空表示按各时段的服务费率
+    pub idle_fee: Money,             // 超时占位费用
+    pub discount_fee: Money,         // 优惠减免
+    pub total_fee: Money,            // 总费用（已扣除优惠）
+    pub start_time: NaiveDateTime,  // 开始时间（充电站当地时间）
+    pub end_time: NaiveDateTime,    // 结束时间（充电站当地时间）
+    pub created_at: NaiveDateTime,  // 详单生成时间（充电站当地时间）
+    pub line_items: Vec<ChargingLineItem>, // 分时段明细和优惠减免
+    #[serde(default = "zero_money")]
+    pub adjustment_fee: Money,       // 管理员累计退款（不存于详单表）
+    #[serde(skip)]
+    pub coupon_code: Option<String>, // 本次减免所用的优惠券，写入详单时在同一事务中核销
+}
+
+/// 生成充电详单所需的充电信息和费用
+#[derive(Debug, Clone)]
+pub struct NewChargingRecord {
+    pub user_id: Uuid,
+    pub pile_id: String,
+    pub mode: ChargingMode,
+    pub charging_amount: f64, // 充电量（度）
+    pub charging_time: f64,   // 充电时长（小时）
+    pub charging_fee: Money,
+    pub service_fee: Money,
+    pub start_time: chrono::DateTime<chrono::Utc>,
+    pub end_time: chrono::DateTime<chrono::Utc>,
 }
 
 impl ChargingRecord {
-    pub fn new(
-        user_id: Uuid,
-        pile_id: String,
-        mode: ChargingMode,
-        charging_amount: f64,
-        charging_time: f64,
-        charging_fee: f64,
-        service_fee: f64,
-        start_time: chrono::DateTime<chrono::Utc>,
-        end_time: chrono::DateTime<chrono::Utc>,
-    ) -> Self {
+    pub fn new(record: NewChargingRecord) -> Self {
         Self {
             id: Uuid::new_v4(),
-            user_id,
-            pile_id,
-            mode,
-            charging_amount,
-            charging_time,
-            charging_fee,
-            service_fee,
-            total_fee: charging_fee + service_fee,
-            start_time: start_time.naive_utc(),
-            end_time: end_time.naive_utc(),
-            created_at: chrono::Utc::now().naive_utc(),
+            user_id: record.user_id,
+            pile_id: record.pile_id,
+            mode: record.mode,
+            charging_amount: record.charging_amount,
+            charging_time: record.charging_time,
+            total_fee: &record.charging_fee + &record.service_fee,
+            charging_fee: record.charging_fee,
+            service_fee: record.service_fee,
+            service_rate: None,
+            idle_fee: zero_money(),
+            discount_fee: zero_money(),
+            start_time: to_station_local(record.start_time),
+            end_time: to_station_local(record.end_time),
+            created_at: to_station_local(chrono::Utc::now()),
+            line_items: Vec::new(),
+            adjustment_fee: zero_money(),
+            coupon_code: None,
         }
     }
 
+    /// 扣除账单调整后的实收金额
+    pub fn net_fee(&self) -> Money {
+        &self.total_fee - &self.adjustment_fee
+    }
+
+    /// 附上分时段明细
+    pub fn with_line_items(mut self, line_items: Vec<ChargingLineItem>) -> Self {
+        self.line_items = line_items;
+        self
+    }
+
+    /// 记录实际使用的服务费率
+    pub fn with_service_rate(mut self, service_rate: Option<Money>) -> Self {
+        self.service_rate = service_rate;
+        self
+    }
+
+    /// 计入超时占位费用
+    pub fn with_idle_fee(mut self, idle_fee: Money) -> Self {
+        self.idle_fee = idle_fee;
+        self.update_total();
+        self
+    }
+
+    /// 记录本次减免所用的优惠券
+    pub fn with_coupon(mut self, coupon_code: Option<String>) -> Self {
+        self.coupon_code = coupon_code;
+        self
+    }
+
+    /// 计入优惠减免
+    pub fn with_discount(mut self, discount_fee: Money) -> Self {
+        self.discount_fee = discount_fee;
+        self.update_total();
+        self
+    }
+
+    fn update_total(&mut self) {
+        self.total_fee = &self.charging_fee + &self.service_fee + &self.idle_fee - &self.discount_fee;
+    }
+
+    /// 统计用户已有的充电详单数量
+    pub async fn count_by_user_id(user_id: Uuid, pool: &sqlx::MySqlPool) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query("SELECT COUNT(*) AS record_count FROM charging_records WHERE user_id = ?")
+            .bind(user_id.as_bytes().to_vec())
+            .fetch_one(pool)
+            .await?;
+        Ok(row.get("record_count"))
+    }
+
     // 根据 user_id 获取所有充电详单
     pub async fn find_by_user_id(user_id: Uuid, pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
         // 将 UUID 转换为字节数组用于查询
         let user_id_bytes = user_id.as_bytes().to_vec();
         
-        let rows = sqlx::query(
-            r#"
-            SELECT 
-                id, 
-                user_id, 
-                pile_id, 
-                mode, 
-                charging_amount, 
-                charging_time, 
-                charging_fee, 
-                service_fee, 
-                total_fee, 
-                start_time, 
-                end_time, 
-                created_at
-            FROM charging_records
-            WHERE user_id = ?
-            "#,
-        )
-        .bind(user_id_bytes)
-        .fetch_all(pool)
-        .await?;
-
-        let mut records = Vec::new();
-        for row in rows {
-            // 从字节数组转换回 UUID
-            let id_bytes: Vec<u8> = row.get("id");
-            let user_id_bytes: Vec<u8> = row.get("user_id");
-            
-            let id = Uuid::from_slice(&id_bytes).map_err(|e| {
-                sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
-            })?;
-            
-            let user_id = Uuid::from_slice(&user_id_bytes).map_err(|e| {
-                sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
-            })?;
-            
-            // 处理 ChargingMode 枚举
-            let mode_str: String = row.get("mode");
-            let mode = match mode_str.as_str() {
-                "Fast" => ChargingMode::Fast,
-                "Slow" => ChargingMode::Slow,
-                _ => return Err(sqlx::Error::Decode("Invalid charging mode".into())),
-            };
-
-            records.push(ChargingRecord {
-                id,
-                user_id,
-                pile_id: row.get("pile_id"),
-                mode,
-                charging_amount: row.get("charging_amount"),
-                charging_time: row.get("charging_time"),
-                charging_fee: row.get("charging_fee"),
-                service_fee: row.get("service_fee"),
-                total_fee: row.get("total_fee"),
-                start_time: row.get("start_time"),
-                end_time: row.get("end_time"),
-                created_at: row.get("created_at"),
-            });
-        }
+        let rows = sqlx::query(&format!("SELECT {} FROM charging_records WHERE user_id = ?", RECORD_COLUMNS))
+            .bind(user_id_bytes)
+            .fetch_all(pool)
+            .await?;
 
+        let mut records = rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?;
+        Self::load_line_items(&mut records, pool).await?;
         Ok(records)
     }
 
-    /// 插入充电详单到数据库
-    pub async fn insert(&self, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+    /// 插入充电详单到数据库；所用优惠券已被其他详单核销时不写入任何数据并返回 false
+    pub async fn insert(&self, pool: &sqlx::MySqlPool) -> Result<bool, sqlx::Error> {
+        self.insert_with(pool, true).await
+    }
+
+    /// 保存详单但不从钱包扣费（费用由支付渠道扣款）
+    pub async fn insert_without_wallet(&self, pool: &sqlx::MySqlPool) -> Result<bool, sqlx::Error> {
+        self.insert_with(pool, false).await
+    }
+
+    async fn insert_with(&self, pool: &sqlx::MySqlPool, deduct_wallet: bool) -> Result<bool, sqlx::Error> {
         // 将 UUID 转换为字节数组
         let id_bytes = self.id.as_bytes().to_vec();
         let user_id_bytes = self.user_id.as_bytes().to_vec();
         
         println!("🔍 准备插入充电详单: ID={}, 用户={}, 充电桩={}", self.id, self.user_id, self.pile_id);
         
-        let result = sqlx::query(
-            r#"
-            INSERT INTO charging_records (
-                id, 
-                user_id, 
-                pile_id, 
-                mode, 
-                charging_amount, 
-                charging_fee, 
-                service_fee, 
-                total_fee, 
-                start_time, 
-                end_time, 
-                created_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(id_bytes)
-        .bind(user_id_bytes)
-        .bind(&self.pile_id)
-        .bind(self.mode.to_string())
-        .bind(self.charging_amount)
-        .bind(self.charging_fee)
-        .bind(self.service_fee)
-        .bind(self.total_fee)
-        .bind(self.start_time)
-        .bind(self.end_time)
-        .bind(self.created_at)
-        .execute(pool)
+        let result: Result<bool, sqlx::Error> = async {
+            let mut tx = pool.begin().await?;
+            if let Some(code) = &self.coupon_code {
+                if !Coupon::redeem(code, self.user_id, self.id, &mut tx).await? {
+                    return Ok(false);
+                }
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO charging_records (
+                    id, 
+                    user_id, 
+                    pile_id, 
+                    mode, 
+                    charging_amount, 
+                    charging_fee, 
+                    service_fee, 
+                    service_rate, 
+                    idle_fee, 
+                    discount_fee, 
+                    total_fee, 
+                    start_time, 
+                    end_time, 
+                    created_at
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(id_bytes)
+            .bind(user_id_bytes)
+            .bind(&self.pile_id)
+            .bind(self.mode.to_string())
+            .bind(self.charging_amount)
+            .bind(&self.charging_fee)
+            .bind(&self.service_fee)
+            .bind(&self.service_rate)
+            .bind(&self.idle_fee)
+            .bind(&self.discount_fee)
+            .bind(&self.total_fee)
+            .bind(self.start_time)
+            .bind(self.end_time)
+            .bind(self.created_at)
+            .execute(&mut *tx)
+            .await?;
+            ChargingLineItem::insert_for_record(self.id, &self.line_items, &mut tx).await?;
+            if deduct_wallet {
+                self.deduct_from_wallet(&mut tx).await?;
+            }
+            tx.commit().await?;
+            Ok(true)
+        }
         .await;
 
         match result {
-            Ok(_) => {
+            Ok(false) => {
+                println!("⚠️ 优惠券 {} 已被使用，充电详单未保存", self.coupon_code.as_deref().unwrap_or_default());
+                Ok(false)
+            }
+            Ok(true) => {
                 println!("✅ 充电详单已保存到数据库: 用户 {}, 充电桩 {}, 充电量 {}度, 总费用 {}元", 
                     self.user_id, self.pile_id, self.charging_amount, self.total_fee);
-                Ok(())
+                Ok(true)
             }
             Err(e) => {
                 println!("❌ 充电详单保存失败: {}", e);
@@ -169,6 +226,13 @@ impl ChargingRecord {
         }
     }
 
+    /// 支付渠道扣款失败时改从用户钱包扣除总费用
+    pub async fn charge_wallet(&self, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        self.deduct_from_wallet(&mut tx).await?;
+        tx.commit().await
+    }
+
     /// 批量插入充电详单
     pub async fn insert_batch(records: &[ChargingRecord], pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
         if records.is_empty() {
@@ -179,7 +243,7 @@ impl ChargingRecord {
             r#"
             INSERT INTO charging_records (
                 id, user_id, pile_id, mode, charging_amount, 
-                charging_fee, service_fee, total_fee, start_time, end_time, created_at
+                charging_fee, service_fee, idle_fee, discount_fee, total_fee, start_time, end_time, created_at
             ) 
             "#
         );
@@ -190,19 +254,410 @@ impl ChargingRecord {
              .push_bind(&record.pile_id)
              .push_bind(record.mode.to_string())
              .push_bind(record.charging_amount)
-             .push_bind(record.charging_fee)
-             .push_bind(record.service_fee)
-             .push_bind(record.total_fee)
+             .push_bind(&record.charging_fee)
+             .push_bind(&record.service_fee)
+             .push_bind(&record.idle_fee)
+             .push_bind(&record.discount_fee)
+             .push_bind(&record.total_fee)
              .push_bind(record.start_time)
              .push_bind(record.end_time)
              .push_bind(record.created_at);
         });
 
+        let mut tx = pool.begin().await?;
         let query = query_builder.build();
-        query.execute(pool).await?;
+        query.execute(&mut *tx).await?;
+        for record in records {
+            ChargingLineItem::insert_for_record(record.id, &record.line_items, &mut tx).await?;
+            record.deduct_from_wallet(&mut tx).await?;
+        }
+        tx.commit().await?;
 
         println!("✅ 批量保存 {} 条充电详单到数据库", records.len());
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// 从用户钱包扣除本次总费用，与详单在同一事务中写入
+    async fn deduct_from_wallet(&self, tx: &mut sqlx::Transaction<'_, sqlx::MySql>) -> Result<(), sqlx::Error> {
+        if self.total_fee == zero_money() {
+            return Ok(());
+        }
+        Wallet::apply(
+            tx,
+            self.user_id,
+            WalletTransactionKind::Charge,
+            -self.total_fee.clone(),
+            Some(self.id),
+            "充电扣费",
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// 按条件分页查询充电详单
+    pub async fn find(query: &ChargingRecordQuery, pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let mut builder = sqlx::QueryBuilder::new(format!("SELECT {} FROM charging_records", RECORD_COLUMNS));
+        query.filter.push_where(&mut builder);
+        builder
+            .push(format!(
+                " ORDER BY {} {}, id",
+                query.sort_by.column(),
+                if query.descending { "DESC" } else { "ASC" }
+            ))
+            .push(" LIMIT ")
+            .push_bind(query.limit)
+            .push(" OFFSET ")
+            .push_bind(query.offset);
+
+        let rows = builder.build().fetch_all(pool).await?;
+        let mut records = rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?;
+        Self::load_line_items(&mut records, pool).await?;
+        Ok(records)
+    }
+
+    /// 逐行读取符合条件的详单（不含分时段明细），按开始时间排序，用于导出
+    ///
+    /// 查询在后台任务中进行，读出的详单经有界通道交给调用方，调用方停止读取时查询随之结束。
+    pub fn stream(filter: ChargingRecordFilter, pool: sqlx::MySqlPool) -> BoxStream<'static, Result<Self, sqlx::Error>> {
+        let (tx, rx) = mpsc::channel(EXPORT_CHANNEL_SIZE);
+        tokio::spawn(async move {
+            let mut builder = sqlx::QueryBuilder::new(format!("SELECT {} FROM charging_records", RECORD_COLUMNS));
+            filter.push_where(&mut builder);
+            builder.push(" ORDER BY start_time, id");
+
+            let mut rows = builder.build().fetch(&pool);
+            while let Some(row) = rows.next().await {
+                let record = row.and_then(|row| Self::from_row(&row));
+                if tx.send(record).await.is_err() {
+                    break;
+                }
+            }
+        });
+        receiver_stream(rx)
+    }
+
+    /// 按ID查询多条详单，按开始时间排序
+    pub async fn find_by_ids(ids: &[Uuid], pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut builder = sqlx::QueryBuilder::new(format!("SELECT {} FROM charging_records WHERE id IN (", RECORD_COLUMNS));
+        let mut separated = builder.separated(", ");
+        for id in ids {
+            separated.push_bind(id.as_bytes().to_vec());
+        }
+        builder.push(") ORDER BY start_time, id");
+
+        let rows = builder.build().fetch_all(pool).await?;
+        let mut records = rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?;
+        Self::load_line_items(&mut records, pool).await?;
+        Ok(records)
+    }
+
+    /// 查询并附上各详单的分时段明细和累计调整金额
+    async fn load_line_items(records: &mut [Self], pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        let ids: Vec<Uuid> = records.iter().map(|r| r.id).collect();
+        for (record_id, item) in ChargingLineItem::find_by_record_ids(&ids, pool).await? {
+            if let Some(record) = records.iter_mut().find(|r| r.id == record_id) {
+                record.line_items.push(item);
+            }
+        }
+        for (record_id, amount) in BillingAdjustment::totals_by_record_ids(&ids, pool).await? {
+            if let Some(record) = records.iter_mut().find(|r| r.id == record_id) {
+                record.adjustment_fee = amount;
+            }
+        }
+        Ok(())
+    }
+
+    /// 统计符合条件的充电详单数量和金额合计
+    pub async fn summarize(filter: &ChargingRecordFilter, pool: &sqlx::MySqlPool) -> Result<ChargingRecordTotals, sqlx::Error> {
+        let mut builder = sqlx::QueryBuilder::new(format!("SELECT {}", TOTALS_COLUMNS));
+        filter.push_where(&mut builder);
+
+        let row = builder.build().fetch_one(pool).await?;
+        Ok(ChargingRecordTotals::from_row(&row))
+    }
+
+    /// 按充电桩分组统计符合条件的充电详单，按充电桩编号排序
+    pub async fn summarize_by_pile(
+        filter: &ChargingRecordFilter,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<(String, ChargingRecordTotals)>, sqlx::Error> {
+        let mut builder = sqlx::QueryBuilder::new(format!("SELECT pile_id, {}", TOTALS_COLUMNS));
+        filter.push_where(&mut builder);
+        builder.push(" GROUP BY pile_id ORDER BY pile_id");
+
+        let rows = builder.build().fetch_all(pool).await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("pile_id"), ChargingRecordTotals::from_row(row)))
+            .collect())
+    }
+
+    /// 查询与 [start, end) 有交集的充电时段 (充电桩, 开始时间, 结束时间)
+    pub async fn charging_intervals(
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<(String, NaiveDateTime, NaiveDateTime)>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT pile_id, start_time, end_time FROM charging_records
+            WHERE start_time < ? AND end_time > ?
+            ORDER BY pile_id, start_time
+            "#,
+        )
+        .bind(end)
+        .bind(start)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("pile_id"), row.get("start_time"), row.get("end_time")))
+            .collect())
+    }
+
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        // 从字节数组转换回 UUID
+        let id_bytes: Vec<u8> = row.get("id");
+        let user_id_bytes: Vec<u8> = row.get("user_id");
+
+        let id = Uuid::from_slice(&id_bytes).map_err(|e| {
+            sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
+        })?;
+
+        let user_id = Uuid::from_slice(&user_id_bytes).map_err(|e| {
+            sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
+        })?;
+
+        // 处理 ChargingMode 枚举
+        let mode_str: String = row.get("mode");
+        let mode = match mode_str.as_str() {
+            "Fast" => ChargingMode::Fast,
+            "Slow" => ChargingMode::Slow,
+            _ => return Err(sqlx::Error::Decode("Invalid charging mode".into())),
+        };
+
+        Ok(ChargingRecord {
+            id,
+            user_id,
+            pile_id: row.get("pile_id"),
+            mode,
+            charging_amount: row.get("charging_amount"),
+            charging_time: row.get("charging_time"),
+            charging_fee: row.get("charging_fee"),
+            service_fee: row.get("service_fee"),
+            service_rate: row.get("service_rate"),
+            idle_fee: row.get("idle_fee"),
+            discount_fee: row.get("discount_fee"),
+            total_fee: row.get("total_fee"),
+            start_time: row.get("start_time"),
+            end_time: row.get("end_time"),
+            created_at: row.get("created_at"),
+            line_items: Vec::new(),
+            adjustment_fee: zero_money(),
+            coupon_code: None,
+        })
+    }
+}
+
+// 导出时后台查询最多预读的行数
+pub(crate) const EXPORT_CHANNEL_SIZE: usize = 256;
+
+/// 将通道接收端转为行流
+pub(crate) fn receiver_stream<T: Send + 'static>(rx: mpsc::Receiver<T>) -> BoxStream<'static, T> {
+    stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|item| (item, rx)) }).boxed()
+}
+
+// 合计列，详单表关联各详单的累计调整金额
+const TOTALS_COLUMNS: &str = r#"
+    COUNT(*) AS record_count,
+    CAST(COALESCE(SUM(charging_amount), 0) AS DOUBLE) AS charging_amount,
+    CAST(COALESCE(SUM(charging_time), 0) AS DOUBLE) AS charging_time,
+    CAST(COALESCE(SUM(charging_fee), 0) AS DECIMAL(14, 2)) AS charging_fee,
+    CAST(COALESCE(SUM(service_fee), 0) AS DECIMAL(14, 2)) AS service_fee,
+    CAST(COALESCE(SUM(idle_fee), 0) AS DECIMAL(14, 2)) AS idle_fee,
+    CAST(COALESCE(SUM(discount_fee), 0) AS DECIMAL(14, 2)) AS discount_fee,
+    CAST(COALESCE(SUM(total_fee), 0) AS DECIMAL(14, 2)) AS total_fee,
+    CAST(COALESCE(SUM(adj.adjusted), 0) AS DECIMAL(14, 2)) AS adjustment_fee
+FROM charging_records
+LEFT JOIN (
+    SELECT record_id, SUM(amount) AS adjusted FROM billing_adjustments GROUP BY record_id
+) adj ON adj.record_id = charging_records.id
+"#;
+
+const RECORD_COLUMNS: &str = "id, user_id, pile_id, mode, charging_amount, charging_time, \
+    charging_fee, service_fee, service_rate, idle_fee, discount_fee, total_fee, start_time, end_time, created_at";
+
+/// 充电详单查询条件，时间为充电站当地时间
+#[derive(Debug, Clone, Default)]
+pub struct ChargingRecordFilter {
+    pub user_id: Option<Uuid>,
+    pub pile_id: Option<String>,
+    pub start_time: Option<NaiveDateTime>, // 充电开始时间不早于该时间
+    pub end_time: Option<NaiveDateTime>,   // 充电开始时间早于该时间
+}
+
+impl ChargingRecordFilter {
+    fn push_where(&self, builder: &mut sqlx::QueryBuilder<'_, sqlx::MySql>) {
+        builder.push(" WHERE 1 = 1");
+        if let Some(user_id) = self.user_id {
+            builder.push(" AND user_id = ").push_bind(user_id.as_bytes().to_vec());
+        }
+        if let Some(pile_id) = &self.pile_id {
+            builder.push(" AND pile_id = ").push_bind(pile_id.clone());
+        }
+        if let Some(start_time) = self.start_time {
+            builder.push(" AND start_time >= ").push_bind(start_time);
+        }
+        if let Some(end_time) = self.end_time {
+            builder.push(" AND start_time < ").push_bind(end_time);
+        }
+    }
+}
+
+/// 充电详单排序字段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecordSortField {
+    #[default]
+    StartTime,
+    EndTime,
+    ChargingAmount,
+    TotalFee,
+    CreatedAt,
+}
+
+impl RecordSortField {
+    fn column(&self) -> &'static str {
+        match self {
+            RecordSortField::StartTime => "start_time",
+            RecordSortField::EndTime => "end_time",
+            RecordSortField::ChargingAmount => "charging_amount",
+            RecordSortField::TotalFee => "total_fee",
+            RecordSortField::CreatedAt => "created_at",
+        }
+    }
+}
+
+impl FromStr for RecordSortField {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start_time" => Ok(RecordSortField::StartTime),
+            "end_time" => Ok(RecordSortField::EndTime),
+            "charging_amount" => Ok(RecordSortField::ChargingAmount),
+            "total_fee" => Ok(RecordSortField::TotalFee),
+            "created_at" => Ok(RecordSortField::CreatedAt),
+            _ => Err(format!("不支持的排序字段: {}", s)),
+        }
+    }
+}
+
+/// 充电详单分页查询
+#[derive(Debug, Clone)]
+pub struct ChargingRecordQuery {
+    pub filter: ChargingRecordFilter,
+    pub sort_by: RecordSortField,
+    pub descending: bool,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// 充电详单合计
+#[derive(Debug, Clone, Serialize)]
+pub struct ChargingRecordTotals {
+    pub record_count: i64,
+    pub charging_amount: f64,
+    pub charging_time: f64,
+    pub charging_fee: Money,
+    pub service_fee: Money,
+    pub idle_fee: Money,
+    pub discount_fee: Money,
+    pub total_fee: Money,
+    pub adjustment_fee: Money, // 管理员累计退款
+}
+
+impl ChargingRecordTotals {
+    /// 没有详单时的合计
+    pub fn empty() -> Self {
+        Self {
+            record_count: 0,
+            charging_amount: 0.0,
+            charging_time: 0.0,
+            charging_fee: zero_money(),
+            service_fee: zero_money(),
+            idle_fee: zero_money(),
+            discount_fee: zero_money(),
+            total_fee: zero_money(),
+            adjustment_fee: zero_money(),
+        }
+    }
+
+    fn from_row(row: &MySqlRow) -> Self {
+        Self {
+            record_count: row.get("record_count"),
+            charging_amount: row.get("charging_amount"),
+            charging_time: row.get("charging_time"),
+            charging_fee: row.get("charging_fee"),
+            service_fee: row.get("service_fee"),
+            idle_fee: row.get("idle_fee"),
+            discount_fee: row.get("discount_fee"),
+            total_fee: row.get("total_fee"),
+            adjustment_fee: row.get("adjustment_fee"),
+        }
+    }
+
+    /// 扣除账单调整后的实收金额
+    pub fn net_fee(&self) -> Money {
+        &self.total_fee - &self.adjustment_fee
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_row_columns_match_record_columns() {
+        // from_row 读取的列必须都在 RECORD_COLUMNS 中，否则 Row::get 在查询时 panic
+        let source = include_str!("charging_record.rs");
+        let body = &source[source.find("fn from_row(").unwrap()..];
+        let body = &body[..body.find("\n    }").unwrap()];
+        let mut read: Vec<&str> = body
+            .split("row.get(\"")
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        let mut columns: Vec<&str> = RECORD_COLUMNS.split(',').map(str::trim).collect();
+        read.sort_unstable();
+        columns.sort_unstable();
+        assert_eq!(read, columns);
+    }
+
+    #[test]
+    fn test_total_and_net_fee() {
+        let yuan = |s: &str| -> Money { s.parse().unwrap() };
+        let now = chrono::Utc::now();
+        let mut record = ChargingRecord::new(NewChargingRecord {
+            user_id: Uuid::new_v4(),
+            pile_id: "F1".to_string(),
+            mode: ChargingMode::Fast,
+            charging_amount: 30.0,
+            charging_time: 1.0,
+            charging_fee: yuan("30.00"),
+            service_fee: yuan("24.00"),
+            start_time: now,
+            end_time: now,
+        })
+        .with_idle_fee(yuan("5.00"))
+        .with_discount(yuan("4.80"));
+        assert_eq!(record.total_fee, yuan("54.20"));
+
+        // 账单调整不改变详单金额，只影响实收金额
+        record.adjustment_fee = yuan("10.00");
+        assert_eq!(record.total_fee, yuan("54.20"));
+        assert_eq!(record.net_fee(), yuan("44.20"));
+    }
+}
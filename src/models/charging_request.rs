@@ -1,5 +1,6 @@
 use crate::models::ChargingMode;
 use crate::models::RequestStatus;
+use crate::models::RequestTimeline;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sqlx::types::chrono::DateTime;
@@ -17,6 +18,12 @@ pub struct ChargingRequest {
     pub status: String,            // 请求状态
     pub created_at: DateTime<Utc>, // 创建时间
     pub updated_at: DateTime<Utc>, // 更新时间
+    #[serde(default)]
+    #[sqlx(default)]
+    pub coupon_code: Option<String>, // 优惠券码，充电结算时核销
+    #[serde(default)]
+    #[sqlx(skip)]
+    pub timeline: RequestTimeline, // 调度各环节时间点
 }
 
 impl ChargingRequest {
@@ -31,9 +38,17 @@ impl ChargingRequest {
             status: RequestStatus::Waiting.to_string(),
             created_at: now,
             updated_at: now,
+            coupon_code: None,
+            timeline: RequestTimeline::default(),
         }
     }
 
+    /// 附带优惠券
+    pub fn with_coupon(mut self, coupon_code: Option<String>) -> Self {
+        self.coupon_code = coupon_code.filter(|code| !code.trim().is_empty());
+        self
+    }
+
     /// 开始充电
     pub fn start_charging(&mut self) -> Result<(), String> {
         let status = RequestStatus::from_str(&self.status)?;
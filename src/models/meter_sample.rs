@@ -0,0 +1,97 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+/// 电表采样（充电过程中的一个计量点）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeterSample {
+    pub id: Uuid,                  // 采样ID
+    pub record_id: Uuid,           // 所属充电详单ID（与充电请求ID一致）
+    pub pile_id: String,           // 充电桩编号
+    pub timestamp: NaiveDateTime,  // 采样时间（UTC）
+    pub energy_kwh: f64,           // 本次充电累计电量（度）
+    pub power_kw: f64,             // 瞬时功率（千瓦）
+}
+
+impl MeterSample {
+    pub fn new(
+        record_id: Uuid,
+        pile_id: String,
+        timestamp: DateTime<Utc>,
+        energy_kwh: f64,
+        power_kw: f64,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            record_id,
+            pile_id,
+            timestamp: timestamp.naive_utc(),
+            energy_kwh,
+            power_kw,
+        }
+    }
+
+    /// 转换为计费用的 (时间, 累计电量) 曲线点
+    pub fn curve_point(&self) -> (DateTime<Utc>, f64) {
+        (self.timestamp.and_utc(), self.energy_kwh)
+    }
+
+    /// 插入电表采样到数据库
+    pub async fn insert(&self, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO meter_values (id, record_id, pile_id, timestamp, energy_kwh, power_kw)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(self.id.as_bytes().to_vec())
+        .bind(self.record_id.as_bytes().to_vec())
+        .bind(&self.pile_id)
+        .bind(self.timestamp)
+        .bind(self.energy_kwh)
+        .bind(self.power_kw)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 获取某次充电的全部采样，按时间排序
+    pub async fn find_by_record_id(record_id: Uuid, pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, record_id, pile_id, timestamp, energy_kwh, power_kw
+            FROM meter_values
+            WHERE record_id = ?
+            ORDER BY timestamp ASC
+            "#,
+        )
+        .bind(record_id.as_bytes().to_vec())
+        .fetch_all(pool)
+        .await?;
+
+        let mut samples = Vec::new();
+        for row in rows {
+            let id_bytes: Vec<u8> = row.get("id");
+            let record_id_bytes: Vec<u8> = row.get("record_id");
+
+            let id = Uuid::from_slice(&id_bytes).map_err(|e| {
+                sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
+            })?;
+            let record_id = Uuid::from_slice(&record_id_bytes).map_err(|e| {
+                sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into())
+            })?;
+
+            samples.push(MeterSample {
+                id,
+                record_id,
+                pile_id: row.get("pile_id"),
+                timestamp: row.get("timestamp"),
+                energy_kwh: row.get("energy_kwh"),
+                power_kw: row.get("power_kw"),
+            });
+        }
+
+        Ok(samples)
+    }
+}
@@ -1,27 +1,35 @@
 pub mod charging_pile;
+mod audit_log;
+mod billing_adjustment;
+mod charging_line_item;
 mod charging_record;
 mod charging_request;
+mod meter_sample;
+mod money;
+mod request_timeline;
+mod station_time;
 pub mod user;
 mod vehicle;
+mod wallet;
 
 use std::str::FromStr;
 
 pub use self::charging_pile::{ChargingMode, ChargingPile, PileStatus};
+pub use audit_log::*;
+pub use billing_adjustment::*;
+pub use charging_line_item::*;
 pub use charging_record::*;
 pub use charging_request::*;
+pub use meter_sample::*;
+pub use money::*;
+pub use request_timeline::*;
+pub use station_time::*;
 pub use user::*;
 pub use vehicle::*;
+pub use wallet::*;
 
 use serde::{Deserialize, Serialize};
 
-// 时段类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TimeSlotType {
-    Peak,   // 峰时 10:00-15:00, 18:00-21:00
-    Normal, // 平时 7:00-10:00, 15:00-18:00, 21:00-23:00
-    Valley, // 谷时 23:00-次日7:00
-}
-
 // 系统常量
 pub const WAITING_AREA_CAPACITY: usize = 6; // 等候区容量
 pub const FAST_CHARGING_PILES: usize = 2; // 快充桩数量
@@ -29,12 +37,7 @@ pub const SLOW_CHARGING_PILES: usize = 3; // 慢充桩数量
 pub const PILE_QUEUE_CAPACITY: usize = 1; // 每个充电桩队列容量 (1个充电中，1个排队中)
 pub const FAST_CHARGING_POWER: f64 = 30.0; // 快充功率（度/小时）
 pub const SLOW_CHARGING_POWER: f64 = 7.0; // 慢充功率（度/小时）
-pub const SERVICE_FEE_RATE: f64 = 0.8; // 服务费率（元/度）
-
-// 电价常量
-pub const PEAK_PRICE: f64 = 1.0; // 峰时电价
-pub const NORMAL_PRICE: f64 = 0.7; // 平时电价
-pub const VALLEY_PRICE: f64 = 0.4; // 谷时电价
+pub const METER_SAMPLE_INTERVAL: i64 = 5; // 模拟充电桩电表采样间隔（分钟，系统时间）
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "VARCHAR")]
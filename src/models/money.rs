@@ -0,0 +1,85 @@
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Deserializer};
+
+/// 金额（元），以十进制精确表示，数据库中对应 DECIMAL 列
+///
+/// 舍入规则：
+/// - 电量按电表精度保留到 0.001 度，费率保留到 0.0001 元
+/// - 每个计费时段的电费、服务费分别四舍五入到分，账单金额为各时段金额之和，
+///   因此账单总额与明细逐项相加完全一致
+/// - 超时占位费按计费分钟数乘以费率后四舍五入到分
+/// - 累计金额（充电桩统计、汇总报表）只做精确加法，不再舍入
+pub type Money = BigDecimal;
+
+/// 金额保留的小数位数（分）
+pub const MONEY_SCALE: i64 = 2;
+/// 费率保留的小数位数（元/度、元/分钟）
+pub const RATE_SCALE: i64 = 4;
+/// 电量保留的小数位数（度）
+pub const ENERGY_SCALE: i64 = 3;
+
+/// 四舍五入到分（0.005 元进位为 0.01 元）
+pub fn round_money(amount: &BigDecimal) -> Money {
+    amount.round(MONEY_SCALE).with_scale(MONEY_SCALE)
+}
+
+/// 四舍五入到费率精度（0.0001 元）
+pub fn round_rate(rate: &BigDecimal) -> Money {
+    rate.round(RATE_SCALE).with_scale(RATE_SCALE)
+}
+
+/// 反序列化费率（JSON 数字或字符串），四舍五入到费率精度，用于 `#[serde(deserialize_with)]`
+pub fn deserialize_rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+    BigDecimal::deserialize(deserializer).map(|rate| round_rate(&rate))
+}
+
+/// 零元
+pub fn zero_money() -> Money {
+    BigDecimal::from(0).with_scale(MONEY_SCALE)
+}
+
+/// 将浮点数（电量、费率等）四舍五入到指定小数位后转换为十进制
+pub fn decimal_from_f64(value: f64, scale: i64) -> BigDecimal {
+    if !value.is_finite() {
+        return BigDecimal::from(0).with_scale(scale);
+    }
+    BigDecimal::try_from(value)
+        .map(|d| d.round(scale).with_scale(scale))
+        .unwrap_or_else(|_| BigDecimal::from(0).with_scale(scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_round_money_half_up() {
+        let round = |s: &str| round_money(&BigDecimal::from_str(s).unwrap()).to_string();
+        assert_eq!(round("0.125"), "0.13");
+        assert_eq!(round("0.124999"), "0.12");
+        assert_eq!(round("24"), "24.00");
+        assert_eq!(round("10.005"), "10.01");
+    }
+
+    #[test]
+    fn test_decimal_from_f64() {
+        assert_eq!(decimal_from_f64(0.7, RATE_SCALE).to_string(), "0.7000");
+        assert_eq!(decimal_from_f64(0.1 + 0.2, MONEY_SCALE).to_string(), "0.30");
+        assert_eq!(decimal_from_f64(12.3456, ENERGY_SCALE).to_string(), "12.346");
+        assert_eq!(decimal_from_f64(f64::NAN, MONEY_SCALE), zero_money());
+    }
+
+    #[test]
+    fn test_deserialize_rate() {
+        #[derive(Deserialize)]
+        struct Input {
+            #[serde(deserialize_with = "deserialize_rate")]
+            rate: Money,
+        }
+        let rate = |json: &str| serde_json::from_str::<Input>(json).unwrap().rate.to_string();
+        assert_eq!(rate(r#"{"rate": 0.4}"#), "0.4000");
+        assert_eq!(rate(r#"{"rate": "1.23456"}"#), "1.2346");
+        assert_eq!(rate(r#"{"rate": 1}"#), "1.0000");
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::models::{from_local, station_timezone, to_station_local, ChargingRequest};
+
+/// 充电请求在调度各环节的时间点（系统时间）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RequestTimeline {
+    pub submitted_at: Option<DateTime<Utc>>,  // 提交请求、进入等候区
+    pub dispatched_at: Option<DateTime<Utc>>, // 调度到充电桩队列（故障重新调度时取最后一次）
+    pub started_at: Option<DateTime<Utc>>,    // 开始充电
+    pub completed_at: Option<DateTime<Utc>>,  // 充电完成
+}
+
+/// 两个时间点之间的分钟数
+fn minutes_between(from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Option<f64> {
+    let (from, to) = (from?, to?);
+    Some((to - from).num_seconds().max(0) as f64 / 60.0)
+}
+
+impl RequestTimeline {
+    /// 等候区等待时长（分钟）
+    pub fn waiting_area_minutes(&self) -> Option<f64> {
+        minutes_between(self.submitted_at, self.dispatched_at)
+    }
+
+    /// 充电桩队列等待时长（分钟）
+    pub fn pile_queue_minutes(&self) -> Option<f64> {
+        minutes_between(self.dispatched_at, self.started_at)
+    }
+
+    /// 保存一次已完成充电的时间线（按充电站当地时间存储）
+    pub async fn insert(request: &ChargingRequest, pile_id: &str, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        let timeline = &request.timeline;
+        sqlx::query(
+            r#"
+            INSERT INTO request_timelines
+                (request_id, user_id, mode, pile_id, submitted_at, dispatched_at, started_at, completed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(request.id.as_bytes().to_vec())
+        .bind(request.user_id.as_bytes().to_vec())
+        .bind(&request.mode)
+        .bind(pile_id)
+        .bind(timeline.submitted_at.map(to_station_local))
+        .bind(timeline.dispatched_at.map(to_station_local))
+        .bind(timeline.started_at.map(to_station_local))
+        .bind(timeline.completed_at.map(to_station_local))
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 查询提交时间在 [start, end) 内的时间线，可按充电模式筛选
+    pub async fn find(
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        mode: Option<&str>,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let mut builder = sqlx::QueryBuilder::new(
+            "SELECT submitted_at, dispatched_at, started_at, completed_at FROM request_timelines WHERE submitted_at >= ",
+        );
+        builder.push_bind(start).push(" AND submitted_at < ").push_bind(end);
+        if let Some(mode) = mode {
+            builder.push(" AND mode = ").push_bind(mode);
+        }
+        builder.push(" ORDER BY submitted_at");
+
+        let tz = station_timezone();
+        let utc = |local: Option<NaiveDateTime>| local.map(|time| from_local(time, tz));
+        let rows = builder.build().fetch_all(pool).await?;
+        Ok(rows
+            .iter()
+            .map(|row| Self {
+                submitted_at: utc(row.get("submitted_at")),
+                dispatched_at: utc(row.get("dispatched_at")),
+                started_at: utc(row.get("started_at")),
+                completed_at: utc(row.get("completed_at")),
+            })
+            .collect())
+    }
+}
+
+/// 充电桩故障时段（充电站当地时间），end_time 为空表示尚未恢复
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PileFault {
+    pub pile_id: String,
+    pub start_time: NaiveDateTime,
+    pub end_time: Option<NaiveDateTime>,
+}
+
+impl PileFault {
+    /// 记录故障开始
+    pub async fn open(pile_id: &str, at: DateTime<Utc>, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO pile_faults (pile_id, start_time) VALUES (?, ?)")
+            .bind(pile_id)
+            .bind(to_station_local(at))
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 记录故障恢复，结束该充电桩尚未恢复的故障时段
+    pub async fn close(pile_id: &str, at: DateTime<Utc>, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE pile_faults SET end_time = ? WHERE pile_id = ? AND end_time IS NULL")
+            .bind(to_station_local(at))
+            .bind(pile_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 查询与 [start, end) 有交集的故障时段
+    pub async fn find_overlapping(
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT pile_id, start_time, end_time FROM pile_faults
+            WHERE start_time < ? AND (end_time IS NULL OR end_time > ?)
+            ORDER BY pile_id, start_time
+            "#,
+        )
+        .bind(end)
+        .bind(start)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| Self {
+                pile_id: row.get("pile_id"),
+                start_time: row.get("start_time"),
+                end_time: row.get("end_time"),
+            })
+            .collect())
+    }
+}
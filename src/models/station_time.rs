@@ -0,0 +1,83 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+
+pub const DEFAULT_STATION_TIMEZONE: Tz = chrono_tz::Asia::Shanghai;
+
+static STATION_TIMEZONE: OnceLock<Tz> = OnceLock::new();
+
+/// 充电站所在时区，由环境变量 STATION_TIMEZONE（IANA 名称，如 Asia/Shanghai）配置
+pub fn station_timezone() -> Tz {
+    *STATION_TIMEZONE.get_or_init(|| match std::env::var("STATION_TIMEZONE") {
+        Ok(name) => name.parse().unwrap_or_else(|e| {
+            println!("⚠️ 无效的时区 {}: {}，使用 {}", name, e, DEFAULT_STATION_TIMEZONE);
+            DEFAULT_STATION_TIMEZONE
+        }),
+        Err(_) => DEFAULT_STATION_TIMEZONE,
+    })
+}
+
+/// 转换为充电站当地时间
+pub fn to_station_local(time: DateTime<Utc>) -> NaiveDateTime {
+    time.with_timezone(&station_timezone()).naive_local()
+}
+
+/// 将当地时间转换为UTC
+///
+/// 夏令时回拨产生的重复时刻取较早的一个，夏令时跳过的时刻顺延到跳变之后。
+pub fn from_local(local: NaiveDateTime, tz: Tz) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => time.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => {
+            // 跳变通常不超过一小时，逐分钟向后查找第一个存在的时刻
+            (1..=180)
+                .find_map(|minutes| tz.from_local_datetime(&(local + Duration::minutes(minutes))).earliest())
+                .map(|time| time.with_timezone(&Utc))
+                .unwrap_or_else(|| local.and_utc())
+        }
+    }
+}
+
+/// 当地某一天的起止时间 [当天0点, 次日0点)，夏令时切换日不是24小时
+pub fn local_day_bounds(date: NaiveDate, tz: Tz) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = from_local(date.and_hms_opt(0, 0, 0).unwrap(), tz);
+    let end = from_local(date.succ_opt().unwrap_or(date).and_hms_opt(0, 0, 0).unwrap(), tz);
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::New_York;
+
+    #[test]
+    fn test_local_day_bounds_across_dst() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+
+        // 上海没有夏令时，当地0点为前一天16点UTC
+        let (start, end) = local_day_bounds(date(1), DEFAULT_STATION_TIMEZONE);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 2, 29, 16, 0, 0).unwrap());
+        assert_eq!(end - start, Duration::hours(24));
+
+        // 纽约 2024-03-10 开始夏令时，当天只有23小时
+        let (start, end) = local_day_bounds(date(10), New_York);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 3, 10, 5, 0, 0).unwrap());
+        assert_eq!(end - start, Duration::hours(23));
+
+        // 2024-11-03 结束夏令时，当天有25小时
+        let (start, end) = local_day_bounds(NaiveDate::from_ymd_opt(2024, 11, 3).unwrap(), New_York);
+        assert_eq!(end - start, Duration::hours(25));
+    }
+
+    #[test]
+    fn test_from_local_gap_and_fold() {
+        // 02:30 在跳变中不存在，顺延到 03:00 EDT
+        let gap = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap().and_hms_opt(2, 30, 0).unwrap();
+        assert_eq!(from_local(gap, New_York), Utc.with_ymd_and_hms(2024, 3, 10, 7, 0, 0).unwrap());
+
+        // 01:30 在回拨中出现两次，取较早的 EDT
+        let fold = NaiveDate::from_ymd_opt(2024, 11, 3).unwrap().and_hms_opt(1, 30, 0).unwrap();
+        assert_eq!(from_local(fold, New_York), Utc.with_ymd_and_hms(2024, 11, 3, 5, 30, 0).unwrap());
+    }
+}
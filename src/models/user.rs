@@ -1,25 +1,108 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use sqlx::FromRow;
 use sqlx::MySql;
 
+/// 用户角色，决定可以调用哪些管理接口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "VARCHAR")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    Driver,     // 车主，只能访问本人的数据
+    Operator,   // 运维，控制充电桩和调度器
+    Finance,    // 财务，管理电价、促销和退款
+    Superadmin, // 超级管理员，拥有全部权限并可分配角色
+}
+
+/// 管理接口按功能分组的权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    PileControl,      // 启停充电桩、上报故障和恢复
+    SchedulerControl, // 启停调度器、查看全部充电请求
+    Reports,          // 运营报表和分析
+    Billing,          // 电价、服务费率、促销、账单生成和退款
+    UserManagement,   // 用户列表、重置密码和分配角色
+    Audit,            // 查询管理操作审计日志
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Driver => "driver",
+            Role::Operator => "operator",
+            Role::Finance => "finance",
+            Role::Superadmin => "superadmin",
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Driver => &[],
+            Role::Operator => &[
+                Permission::PileControl,
+                Permission::SchedulerControl,
+                Permission::Reports,
+                Permission::Audit,
+            ],
+            Role::Finance => &[Permission::Reports, Permission::Billing],
+            Role::Superadmin => &[
+                Permission::PileControl,
+                Permission::SchedulerControl,
+                Permission::Reports,
+                Permission::Billing,
+                Permission::UserManagement,
+                Permission::Audit,
+            ],
+        }
+    }
+
+    pub fn has(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+
+    /// 车主以外的角色可以查看其他用户的数据
+    pub fn is_staff(&self) -> bool {
+        *self != Role::Driver
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "driver" => Ok(Role::Driver),
+            "operator" => Ok(Role::Operator),
+            "finance" => Ok(Role::Finance),
+            "superadmin" => Ok(Role::Superadmin),
+            _ => Err(format!("不支持的角色: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
+    #[serde(skip_serializing)]
     pub password_hash: String,
-    pub is_admin: bool,
+    pub is_admin: bool, // 与 role 同步（非车主即为 true），供前端判断是否显示管理页面
+    pub role: Role,
     pub created_at: DateTime<Utc>,
 }
 
 impl User {
-    pub fn new(username: String, password_hash: String, is_admin: bool) -> Self {
+    pub fn new(username: String, password_hash: String, role: Role) -> Self {
         Self {
             id: Uuid::new_v4(),
             username,
             password_hash,
-            is_admin,
+            is_admin: role.is_staff(),
+            role,
             created_at: Utc::now(),
         }
     }
@@ -27,13 +110,14 @@ impl User {
     pub async fn insert(&self, pool: &sqlx::MySqlPool) -> Result<(), sqlx::Error> {
         sqlx::query!(
             r#"
-            INSERT INTO users (id, username, password_hash, is_admin, created_at)
-            VALUES (?, ?, ?, ?, ?)
+            INSERT INTO users (id, username, password_hash, is_admin, role, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
             "#,
             self.id,
             self.username,
             self.password_hash,
             self.is_admin,
+            self.role.as_str(),
             self.created_at
         )
         .execute(pool)
@@ -43,7 +127,7 @@ impl User {
 
 pub async fn get_all(pool: &sqlx::MySqlPool) -> Result<Vec<User>, sqlx::Error> {
     let users = sqlx::query_as::<MySql, User>(
-        "SELECT id, username, password_hash, is_admin, created_at FROM users"
+        "SELECT id, username, password_hash, is_admin, role, created_at FROM users"
     )
     .fetch_all(pool)
     .await?;
@@ -51,4 +135,63 @@ pub async fn get_all(pool: &sqlx::MySqlPool) -> Result<Vec<User>, sqlx::Error> {
     Ok(users)
 }
 
+    pub async fn find_by_id(id: Uuid, pool: &sqlx::MySqlPool) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as::<MySql, User>(
+            "SELECT id, username, password_hash, is_admin, role, created_at FROM users WHERE id = ?"
+        )
+        .bind(id.as_bytes().to_vec())
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn find_by_username(username: &str, pool: &sqlx::MySqlPool) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as::<MySql, User>(
+            "SELECT id, username, password_hash, is_admin, role, created_at FROM users WHERE username = ?"
+        )
+        .bind(username)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// 保存新的密码哈希（修改、重置密码或登录时升级旧哈希）
+    pub async fn update_password_hash(id: Uuid, password_hash: &str, pool: &sqlx::MySqlPool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+            .bind(password_hash)
+            .bind(id.as_bytes().to_vec())
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 分配角色，同时更新 is_admin
+    pub async fn set_role(id: Uuid, role: Role, pool: &sqlx::MySqlPool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET role = ?, is_admin = ? WHERE id = ?")
+            .bind(role.as_str())
+            .bind(role.is_staff())
+            .bind(id.as_bytes().to_vec())
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_permissions() {
+        assert!(Role::Driver.permissions().is_empty());
+        assert!(Role::Operator.has(Permission::PileControl) && !Role::Operator.has(Permission::Billing));
+        assert!(Role::Finance.has(Permission::Billing) && !Role::Finance.has(Permission::SchedulerControl));
+        // 只有超级管理员可以管理用户和分配角色
+        for role in [Role::Driver, Role::Operator, Role::Finance] {
+            assert!(!role.has(Permission::UserManagement));
+            assert_eq!(role.as_str().parse::<Role>(), Ok(role));
+        }
+        assert!(Role::Superadmin.has(Permission::UserManagement));
+        assert!(Role::Operator.has(Permission::Audit) && !Role::Finance.has(Permission::Audit));
+        assert!("admin".parse::<Role>().is_err());
+    }
 }
@@ -0,0 +1,207 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::{to_station_local, zero_money, Money};
+
+/// 用户预付费钱包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wallet {
+    pub user_id: Uuid,
+    pub balance: Money,             // 余额（元），充电扣费后可能为负
+    pub updated_at: NaiveDateTime,  // 最近变动时间（充电站当地时间）
+}
+
+/// 钱包流水类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WalletTransactionKind {
+    TopUp,  // 充值
+    Charge, // 充电扣费
+    Credit, // 账单调整退款
+}
+
+impl WalletTransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WalletTransactionKind::TopUp => "TopUp",
+            WalletTransactionKind::Charge => "Charge",
+            WalletTransactionKind::Credit => "Credit",
+        }
+    }
+}
+
+impl FromStr for WalletTransactionKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "TopUp" => Ok(WalletTransactionKind::TopUp),
+            "Charge" => Ok(WalletTransactionKind::Charge),
+            "Credit" => Ok(WalletTransactionKind::Credit),
+            _ => Err(format!("Invalid WalletTransactionKind: {}", s)),
+        }
+    }
+}
+
+/// 钱包流水（只追加，不修改不删除）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletTransaction {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: WalletTransactionKind,
+    pub amount: Money,              // 变动金额，入账为正、扣款为负
+    pub balance_after: Money,       // 变动后余额
+    pub record_id: Option<Uuid>,    // 关联的充电详单
+    pub description: String,
+    pub created_at: NaiveDateTime,  // 充电站当地时间
+}
+
+impl Wallet {
+    /// 查询用户钱包，尚未开通时余额为零
+    pub async fn find(user_id: Uuid, pool: &sqlx::MySqlPool) -> Result<Self, sqlx::Error> {
+        let row = sqlx::query("SELECT balance, updated_at FROM wallets WHERE user_id = ?")
+            .bind(user_id.as_bytes().to_vec())
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(match row {
+            Some(row) => Wallet {
+                user_id,
+                balance: row.get("balance"),
+                updated_at: row.get("updated_at"),
+            },
+            None => Wallet {
+                user_id,
+                balance: zero_money(),
+                updated_at: to_station_local(Utc::now()),
+            },
+        })
+    }
+
+    /// 充值
+    pub async fn top_up(user_id: Uuid, amount: &Money, pool: &sqlx::MySqlPool) -> Result<WalletTransaction, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let transaction = Self::apply(&mut tx, user_id, WalletTransactionKind::TopUp, amount.clone(), None, "钱包充值").await?;
+        tx.commit().await?;
+        Ok(transaction)
+    }
+
+    /// 在事务中变动余额并追加一条流水，钱包不存在时自动开通
+    pub async fn apply(
+        tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+        user_id: Uuid,
+        kind: WalletTransactionKind,
+        amount: Money,
+        record_id: Option<Uuid>,
+        description: &str,
+    ) -> Result<WalletTransaction, sqlx::Error> {
+        let user_id_bytes = user_id.as_bytes().to_vec();
+        let now = to_station_local(Utc::now());
+
+        sqlx::query("INSERT IGNORE INTO wallets (user_id, balance, updated_at) VALUES (?, 0, ?)")
+            .bind(&user_id_bytes)
+            .bind(now)
+            .execute(&mut **tx)
+            .await?;
+
+        // 锁定钱包行，保证并发扣费时余额与流水一致
+        let balance: Money = sqlx::query("SELECT balance FROM wallets WHERE user_id = ? FOR UPDATE")
+            .bind(&user_id_bytes)
+            .fetch_one(&mut **tx)
+            .await?
+            .get("balance");
+        let balance_after = balance + &amount;
+
+        sqlx::query("UPDATE wallets SET balance = ?, updated_at = ? WHERE user_id = ?")
+            .bind(&balance_after)
+            .bind(now)
+            .bind(&user_id_bytes)
+            .execute(&mut **tx)
+            .await?;
+
+        let transaction = WalletTransaction {
+            id: Uuid::new_v4(),
+            user_id,
+            kind,
+            amount,
+            balance_after,
+            record_id,
+            description: description.to_string(),
+            created_at: now,
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO wallet_transactions (
+                id, user_id, kind, amount, balance_after, record_id, description, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(transaction.id.as_bytes().to_vec())
+        .bind(&user_id_bytes)
+        .bind(transaction.kind.as_str())
+        .bind(&transaction.amount)
+        .bind(&transaction.balance_after)
+        .bind(transaction.record_id.map(|id| id.as_bytes().to_vec()))
+        .bind(&transaction.description)
+        .bind(transaction.created_at)
+        .execute(&mut **tx)
+        .await?;
+
+        println!(
+            "💰 用户 {} 钱包{} {}元，余额 {}元",
+            user_id, transaction.description, transaction.amount, transaction.balance_after
+        );
+        Ok(transaction)
+    }
+}
+
+impl WalletTransaction {
+    /// 分页查询用户钱包流水，最新的在前
+    pub async fn find_by_user_id(
+        user_id: Uuid,
+        limit: u32,
+        offset: u32,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, kind, amount, balance_after, record_id, description, created_at
+            FROM wallet_transactions
+            WHERE user_id = ?
+            ORDER BY created_at DESC, seq DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(user_id.as_bytes().to_vec())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let decode_uuid = |bytes: Vec<u8>| {
+            Uuid::from_slice(&bytes)
+                .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))
+        };
+        let kind: String = row.get("kind");
+
+        Ok(WalletTransaction {
+            id: decode_uuid(row.get("id"))?,
+            user_id: decode_uuid(row.get("user_id"))?,
+            kind: kind.parse().map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+            amount: row.get("amount"),
+            balance_after: row.get("balance_after"),
+            record_id: row
+                .get::<Option<Vec<u8>>, _>("record_id")
+                .map(decode_uuid)
+                .transpose()?,
+            description: row.get("description"),
+            created_at: row.get("created_at"),
+        })
+    }
+}
@@ -0,0 +1,740 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, RwLock};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::messages::*;
+use super::{HEARTBEAT_INTERVAL, OCPP_SUBPROTOCOL};
+use crate::auth::constant_time_eq;
+use crate::models::{ChargingRequest, MeterSample, PileStatus};
+use crate::scheduler::{FinishedSession, QueueManager};
+
+/// 进行中的充电事务
+#[derive(Debug, Clone)]
+struct Transaction {
+    pile_number: String,
+    request: Option<Arc<ChargingRequest>>, // 未授权的事务没有对应的充电请求
+    meter_start: i32,                      // 开始电表读数（Wh）
+    last_meter: i32,                       // 最近一次上报的电表读数（Wh）
+    start_time: DateTime<Utc>,
+    samples: Vec<MeterSample>,             // 本次充电的电表采样
+}
+
+/// 充电桩接入凭据：OCPP 1.6 安全配置 1，充电桩以 HTTP Basic 认证连接，用户名为充电桩编号，密码为该桩的密钥
+#[derive(Debug, Clone, Default)]
+pub struct ChargePointCredentials(HashMap<String, String>);
+
+impl ChargePointCredentials {
+    /// 从环境变量 OCPP_PILE_SECRETS 读取，格式为 编号=密钥，逗号分隔，如 F1=secret1,T1=secret2
+    pub fn from_env() -> Self {
+        Self::parse(&env::var("OCPP_PILE_SECRETS").unwrap_or_default())
+    }
+
+    pub fn parse(value: &str) -> Self {
+        let secrets = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| match entry.split_once('=') {
+                Some((pile, secret)) if !pile.trim().is_empty() && !secret.trim().is_empty() => {
+                    Some((pile.trim().to_string(), secret.trim().to_string()))
+                }
+                _ => {
+                    println!("⚠️ 忽略无效的充电桩密钥配置: {}", entry);
+                    None
+                }
+            })
+            .collect();
+        ChargePointCredentials(secrets)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 某一充电桩的密钥
+    pub fn secret(&self, pile_number: &str) -> Option<&str> {
+        self.0.get(pile_number).map(String::as_str)
+    }
+
+    /// 校验 Authorization 请求头：Basic 认证的用户名须为充电桩编号，密码须与该桩的密钥一致
+    fn verify(&self, pile_number: &str, authorization: Option<&str>) -> bool {
+        let Some(secret) = self.secret(pile_number) else {
+            return false;
+        };
+        let Some(encoded) = authorization.and_then(|value| value.strip_prefix("Basic ")) else {
+            return false;
+        };
+        let Some(decoded) = STANDARD.decode(encoded.trim()).ok().and_then(|bytes| String::from_utf8(bytes).ok()) else {
+            return false;
+        };
+        match decoded.split_once(':') {
+            Some((user, password)) => user == pile_number && constant_time_eq(password.as_bytes(), secret.as_bytes()),
+            None => false,
+        }
+    }
+}
+
+/// OCPP 1.6-J 中央系统
+pub struct CentralSystem {
+    queue_manager: Arc<QueueManager>,
+    credentials: ChargePointCredentials,
+    // 已连接的充电桩，key为充电桩编号
+    connections: RwLock<HashMap<String, mpsc::UnboundedSender<Message>>>,
+    // 进行中的事务，key为transactionId
+    transactions: RwLock<HashMap<i32, Transaction>>,
+    next_transaction_id: AtomicI32,
+    next_message_id: AtomicU64,
+}
+
+impl CentralSystem {
+    pub fn new(queue_manager: Arc<QueueManager>) -> Self {
+        Self {
+            queue_manager,
+            credentials: ChargePointCredentials::default(),
+            connections: RwLock::new(HashMap::new()),
+            transactions: RwLock::new(HashMap::new()),
+            next_transaction_id: AtomicI32::new(1),
+            next_message_id: AtomicU64::new(1),
+        }
+    }
+
+    /// 指定充电桩接入凭据，设置后充电桩连接须通过 HTTP Basic 认证
+    pub fn with_credentials(mut self, credentials: ChargePointCredentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// 监听地址并在后台接受充电桩连接，返回实际监听的地址
+    ///
+    /// 未配置充电桩密钥时只允许监听本机回环地址，避免任意客户端冒充充电桩。
+    pub async fn start(self: Arc<Self>, addr: &str) -> std::io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        if self.credentials.is_empty() && !local_addr.ip().is_loopback() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("未配置充电桩密钥（OCPP_PILE_SECRETS）时不允许监听非本机地址 {}", local_addr),
+            ));
+        }
+
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let central_system = self.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = central_system.handle_connection(stream).await {
+                                println!("⚠️ OCPP连接 {} 异常: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) => println!("⚠️ OCPP接受连接失败: {}", e),
+                }
+            }
+        });
+
+        println!("🔌 OCPP中央系统已启动: ws://{}/ocpp/{{充电桩编号}}", local_addr);
+        Ok(local_addr)
+    }
+
+    /// 判断充电桩是否已通过OCPP连接
+    pub async fn is_connected(&self, pile_number: &str) -> bool {
+        self.connections.read().await.contains_key(pile_number)
+    }
+
+    /// 通知充电桩为指定请求远程启动充电，idTag 使用排队号码
+    pub async fn remote_start_transaction(&self, pile_number: &str, request: &ChargingRequest) {
+        let payload = RemoteStartTransactionRequest {
+            connector_id: Some(1),
+            id_tag: request.queue_number.clone(),
+        };
+        match self.send_call(pile_number, "RemoteStartTransaction", &payload).await {
+            Ok(_) => println!(
+                "📡 已向充电桩 {} 发送 RemoteStartTransaction (号码: {})",
+                pile_number, request.queue_number
+            ),
+            Err(e) => println!("⚠️ 发送 RemoteStartTransaction 失败: {}", e),
+        }
+    }
+
+    /// 向充电桩发送一条请求
+    async fn send_call<T: Serialize>(
+        &self,
+        pile_number: &str,
+        action: &str,
+        payload: &T,
+    ) -> Result<String, String> {
+        let unique_id = self.next_message_id.fetch_add(1, Ordering::SeqCst).to_string();
+        let message = OcppMessage::Call {
+            unique_id: unique_id.clone(),
+            action: action.to_string(),
+            payload: serde_json::to_value(payload).map_err(|e| e.to_string())?,
+        };
+
+        let connections = self.connections.read().await;
+        let sender = connections
+            .get(pile_number)
+            .ok_or_else(|| format!("充电桩 {} 未连接", pile_number))?;
+        sender
+            .send(Message::Text(message.to_text()))
+            .map_err(|_| format!("充电桩 {} 连接已关闭", pile_number))?;
+        Ok(unique_id)
+    }
+
+    /// 处理一个充电桩连接，路径形如 /ocpp/{充电桩编号}
+    #[allow(clippy::result_large_err)] // 握手回调的错误类型由 tungstenite 决定
+    async fn handle_connection(self: Arc<Self>, stream: TcpStream) -> Result<(), String> {
+        let mut path = String::new();
+        let credentials = &self.credentials;
+        let callback = |request: &Request, mut response: Response| -> Result<Response, ErrorResponse> {
+            path = request.uri().path().to_string();
+            let pile_number = path.rsplit('/').next().unwrap_or_default();
+            let authorization = request.headers().get("Authorization").and_then(|v| v.to_str().ok());
+            if !credentials.is_empty() && !credentials.verify(pile_number, authorization) {
+                let mut error = ErrorResponse::new(Some("充电桩认证失败".to_string()));
+                *error.status_mut() = StatusCode::UNAUTHORIZED;
+                error
+                    .headers_mut()
+                    .insert("WWW-Authenticate", HeaderValue::from_static("Basic realm=\"OCPP\""));
+                return Err(error);
+            }
+            let offers_ocpp16 = request
+                .headers()
+                .get("Sec-WebSocket-Protocol")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.split(',').any(|p| p.trim() == OCPP_SUBPROTOCOL));
+            if offers_ocpp16 {
+                response
+                    .headers_mut()
+                    .insert("Sec-WebSocket-Protocol", HeaderValue::from_static(OCPP_SUBPROTOCOL));
+            }
+            Ok(response)
+        };
+        let ws_stream = tokio_tungstenite::accept_hdr_async(stream, callback)
+            .await
+            .map_err(|e| format!("握手失败: {}", e))?;
+
+        let pile_number = path.rsplit('/').next().unwrap_or_default().to_string();
+        if !self.queue_manager.pile_infos.read().await.contains_key(&pile_number) {
+            return Err(format!("未知的充电桩: {}", pile_number));
+        }
+
+        let (mut sink, mut source) = ws_stream.split();
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Message>();
+        self.connections
+            .write()
+            .await
+            .insert(pile_number.clone(), sender.clone());
+        self.set_ocpp_connected(&pile_number, true).await;
+        println!("✅ 充电桩 {} 已通过OCPP连接", pile_number);
+
+        // 写任务：把待发送的消息写入连接
+        let writer = tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                if sink.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        while let Some(message) = source.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) | Err(_) => break,
+                Ok(_) => continue,
+            };
+
+            match OcppMessage::parse(&text) {
+                Ok(OcppMessage::Call {
+                    unique_id,
+                    action,
+                    payload,
+                }) => {
+                    let reply = match self.handle_call(&pile_number, &action, payload).await {
+                        Ok(payload) => OcppMessage::CallResult { unique_id, payload },
+                        Err((error_code, error_description)) => OcppMessage::CallError {
+                            unique_id,
+                            error_code: error_code.to_string(),
+                            error_description,
+                        },
+                    };
+                    let _ = sender.send(Message::Text(reply.to_text()));
+                }
+                Ok(OcppMessage::CallResult { unique_id, payload }) => {
+                    println!("📨 充电桩 {} 响应 {}: {}", pile_number, unique_id, payload);
+                }
+                Ok(OcppMessage::CallError {
+                    unique_id,
+                    error_code,
+                    error_description,
+                }) => {
+                    println!(
+                        "⚠️ 充电桩 {} 请求 {} 出错: {} {}",
+                        pile_number, unique_id, error_code, error_description
+                    );
+                }
+                Err(e) => println!("⚠️ 充电桩 {} 发送了无效消息: {}", pile_number, e),
+            }
+        }
+
+        writer.abort();
+        // 充电桩重连后旧连接才断开时，登记的已是新连接，不能移除
+        let still_registered = {
+            let mut connections = self.connections.write().await;
+            let registered = connections
+                .get(&pile_number)
+                .is_some_and(|current| current.same_channel(&sender));
+            if registered {
+                connections.remove(&pile_number);
+            }
+            registered
+        };
+        if still_registered {
+            self.set_ocpp_connected(&pile_number, false).await;
+            println!("🔌 充电桩 {} 已断开OCPP连接", pile_number);
+        } else {
+            println!("🔌 充电桩 {} 的旧OCPP连接已关闭", pile_number);
+        }
+        Ok(())
+    }
+
+    /// 处理充电桩发来的请求，返回响应负载或 (错误码, 描述)
+    async fn handle_call(
+        &self,
+        pile_number: &str,
+        action: &str,
+        payload: Value,
+    ) -> Result<Value, (&'static str, String)> {
+        let response = match action {
+            "BootNotification" => {
+                let request: BootNotificationRequest = parse_payload(payload)?;
+                println!(
+                    "📟 充电桩 {} 启动: {} {}",
+                    pile_number, request.charge_point_vendor, request.charge_point_model
+                );
+                json!(BootNotificationResponse {
+                    status: RegistrationStatus::Accepted,
+                    current_time: Utc::now(),
+                    interval: HEARTBEAT_INTERVAL,
+                })
+            }
+            "Heartbeat" => json!(HeartbeatResponse {
+                current_time: Utc::now(),
+            }),
+            "StatusNotification" => {
+                let request: StatusNotificationRequest = parse_payload(payload)?;
+                self.status_notification(pile_number, request).await;
+                json!({})
+            }
+            "StartTransaction" => {
+                let request: StartTransactionRequest = parse_payload(payload)?;
+                json!(self.start_transaction(pile_number, request).await)
+            }
+            "MeterValues" => {
+                let request: MeterValuesRequest = parse_payload(payload)?;
+                self.meter_values(request).await;
+                json!({})
+            }
+            "StopTransaction" => {
+                let request: StopTransactionRequest = parse_payload(payload)?;
+                json!(self.stop_transaction(request).await)
+            }
+            other => {
+                return Err(("NotImplemented", format!("不支持的操作: {}", other)));
+            }
+        };
+        Ok(response)
+    }
+
+    /// StatusNotification：同步充电桩状态
+    async fn status_notification(&self, pile_number: &str, request: StatusNotificationRequest) {
+        let new_status = request.status.to_pile_status();
+        let mut awaiting_unplug = false;
+        let mut old_status = None;
+        if let Some(pile_info) = self.queue_manager.pile_infos.read().await.get(pile_number) {
+            old_status = Some(std::mem::replace(&mut pile_info.pile.write().await.status, new_status));
+            awaiting_unplug = pile_info.plugged_in.is_some();
+        }
+        println!(
+            "📟 充电桩 {} 状态: {:?} (错误码: {})",
+            pile_number, request.status, request.error_code
+        );
+
+        if let Some(pool_arc) = self.queue_manager.db_pool.read().await.as_ref() {
+            let pool: &sqlx::MySqlPool = pool_arc;
+            if let Err(e) = sqlx::query("UPDATE charging_piles SET status = ? WHERE number = ?")
+                .bind(new_status.to_string())
+                .bind(pile_number)
+                .execute(pool)
+                .await
+            {
+                println!("⚠️ 无法更新充电桩 {} 状态: {}", pile_number, e);
+            }
+        }
+
+        // 记录故障时段
+        if let Some(old_status) = old_status {
+            let faulted = new_status == PileStatus::Fault;
+            if faulted != (old_status == PileStatus::Fault) {
+                let at = request.timestamp.unwrap_or_else(Utc::now);
+                self.queue_manager.record_pile_fault(pile_number, faulted, at).await;
+            }
+        }
+
+        // 充电结束后连接器回到 Available 表示车辆已拔枪
+        if awaiting_unplug && request.status == ChargePointStatus::Available {
+            let unplug_time = request.timestamp.unwrap_or_else(Utc::now);
+            if let Err(e) = self.queue_manager.unplug(pile_number, unplug_time).await {
+                println!("⚠️ {}", e);
+            }
+        }
+    }
+
+    /// StartTransaction：idTag 必须与该桩当前叫号的排队号码一致
+    async fn start_transaction(
+        &self,
+        pile_number: &str,
+        request: StartTransactionRequest,
+    ) -> StartTransactionResponse {
+        let transaction_id = self.next_transaction_id.fetch_add(1, Ordering::SeqCst);
+
+        let mut pile_infos = self.queue_manager.pile_infos.write().await;
+        let charging_request = pile_infos.get_mut(pile_number).and_then(|pile_info| {
+            let current = pile_info.current_charging.clone()?;
+            if current.queue_number != request.id_tag {
+                return None;
+            }
+            // 以充电桩上报的时间为实际开始时间
+            pile_info.charging_start_time = Some(request.timestamp);
+            Some(current)
+        });
+        drop(pile_infos);
+
+        let status = if charging_request.is_some() {
+            println!(
+                "⚡ 充电桩 {} 开始事务 {} (号码: {})",
+                pile_number, transaction_id, request.id_tag
+            );
+            AuthorizationStatus::Accepted
+        } else {
+            println!(
+                "⚠️ 充电桩 {} 的事务 {} 未授权 (号码: {})",
+                pile_number, transaction_id, request.id_tag
+            );
+            AuthorizationStatus::Invalid
+        };
+
+        self.transactions.write().await.insert(
+            transaction_id,
+            Transaction {
+                pile_number: pile_number.to_string(),
+                request: charging_request,
+                meter_start: request.meter_start,
+                last_meter: request.meter_start,
+                start_time: request.timestamp,
+                samples: Vec::new(),
+            },
+        );
+
+        StartTransactionResponse {
+            id_tag_info: IdTagInfo { status },
+            transaction_id,
+        }
+    }
+
+    /// MeterValues：记录电表读数，并为已授权的事务保存电表采样
+    async fn meter_values(&self, request: MeterValuesRequest) {
+        let Some(transaction_id) = request.transaction_id else {
+            return;
+        };
+        let mut new_samples = Vec::new();
+        if let Some(transaction) = self.transactions.write().await.get_mut(&transaction_id) {
+            for meter_value in &request.meter_value {
+                let Some(energy_wh) = meter_value.energy_wh() else {
+                    continue;
+                };
+                transaction.last_meter = energy_wh as i32;
+
+                let Some(charging_request) = &transaction.request else {
+                    continue;
+                };
+                let energy_kwh = (energy_wh - transaction.meter_start as f64).max(0.0) / 1000.0;
+                // 未上报功率时按与上一采样点的电量差估算
+                let power_kw = meter_value.power_kw().unwrap_or_else(|| {
+                    let (last_time, last_energy) = transaction
+                        .samples
+                        .last()
+                        .map(|s| s.curve_point())
+                        .unwrap_or((transaction.start_time, 0.0));
+                    let hours = (meter_value.timestamp - last_time).num_milliseconds() as f64 / 3_600_000.0;
+                    if hours > 0.0 {
+                        (energy_kwh - last_energy) / hours
+                    } else {
+                        0.0
+                    }
+                });
+                let sample = MeterSample::new(
+                    charging_request.id,
+                    transaction.pile_number.clone(),
+                    meter_value.timestamp,
+                    energy_kwh,
+                    power_kw,
+                );
+                transaction.samples.push(sample.clone());
+                new_samples.push(sample);
+            }
+        }
+
+        for sample in &new_samples {
+            self.queue_manager.save_meter_sample(sample).await;
+        }
+    }
+
+    /// StopTransaction：按实际计量电量生成充电详单，并为下一辆车叫号
+    async fn stop_transaction(&self, request: StopTransactionRequest) -> StopTransactionResponse {
+        let Some(transaction) = self.transactions.write().await.remove(&request.transaction_id) else {
+            println!("⚠️ 未知的事务: {}", request.transaction_id);
+            return StopTransactionResponse { id_tag_info: None };
+        };
+        let Some(charging_request) = transaction.request else {
+            return StopTransactionResponse { id_tag_info: None };
+        };
+
+        let meter_stop = request.meter_stop.max(transaction.last_meter);
+        let charge_amount = (meter_stop - transaction.meter_start).max(0) as f64 / 1000.0;
+        println!(
+            "🏁 充电桩 {} 结束事务 {} (电量: {}度)",
+            transaction.pile_number, request.transaction_id, charge_amount
+        );
+
+        let mut completed = (*charging_request).clone();
+        completed.timeline.completed_at = Some(request.timestamp);
+        if let Err(e) = completed.complete_charging() {
+            println!("⚠️ 更新充电完成状态失败: {}", e);
+        }
+        let session = FinishedSession {
+            request: Arc::new(completed),
+            charge_amount,
+            start_time: transaction.start_time,
+            end_time: request.timestamp,
+            meter_samples: transaction.samples,
+        };
+
+        // 持有 pile_infos 写锁时只更新内存状态，结算详单的数据库读写在释放锁后进行
+        let to_settle = {
+            let mut pile_infos = self.queue_manager.pile_infos.write().await;
+            match pile_infos.get_mut(&transaction.pile_number) {
+                Some(pile_info) => {
+                    if pile_info
+                        .current_charging
+                        .as_ref()
+                        .is_some_and(|current| current.id == charging_request.id)
+                    {
+                        pile_info.current_charging = None;
+                        pile_info.charging_start_time = None;
+                    }
+                    let pile = pile_info.pile.clone();
+                    self.queue_manager
+                        .end_session(pile_info, session)
+                        .await
+                        .map(|session| (pile, session))
+                }
+                None => None,
+            }
+        };
+        if let Some((pile, session)) = to_settle {
+            self.queue_manager.settle_session(&pile, &session, session.end_time).await;
+        }
+        self.queue_manager.start_next_on_pile(&transaction.pile_number).await;
+
+        StopTransactionResponse {
+            id_tag_info: Some(IdTagInfo {
+                status: AuthorizationStatus::Accepted,
+            }),
+        }
+    }
+
+    async fn set_ocpp_connected(&self, pile_number: &str, connected: bool) {
+        if let Some(pile_info) = self.queue_manager.pile_infos.write().await.get_mut(pile_number) {
+            pile_info.ocpp_connected = connected;
+        }
+    }
+}
+
+fn parse_payload<T: DeserializeOwned>(payload: Value) -> Result<T, (&'static str, String)> {
+    serde_json::from_value(payload).map_err(|e| ("FormationViolation", e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChargingMode, ChargingPile, PileStatus};
+    use crate::ocpp::ChargePoint;
+    use crate::scheduler::Dispatcher;
+
+    #[tokio::test]
+    async fn test_full_charging_flow_over_localhost() {
+        let queue_manager = Arc::new(QueueManager::new());
+        queue_manager
+            .add_pile(Arc::new(RwLock::new(ChargingPile::new(
+                "F1".to_string(),
+                ChargingMode::Fast,
+            ))))
+            .await;
+        let dispatcher = Dispatcher::new(queue_manager.clone());
+        dispatcher.start_calling().await;
+
+        let central_system = Arc::new(CentralSystem::new(queue_manager.clone()));
+        queue_manager.set_central_system(central_system.clone()).await;
+        let addr = central_system.clone().start("127.0.0.1:0").await.unwrap();
+
+        // 充电桩上线
+        let mut charge_point = ChargePoint::connect(&format!("ws://{}", addr), "F1", None)
+            .await
+            .unwrap();
+        let boot = charge_point.boot_notification("SimVendor", "SimPile").await.unwrap();
+        assert_eq!(boot.status, RegistrationStatus::Accepted);
+        charge_point
+            .status_notification(ChargePointStatus::Available)
+            .await
+            .unwrap();
+        assert!(central_system.is_connected("F1").await);
+
+        // 调度器叫号后向充电桩发送 RemoteStartTransaction
+        let request = ChargingRequest::new(uuid::Uuid::new_v4(), ChargingMode::Fast, 30.0, "F7".to_string());
+        queue_manager
+            .add_to_waiting_queue(Arc::new(request.clone()))
+            .await
+            .unwrap();
+        dispatcher.tick().await;
+
+        let (unique_id, action, payload) = charge_point.next_call().await.unwrap();
+        assert_eq!(action, "RemoteStartTransaction");
+        let remote_start: RemoteStartTransactionRequest = serde_json::from_value(payload).unwrap();
+        assert_eq!(remote_start.id_tag, "F7");
+        charge_point
+            .reply(&unique_id, json!({ "status": "Accepted" }))
+            .await
+            .unwrap();
+
+        // 充电桩上报事务
+        charge_point
+            .status_notification(ChargePointStatus::Charging)
+            .await
+            .unwrap();
+        assert_eq!(
+            queue_manager.pile_infos.read().await["F1"].pile.read().await.status,
+            PileStatus::Charging
+        );
+        let start = charge_point.start_transaction("F7", 1_000).await.unwrap();
+        assert_eq!(start.id_tag_info.status, AuthorizationStatus::Accepted);
+        charge_point
+            .meter_values(start.transaction_id, 16_000, 30_000.0)
+            .await
+            .unwrap();
+        {
+            let transactions = central_system.transactions.read().await;
+            let samples = &transactions[&start.transaction_id].samples;
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].record_id, request.id);
+            assert!((samples[0].energy_kwh - 15.0).abs() < 1e-9);
+            assert!((samples[0].power_kw - 30.0).abs() < 1e-9);
+        }
+        let stop = charge_point
+            .stop_transaction(start.transaction_id, 29_500)
+            .await
+            .unwrap();
+        assert_eq!(stop.id_tag_info.unwrap().status, AuthorizationStatus::Accepted);
+
+        // 按实际计量的 28.5 度结算
+        {
+            let pile_infos = queue_manager.pile_infos.read().await;
+            let pile_info = &pile_infos["F1"];
+            assert!(pile_info.current_charging.is_none());
+            let pile = pile_info.pile.read().await;
+            assert_eq!(pile.total_charge_count, 1);
+            assert!((pile.total_charge_amount - 28.5).abs() < 1e-9);
+        }
+
+        // 未叫号的 idTag 不被授权
+        let rejected = charge_point.start_transaction("T9", 0).await.unwrap();
+        assert_eq!(rejected.id_tag_info.status, AuthorizationStatus::Invalid);
+    }
+
+    #[tokio::test]
+    async fn test_stale_connection_close_keeps_reconnected_pile() {
+        let queue_manager = Arc::new(QueueManager::new());
+        queue_manager
+            .add_pile(Arc::new(RwLock::new(ChargingPile::new(
+                "F1".to_string(),
+                ChargingMode::Fast,
+            ))))
+            .await;
+        let central_system = Arc::new(CentralSystem::new(queue_manager.clone()));
+        let addr = central_system.clone().start("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", addr);
+
+        let mut old = ChargePoint::connect(&url, "F1", None).await.unwrap();
+        old.heartbeat().await.unwrap();
+        let mut reconnected = ChargePoint::connect(&url, "F1", None).await.unwrap();
+        reconnected.heartbeat().await.unwrap();
+
+        // 旧连接在重连之后才断开
+        drop(old);
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        assert!(central_system.is_connected("F1").await);
+        assert!(queue_manager.pile_infos.read().await["F1"].ocpp_connected);
+        reconnected.heartbeat().await.unwrap();
+
+        drop(reconnected);
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(!central_system.is_connected("F1").await);
+        assert!(!queue_manager.pile_infos.read().await["F1"].ocpp_connected);
+    }
+
+    #[tokio::test]
+    async fn test_charge_point_basic_auth() {
+        let queue_manager = Arc::new(QueueManager::new());
+        for number in ["F1", "F2"] {
+            queue_manager
+                .add_pile(Arc::new(RwLock::new(ChargingPile::new(number.to_string(), ChargingMode::Fast))))
+                .await;
+        }
+        let credentials = ChargePointCredentials::parse("F1=secret-f1, F2=secret-f2, bad-entry");
+        assert_eq!(credentials.secret("F2"), Some("secret-f2"));
+        let central_system = Arc::new(CentralSystem::new(queue_manager.clone()).with_credentials(credentials));
+        let addr = central_system.clone().start("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", addr);
+
+        // 没有密钥、密钥错误或用其他桩的密钥冒充都在握手时被拒绝
+        assert!(ChargePoint::connect(&url, "F1", None).await.is_err());
+        assert!(ChargePoint::connect(&url, "F1", Some("wrong")).await.is_err());
+        assert!(ChargePoint::connect(&url, "F1", Some("secret-f2")).await.is_err());
+        assert!(!central_system.is_connected("F1").await);
+
+        let mut charge_point = ChargePoint::connect(&url, "F1", Some("secret-f1")).await.unwrap();
+        charge_point.heartbeat().await.unwrap();
+        assert!(central_system.is_connected("F1").await);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_public_bind_without_credentials() {
+        let central_system = Arc::new(CentralSystem::new(Arc::new(QueueManager::new())));
+        assert!(central_system.start("0.0.0.0:0").await.is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use super::messages::*;
+use super::OCPP_SUBPROTOCOL;
+
+/// OCPP 1.6-J 充电桩客户端（模拟真实充电桩）
+pub struct ChargePoint {
+    pub id: String,
+    ws_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_message_id: u64,
+    // 等待响应期间收到的中央系统请求
+    pending_calls: VecDeque<(String, String, Value)>,
+}
+
+impl ChargePoint {
+    /// 连接中央系统，server_url 形如 ws://127.0.0.1:9000；secret 为该桩的密钥，以 HTTP Basic 认证发送
+    pub async fn connect(server_url: &str, id: &str, secret: Option<&str>) -> Result<Self, String> {
+        let url = format!("{}/ocpp/{}", server_url.trim_end_matches('/'), id);
+        let mut request = url.into_client_request().map_err(|e| e.to_string())?;
+        request
+            .headers_mut()
+            .insert("Sec-WebSocket-Protocol", HeaderValue::from_static(OCPP_SUBPROTOCOL));
+        if let Some(secret) = secret {
+            let authorization = format!("Basic {}", STANDARD.encode(format!("{}:{}", id, secret)));
+            request
+                .headers_mut()
+                .insert("Authorization", HeaderValue::from_str(&authorization).map_err(|e| e.to_string())?);
+        }
+
+        let (ws_stream, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| format!("连接中央系统失败: {}", e))?;
+
+        Ok(Self {
+            id: id.to_string(),
+            ws_stream,
+            next_message_id: 1,
+            pending_calls: VecDeque::new(),
+        })
+    }
+
+    /// 发送请求并等待对应的响应
+    pub async fn call(&mut self, action: &str, payload: Value) -> Result<Value, String> {
+        let unique_id = format!("{}-{}", self.id, self.next_message_id);
+        self.next_message_id += 1;
+
+        let message = OcppMessage::Call {
+            unique_id: unique_id.clone(),
+            action: action.to_string(),
+            payload,
+        };
+        self.send(message).await?;
+
+        loop {
+            match self.receive().await? {
+                OcppMessage::CallResult { unique_id: id, payload } if id == unique_id => {
+                    return Ok(payload)
+                }
+                OcppMessage::CallError {
+                    unique_id: id,
+                    error_code,
+                    error_description,
+                } if id == unique_id => {
+                    return Err(format!("{}: {}", error_code, error_description))
+                }
+                OcppMessage::Call {
+                    unique_id,
+                    action,
+                    payload,
+                } => self.pending_calls.push_back((unique_id, action, payload)),
+                _ => {}
+            }
+        }
+    }
+
+    /// 等待中央系统发来的下一条请求，返回 (uniqueId, action, payload)
+    pub async fn next_call(&mut self) -> Result<(String, String, Value), String> {
+        if let Some(call) = self.pending_calls.pop_front() {
+            return Ok(call);
+        }
+        loop {
+            if let OcppMessage::Call {
+                unique_id,
+                action,
+                payload,
+            } = self.receive().await?
+            {
+                return Ok((unique_id, action, payload));
+            }
+        }
+    }
+
+    /// 响应中央系统的请求
+    pub async fn reply(&mut self, unique_id: &str, payload: Value) -> Result<(), String> {
+        self.send(OcppMessage::CallResult {
+            unique_id: unique_id.to_string(),
+            payload,
+        })
+        .await
+    }
+
+    pub async fn boot_notification(
+        &mut self,
+        vendor: &str,
+        model: &str,
+    ) -> Result<BootNotificationResponse, String> {
+        let request = BootNotificationRequest {
+            charge_point_vendor: vendor.to_string(),
+            charge_point_model: model.to_string(),
+            charge_point_serial_number: None,
+            firmware_version: None,
+        };
+        self.typed_call("BootNotification", json!(request)).await
+    }
+
+    pub async fn heartbeat(&mut self) -> Result<HeartbeatResponse, String> {
+        self.typed_call("Heartbeat", json!({})).await
+    }
+
+    pub async fn status_notification(&mut self, status: ChargePointStatus) -> Result<(), String> {
+        let error_code = if status == ChargePointStatus::Faulted {
+            "OtherError"
+        } else {
+            "NoError"
+        };
+        let request = StatusNotificationRequest {
+            connector_id: 1,
+            error_code: error_code.to_string(),
+            status,
+            timestamp: Some(Utc::now()),
+        };
+        self.call("StatusNotification", json!(request)).await?;
+        Ok(())
+    }
+
+    pub async fn start_transaction(
+        &mut self,
+        id_tag: &str,
+        meter_start: i32,
+    ) -> Result<StartTransactionResponse, String> {
+        let request = StartTransactionRequest {
+            connector_id: 1,
+            id_tag: id_tag.to_string(),
+            meter_start,
+            timestamp: Utc::now(),
+            reservation_id: None,
+        };
+        self.typed_call("StartTransaction", json!(request)).await
+    }
+
+    /// 上报累计电能读数（Wh）和瞬时功率（W）
+    pub async fn meter_values(
+        &mut self,
+        transaction_id: i32,
+        energy_wh: i32,
+        power_w: f64,
+    ) -> Result<(), String> {
+        let request = MeterValuesRequest {
+            connector_id: 1,
+            transaction_id: Some(transaction_id),
+            meter_value: vec![MeterValue {
+                timestamp: Utc::now(),
+                sampled_value: vec![
+                    SampledValue {
+                        value: energy_wh.to_string(),
+                        measurand: Some("Energy.Active.Import.Register".to_string()),
+                        unit: Some("Wh".to_string()),
+                    },
+                    SampledValue {
+                        value: power_w.to_string(),
+                        measurand: Some("Power.Active.Import".to_string()),
+                        unit: Some("W".to_string()),
+                    },
+                ],
+            }],
+        };
+        self.call("MeterValues", json!(request)).await?;
+        Ok(())
+    }
+
+    pub async fn stop_transaction(
+        &mut self,
+        transaction_id: i32,
+        meter_stop: i32,
+    ) -> Result<StopTransactionResponse, String> {
+        let request = StopTransactionRequest {
+            id_tag: None,
+            meter_stop,
+            timestamp: Utc::now(),
+            transaction_id,
+            reason: None,
+        };
+        self.typed_call("StopTransaction", json!(request)).await
+    }
+
+    async fn typed_call<T: DeserializeOwned>(&mut self, action: &str, payload: Value) -> Result<T, String> {
+        let response = self.call(action, payload).await?;
+        serde_json::from_value(response).map_err(|e| format!("无效的{}响应: {}", action, e))
+    }
+
+    async fn send(&mut self, message: OcppMessage) -> Result<(), String> {
+        self.ws_stream
+            .send(Message::Text(message.to_text()))
+            .await
+            .map_err(|e| format!("发送失败: {}", e))
+    }
+
+    async fn receive(&mut self) -> Result<OcppMessage, String> {
+        loop {
+            match self.ws_stream.next().await {
+                Some(Ok(Message::Text(text))) => return OcppMessage::parse(&text),
+                Some(Ok(Message::Close(_))) | None => return Err("连接已关闭".to_string()),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(format!("接收失败: {}", e)),
+            }
+        }
+    }
+}
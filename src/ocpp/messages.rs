@@ -0,0 +1,361 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::models::PileStatus;
+
+// OCPP-J 消息类型编号
+const CALL: u64 = 2;
+const CALL_RESULT: u64 = 3;
+const CALL_ERROR: u64 = 4;
+
+/// OCPP-J 消息帧
+#[derive(Debug, Clone, PartialEq)]
+pub enum OcppMessage {
+    /// 请求 [2, uniqueId, action, payload]
+    Call {
+        unique_id: String,
+        action: String,
+        payload: Value,
+    },
+    /// 响应 [3, uniqueId, payload]
+    CallResult { unique_id: String, payload: Value },
+    /// 错误 [4, uniqueId, errorCode, errorDescription, errorDetails]
+    CallError {
+        unique_id: String,
+        error_code: String,
+        error_description: String,
+    },
+}
+
+impl OcppMessage {
+    /// 解析一条文本帧
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(text).map_err(|e| format!("无效的JSON: {}", e))?;
+        let frame = value.as_array().ok_or("消息帧必须是JSON数组")?;
+
+        let message_type = frame.first().and_then(|v| v.as_u64()).ok_or("缺少消息类型")?;
+        let unique_id = frame
+            .get(1)
+            .and_then(|v| v.as_str())
+            .ok_or("缺少消息ID")?
+            .to_string();
+
+        match message_type {
+            CALL => Ok(OcppMessage::Call {
+                unique_id,
+                action: frame
+                    .get(2)
+                    .and_then(|v| v.as_str())
+                    .ok_or("缺少action")?
+                    .to_string(),
+                payload: frame.get(3).cloned().unwrap_or_else(|| json!({})),
+            }),
+            CALL_RESULT => Ok(OcppMessage::CallResult {
+                unique_id,
+                payload: frame.get(2).cloned().unwrap_or_else(|| json!({})),
+            }),
+            CALL_ERROR => Ok(OcppMessage::CallError {
+                unique_id,
+                error_code: frame
+                    .get(2)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("GenericError")
+                    .to_string(),
+                error_description: frame
+                    .get(3)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+            }),
+            other => Err(format!("未知的消息类型: {}", other)),
+        }
+    }
+
+    /// 序列化为文本帧
+    pub fn to_text(&self) -> String {
+        let frame = match self {
+            OcppMessage::Call {
+                unique_id,
+                action,
+                payload,
+            } => json!([CALL, unique_id, action, payload]),
+            OcppMessage::CallResult { unique_id, payload } => {
+                json!([CALL_RESULT, unique_id, payload])
+            }
+            OcppMessage::CallError {
+                unique_id,
+                error_code,
+                error_description,
+            } => json!([CALL_ERROR, unique_id, error_code, error_description, {}]),
+        };
+        frame.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootNotificationRequest {
+    pub charge_point_vendor: String,
+    pub charge_point_model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charge_point_serial_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firmware_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegistrationStatus {
+    Accepted,
+    Pending,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootNotificationResponse {
+    pub status: RegistrationStatus,
+    pub current_time: DateTime<Utc>,
+    pub interval: i32, // 心跳间隔（秒）
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatResponse {
+    pub current_time: DateTime<Utc>,
+}
+
+/// 充电桩上报的连接器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChargePointStatus {
+    Available,
+    Preparing,
+    Charging,
+    SuspendedEVSE,
+    SuspendedEV,
+    Finishing,
+    Reserved,
+    Unavailable,
+    Faulted,
+}
+
+impl ChargePointStatus {
+    /// 映射为系统内的充电桩状态
+    pub fn to_pile_status(self) -> PileStatus {
+        match self {
+            ChargePointStatus::Available | ChargePointStatus::Reserved => PileStatus::Available,
+            ChargePointStatus::Preparing
+            | ChargePointStatus::Charging
+            | ChargePointStatus::SuspendedEVSE
+            | ChargePointStatus::SuspendedEV
+            | ChargePointStatus::Finishing => PileStatus::Charging,
+            ChargePointStatus::Unavailable => PileStatus::Shutdown,
+            ChargePointStatus::Faulted => PileStatus::Fault,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusNotificationRequest {
+    pub connector_id: i32,
+    pub error_code: String,
+    pub status: ChargePointStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthorizationStatus {
+    Accepted,
+    Blocked,
+    Expired,
+    Invalid,
+    ConcurrentTx,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdTagInfo {
+    pub status: AuthorizationStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartTransactionRequest {
+    pub connector_id: i32,
+    pub id_tag: String,
+    pub meter_start: i32, // 电表读数（Wh）
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reservation_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartTransactionResponse {
+    pub id_tag_info: IdTagInfo,
+    pub transaction_id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampledValue {
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub measurand: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterValue {
+    pub timestamp: DateTime<Utc>,
+    pub sampled_value: Vec<SampledValue>,
+}
+
+impl MeterValue {
+    /// 取出累计有功电能读数（Wh），缺省measurand即为该项
+    pub fn energy_wh(&self) -> Option<f64> {
+        self.sampled_value
+            .iter()
+            .find(|v| {
+                v.measurand
+                    .as_deref()
+                    .is_none_or(|m| m == "Energy.Active.Import.Register")
+            })
+            .and_then(|v| {
+                let value: f64 = v.value.parse().ok()?;
+                match v.unit.as_deref() {
+                    Some("kWh") => Some(value * 1000.0),
+                    _ => Some(value),
+                }
+            })
+    }
+
+    /// 取出瞬时有功功率（kW）
+    pub fn power_kw(&self) -> Option<f64> {
+        self.sampled_value
+            .iter()
+            .find(|v| v.measurand.as_deref() == Some("Power.Active.Import"))
+            .and_then(|v| {
+                let value: f64 = v.value.parse().ok()?;
+                match v.unit.as_deref() {
+                    Some("kW") => Some(value),
+                    _ => Some(value / 1000.0),
+                }
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterValuesRequest {
+    pub connector_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<i32>,
+    pub meter_value: Vec<MeterValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopTransactionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_tag: Option<String>,
+    pub meter_stop: i32, // 电表读数（Wh）
+    pub timestamp: DateTime<Utc>,
+    pub transaction_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopTransactionResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_tag_info: Option<IdTagInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteStartTransactionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connector_id: Option<i32>,
+    pub id_tag: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemoteStartStopStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteStartTransactionResponse {
+    pub status: RemoteStartStopStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let call = OcppMessage::parse(r#"[2,"19223201","BootNotification",{"chargePointVendor":"VendorX","chargePointModel":"SingleSocketCharger"}]"#).unwrap();
+        match &call {
+            OcppMessage::Call { unique_id, action, payload } => {
+                assert_eq!(unique_id, "19223201");
+                assert_eq!(action, "BootNotification");
+                let request: BootNotificationRequest = serde_json::from_value(payload.clone()).unwrap();
+                assert_eq!(request.charge_point_vendor, "VendorX");
+            }
+            _ => panic!("应解析为Call"),
+        }
+        assert_eq!(OcppMessage::parse(&call.to_text()).unwrap(), call);
+
+        let error = OcppMessage::parse(r#"[4,"1","NotImplemented","unknown action",{}]"#).unwrap();
+        assert_eq!(
+            error,
+            OcppMessage::CallError {
+                unique_id: "1".to_string(),
+                error_code: "NotImplemented".to_string(),
+                error_description: "unknown action".to_string(),
+            }
+        );
+
+        assert!(OcppMessage::parse(r#"{"not":"a frame"}"#).is_err());
+        assert!(OcppMessage::parse(r#"[9,"1"]"#).is_err());
+    }
+
+    #[test]
+    fn test_meter_value_energy() {
+        let wh: MeterValue = serde_json::from_value(json!({
+            "timestamp": "2024-03-01T11:00:00Z",
+            "sampledValue": [{"value": "1500"}]
+        }))
+        .unwrap();
+        assert_eq!(wh.energy_wh(), Some(1500.0));
+
+        let kwh: MeterValue = serde_json::from_value(json!({
+            "timestamp": "2024-03-01T11:00:00Z",
+            "sampledValue": [
+                {"value": "7.2", "measurand": "Power.Active.Import", "unit": "kW"},
+                {"value": "1.5", "measurand": "Energy.Active.Import.Register", "unit": "kWh"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(kwh.energy_wh(), Some(1500.0));
+        assert_eq!(kwh.power_kw(), Some(7.2));
+        assert_eq!(wh.power_kw(), None);
+    }
+
+    #[test]
+    fn test_status_mapping() {
+        assert_eq!(ChargePointStatus::Available.to_pile_status(), PileStatus::Available);
+        assert_eq!(ChargePointStatus::SuspendedEV.to_pile_status(), PileStatus::Charging);
+        assert_eq!(ChargePointStatus::Faulted.to_pile_status(), PileStatus::Fault);
+        assert_eq!(ChargePointStatus::Unavailable.to_pile_status(), PileStatus::Shutdown);
+    }
+}
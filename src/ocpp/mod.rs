@@ -0,0 +1,11 @@
+mod central_system;
+mod charge_point;
+pub mod messages;
+
+pub use central_system::{CentralSystem, ChargePointCredentials};
+pub use charge_point::ChargePoint;
+
+// OCPP 1.6-J WebSocket 子协议
+pub const OCPP_SUBPROTOCOL: &str = "ocpp1.6";
+// 充电桩心跳间隔（秒）
+pub const HEARTBEAT_INTERVAL: i32 = 300;
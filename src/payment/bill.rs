@@ -0,0 +1,291 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::models::{to_station_local, zero_money, Money};
+
+/// 账单状态
+///
+/// Pending → Authorized → Paid → Refunded，Pending 和 Authorized 可转为 Failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BillStatus {
+    Pending,    // 已发起预授权，等待渠道确认
+    Authorized, // 预授权成功，等待充电完成后扣款
+    Paid,       // 已扣款
+    Failed,     // 预授权或扣款失败、请求取消
+    Refunded,   // 已退款
+}
+
+impl BillStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillStatus::Pending => "Pending",
+            BillStatus::Authorized => "Authorized",
+            BillStatus::Paid => "Paid",
+            BillStatus::Failed => "Failed",
+            BillStatus::Refunded => "Refunded",
+        }
+    }
+
+    pub fn can_transition_to(&self, next: BillStatus) -> bool {
+        matches!(
+            (self, next),
+            (BillStatus::Pending, BillStatus::Authorized)
+                | (BillStatus::Pending, BillStatus::Failed)
+                | (BillStatus::Authorized, BillStatus::Paid)
+                | (BillStatus::Authorized, BillStatus::Failed)
+                | (BillStatus::Paid, BillStatus::Refunded)
+        )
+    }
+}
+
+/// 账单类型：充电请求的预授权扣款，或车主自助充值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BillKind {
+    Charging,
+    TopUp,
+}
+
+impl BillKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillKind::Charging => "Charging",
+            BillKind::TopUp => "TopUp",
+        }
+    }
+}
+
+impl FromStr for BillKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Charging" => Ok(BillKind::Charging),
+            "TopUp" => Ok(BillKind::TopUp),
+            _ => Err(format!("Invalid BillKind: {}", s)),
+        }
+    }
+}
+
+impl FromStr for BillStatus {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pending" => Ok(BillStatus::Pending),
+            "Authorized" => Ok(BillStatus::Authorized),
+            "Paid" => Ok(BillStatus::Paid),
+            "Failed" => Ok(BillStatus::Failed),
+            "Refunded" => Ok(BillStatus::Refunded),
+            _ => Err(format!("Invalid BillStatus: {}", s)),
+        }
+    }
+}
+
+/// 一次充电请求的支付账单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bill {
+    pub id: Uuid,
+    pub request_id: Uuid,                // 充电请求ID（与充电详单ID一致），充值账单为账单ID
+    pub user_id: Uuid,
+    pub kind: BillKind,
+    pub provider: String,                // 支付渠道
+    pub status: BillStatus,
+    pub authorized_amount: Money,        // 预授权金额（提交请求时的报价）
+    pub amount_due: Option<Money>,       // 应付金额，充电完成后确定
+    pub captured_amount: Money,          // 已扣款金额
+    pub refunded_amount: Money,          // 已退款金额
+    pub reference: Option<String>,       // 渠道交易号
+    pub failure_reason: Option<String>,
+    pub created_at: NaiveDateTime,       // 充电站当地时间
+    pub updated_at: NaiveDateTime,
+}
+
+impl Bill {
+    pub fn new(request_id: Uuid, user_id: Uuid, provider: &str, authorized_amount: Money) -> Self {
+        let now = to_station_local(Utc::now());
+        Self {
+            id: Uuid::new_v4(),
+            request_id,
+            user_id,
+            kind: BillKind::Charging,
+            provider: provider.to_string(),
+            status: BillStatus::Pending,
+            authorized_amount,
+            amount_due: None,
+            captured_amount: zero_money(),
+            refunded_amount: zero_money(),
+            reference: None,
+            failure_reason: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// 自助充值账单，应付金额即充值金额
+    pub fn top_up(user_id: Uuid, provider: &str, amount: Money) -> Self {
+        let mut bill = Self::new(Uuid::new_v4(), user_id, provider, amount.clone());
+        bill.request_id = bill.id;
+        bill.kind = BillKind::TopUp;
+        bill.amount_due = Some(amount);
+        bill
+    }
+
+    /// 状态迁移，不允许的迁移返回错误
+    pub fn transition(&mut self, next: BillStatus) -> Result<(), String> {
+        if !self.status.can_transition_to(next) {
+            return Err(format!(
+                "账单 {} 不能从 {} 变为 {}",
+                self.id,
+                self.status.as_str(),
+                next.as_str()
+            ));
+        }
+        self.status = next;
+        self.updated_at = to_station_local(Utc::now());
+        Ok(())
+    }
+
+    /// 标记失败并记录原因
+    pub fn fail(&mut self, reason: &str) -> Result<(), String> {
+        self.transition(BillStatus::Failed)?;
+        self.failure_reason = Some(reason.to_string());
+        Ok(())
+    }
+
+    /// 保存账单（按ID插入或更新），可在事务中执行
+    pub async fn save<'e>(&self, executor: impl sqlx::Executor<'e, Database = sqlx::MySql>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO bills (
+                id, request_id, user_id, kind, provider, status, authorized_amount, amount_due,
+                captured_amount, refunded_amount, reference, failure_reason, created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                status = VALUES(status),
+                amount_due = VALUES(amount_due),
+                captured_amount = VALUES(captured_amount),
+                refunded_amount = VALUES(refunded_amount),
+                reference = VALUES(reference),
+                failure_reason = VALUES(failure_reason),
+                updated_at = VALUES(updated_at)
+            "#,
+        )
+        .bind(self.id.as_bytes().to_vec())
+        .bind(self.request_id.as_bytes().to_vec())
+        .bind(self.user_id.as_bytes().to_vec())
+        .bind(self.kind.as_str())
+        .bind(&self.provider)
+        .bind(self.status.as_str())
+        .bind(&self.authorized_amount)
+        .bind(&self.amount_due)
+        .bind(&self.captured_amount)
+        .bind(&self.refunded_amount)
+        .bind(&self.reference)
+        .bind(&self.failure_reason)
+        .bind(self.created_at)
+        .bind(self.updated_at)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// 在事务中锁定账单行并返回当前状态，账单尚未保存时返回 None
+    pub async fn lock_status(
+        id: Uuid,
+        tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+    ) -> Result<Option<BillStatus>, sqlx::Error> {
+        let row = sqlx::query("SELECT status FROM bills WHERE id = ? FOR UPDATE")
+            .bind(id.as_bytes().to_vec())
+            .fetch_optional(&mut **tx)
+            .await?;
+        row.map(|row| row.get::<String, _>("status").parse().map_err(|e: String| sqlx::Error::Decode(e.into())))
+            .transpose()
+    }
+
+    /// 根据充电请求ID查询账单
+    pub async fn find_by_request_id(request_id: Uuid, pool: &sqlx::MySqlPool) -> Result<Option<Self>, sqlx::Error> {
+        let row = sqlx::query(&format!("SELECT {} FROM bills WHERE request_id = ?", BILL_COLUMNS))
+            .bind(request_id.as_bytes().to_vec())
+            .fetch_optional(pool)
+            .await?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    /// 根据渠道交易号查询账单
+    pub async fn find_by_reference(reference: &str, pool: &sqlx::MySqlPool) -> Result<Option<Self>, sqlx::Error> {
+        let row = sqlx::query(&format!("SELECT {} FROM bills WHERE reference = ?", BILL_COLUMNS))
+            .bind(reference)
+            .fetch_optional(pool)
+            .await?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    /// 查询用户的全部账单，最新的在前
+    pub async fn find_by_user_id(user_id: Uuid, pool: &sqlx::MySqlPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM bills WHERE user_id = ? ORDER BY created_at DESC",
+            BILL_COLUMNS
+        ))
+        .bind(user_id.as_bytes().to_vec())
+        .fetch_all(pool)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let decode_uuid = |bytes: Vec<u8>| {
+            Uuid::from_slice(&bytes)
+                .map_err(|e| sqlx::Error::Decode(format!("Failed to decode UUID: {}", e).into()))
+        };
+        let status: String = row.get("status");
+        let kind: String = row.get("kind");
+
+        Ok(Bill {
+            id: decode_uuid(row.get("id"))?,
+            request_id: decode_uuid(row.get("request_id"))?,
+            user_id: decode_uuid(row.get("user_id"))?,
+            kind: kind.parse().map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+            provider: row.get("provider"),
+            status: status.parse().map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+            authorized_amount: row.get("authorized_amount"),
+            amount_due: row.get("amount_due"),
+            captured_amount: row.get("captured_amount"),
+            refunded_amount: row.get("refunded_amount"),
+            reference: row.get("reference"),
+            failure_reason: row.get("failure_reason"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+}
+
+const BILL_COLUMNS: &str = "id, request_id, user_id, kind, provider, status, authorized_amount, amount_due, \
+    captured_amount, refunded_amount, reference, failure_reason, created_at, updated_at";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bill_state_machine() {
+        let mut bill = Bill::new(Uuid::new_v4(), Uuid::new_v4(), "mock", zero_money());
+        assert_eq!(bill.status, BillStatus::Pending);
+
+        // 未授权不能直接扣款
+        assert!(bill.transition(BillStatus::Paid).is_err());
+        bill.transition(BillStatus::Authorized).unwrap();
+        bill.transition(BillStatus::Paid).unwrap();
+        bill.transition(BillStatus::Refunded).unwrap();
+
+        // 终态不能再变化
+        assert!(bill.transition(BillStatus::Paid).is_err());
+        assert!(bill.fail("重复退款").is_err());
+
+        let mut declined = Bill::new(Uuid::new_v4(), Uuid::new_v4(), "mock", zero_money());
+        declined.fail("余额不足").unwrap();
+        assert_eq!(declined.failure_reason.as_deref(), Some("余额不足"));
+        assert!(declined.transition(BillStatus::Authorized).is_err());
+    }
+}
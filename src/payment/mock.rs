@@ -0,0 +1,132 @@
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::{PaymentError, PaymentOutcome, PaymentProvider, PaymentResponse, WebhookEvent, WebhookEventKind};
+use crate::models::{zero_money, Money};
+
+#[derive(Debug, Clone)]
+struct MockPayment {
+    captured: Money,
+    refunded: Money,
+}
+
+/// 本地模拟支付渠道，可模拟拒付和延迟确认（延迟时通过 webhook 生成签名回调）
+pub struct MockPaymentProvider {
+    secret: String,
+    decline_authorizations: AtomicBool,
+    decline_captures: AtomicBool,
+    delayed_confirmation: AtomicBool,
+    payments: Mutex<HashMap<String, MockPayment>>,
+}
+
+impl MockPaymentProvider {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            secret: secret.to_string(),
+            decline_authorizations: AtomicBool::new(false),
+            decline_captures: AtomicBool::new(false),
+            delayed_confirmation: AtomicBool::new(false),
+            payments: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 拒绝后续的预授权
+    pub fn set_decline_authorizations(&self, decline: bool) {
+        self.decline_authorizations.store(decline, Ordering::SeqCst);
+    }
+
+    /// 拒绝后续的扣款
+    pub fn set_decline_captures(&self, decline: bool) {
+        self.decline_captures.store(decline, Ordering::SeqCst);
+    }
+
+    /// 后续操作只受理不确认，需通过回调确认结果
+    pub fn set_delayed_confirmation(&self, delayed: bool) {
+        self.delayed_confirmation.store(delayed, Ordering::SeqCst);
+    }
+
+    /// 回调签名：sha256(密钥 + 报文) 的十六进制
+    pub fn sign(&self, payload: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.secret.as_bytes());
+        hasher.update(payload);
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// 生成一条带签名的回调报文，模拟渠道异步通知
+    pub fn webhook(&self, reference: &str, kind: WebhookEventKind) -> (Vec<u8>, String) {
+        let event = WebhookEvent {
+            reference: reference.to_string(),
+            kind,
+            reason: None,
+        };
+        let payload = serde_json::to_vec(&event).unwrap_or_default();
+        let signature = self.sign(&payload);
+        (payload, signature)
+    }
+
+    fn outcome(&self) -> PaymentOutcome {
+        if self.delayed_confirmation.load(Ordering::SeqCst) {
+            PaymentOutcome::Pending
+        } else {
+            PaymentOutcome::Succeeded
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MockPaymentProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn authorize(&self, bill_id: Uuid, _user_id: Uuid, _amount: &Money) -> Result<PaymentResponse, PaymentError> {
+        if self.decline_authorizations.load(Ordering::SeqCst) {
+            return Err(PaymentError::Declined("模拟渠道拒绝预授权".to_string()));
+        }
+        let reference = format!("mock_{}", bill_id.simple());
+        self.payments.lock().unwrap().insert(
+            reference.clone(),
+            MockPayment {
+                captured: zero_money(),
+                refunded: zero_money(),
+            },
+        );
+        Ok(PaymentResponse { reference, outcome: self.outcome() })
+    }
+
+    async fn capture(&self, reference: &str, amount: &Money) -> Result<PaymentResponse, PaymentError> {
+        if self.decline_captures.load(Ordering::SeqCst) {
+            return Err(PaymentError::Declined("模拟渠道拒绝扣款".to_string()));
+        }
+        let mut payments = self.payments.lock().unwrap();
+        let payment = payments
+            .get_mut(reference)
+            .ok_or_else(|| PaymentError::NotFound(reference.to_string()))?;
+        payment.captured = amount.clone();
+        Ok(PaymentResponse { reference: reference.to_string(), outcome: self.outcome() })
+    }
+
+    async fn refund(&self, reference: &str, amount: &Money) -> Result<PaymentResponse, PaymentError> {
+        let mut payments = self.payments.lock().unwrap();
+        let payment = payments
+            .get_mut(reference)
+            .ok_or_else(|| PaymentError::NotFound(reference.to_string()))?;
+        if &payment.refunded + amount > payment.captured {
+            return Err(PaymentError::Declined("退款金额超过已扣款金额".to_string()));
+        }
+        payment.refunded += amount;
+        Ok(PaymentResponse { reference: reference.to_string(), outcome: self.outcome() })
+    }
+
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> Result<WebhookEvent, PaymentError> {
+        if self.sign(payload) != signature {
+            return Err(PaymentError::InvalidSignature);
+        }
+        serde_json::from_slice(payload).map_err(|e| PaymentError::Provider(format!("回调报文无效: {}", e)))
+    }
+}
@@ -0,0 +1,11 @@
+mod bill;
+mod mock;
+mod provider;
+mod service;
+
+pub use bill::{Bill, BillKind, BillStatus};
+pub use mock::MockPaymentProvider;
+pub use provider::{
+    PaymentError, PaymentOutcome, PaymentProvider, PaymentResponse, WebhookEvent, WebhookEventKind,
+};
+pub use service::PaymentService;
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::models::Money;
+
+/// 支付渠道返回的错误
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PaymentError {
+    #[error("支付被拒绝: {0}")]
+    Declined(String),
+    #[error("支付单不存在: {0}")]
+    NotFound(String),
+    #[error("回调签名校验失败")]
+    InvalidSignature,
+    #[error("支付渠道错误: {0}")]
+    Provider(String),
+}
+
+/// 支付渠道受理结果：同步成功，或等待渠道异步回调确认
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaymentOutcome {
+    Succeeded,
+    Pending,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentResponse {
+    pub reference: String, // 渠道交易号
+    pub outcome: PaymentOutcome,
+}
+
+/// 渠道回调事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEventKind {
+    Authorized,
+    Captured,
+    Failed,
+    Refunded,
+}
+
+/// 校验通过的渠道回调
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub reference: String,
+    pub kind: WebhookEventKind,
+    pub reason: Option<String>,
+}
+
+/// 支付渠道：预授权、扣款、退款和回调校验
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// 渠道名称
+    fn name(&self) -> &str;
+
+    /// 按预估金额冻结资金
+    async fn authorize(&self, bill_id: Uuid, user_id: Uuid, amount: &Money) -> Result<PaymentResponse, PaymentError>;
+
+    /// 按实际金额从预授权中扣款
+    async fn capture(&self, reference: &str, amount: &Money) -> Result<PaymentResponse, PaymentError>;
+
+    /// 退还已扣款金额
+    async fn refund(&self, reference: &str, amount: &Money) -> Result<PaymentResponse, PaymentError>;
+
+    /// 校验回调签名并解析事件
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> Result<WebhookEvent, PaymentError>;
+}
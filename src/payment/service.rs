@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use super::{Bill, BillKind, BillStatus, PaymentOutcome, PaymentProvider, WebhookEventKind};
+use crate::metrics;
+use crate::models::{round_money, Money, Wallet, WalletTransactionKind};
+
+/// 支付服务：提交请求时预授权，充电完成后扣款，并处理渠道回调；车主自助充值也经由渠道收款
+pub struct PaymentService {
+    provider: Arc<dyn PaymentProvider>,
+    bills: RwLock<HashMap<Uuid, Bill>>, // 按充电请求ID索引
+    db_pool: RwLock<Option<Arc<sqlx::MySqlPool>>>,
+}
+
+impl PaymentService {
+    pub fn new(provider: Arc<dyn PaymentProvider>) -> Self {
+        Self {
+            provider,
+            bills: RwLock::new(HashMap::new()),
+            db_pool: RwLock::new(None),
+        }
+    }
+
+    pub async fn set_db_pool(&self, pool: Arc<sqlx::MySqlPool>) {
+        *self.db_pool.write().await = Some(pool);
+    }
+
+    pub fn provider_name(&self) -> &str {
+        self.provider.name()
+    }
+
+    /// 按报价预授权，渠道拒绝时账单置为 Failed 并返回错误
+    pub async fn pre_authorize(&self, request_id: Uuid, user_id: Uuid, quote: &Money) -> Result<Bill, String> {
+        let mut bill = Bill::new(request_id, user_id, self.provider.name(), round_money(quote));
+
+        match self.provider.authorize(bill.id, user_id, &bill.authorized_amount).await {
+            Ok(response) => {
+                bill.reference = Some(response.reference);
+                if response.outcome == PaymentOutcome::Succeeded {
+                    bill.transition(BillStatus::Authorized)?;
+                }
+                println!(
+                    "💳 请求 {} 预授权 {}元，状态 {}",
+                    request_id,
+                    bill.authorized_amount,
+                    bill.status.as_str()
+                );
+                Ok(self.store(bill).await)
+            }
+            Err(e) => {
+                bill.fail(&e.to_string())?;
+                self.store(bill).await;
+                println!("❌ 请求 {} 预授权失败: {}", request_id, e);
+                Err(format!("预授权失败: {}", e))
+            }
+        }
+    }
+
+    /// 车主自助充值：向渠道发起收款，渠道回调确认扣款后才入账钱包
+    pub async fn start_top_up(&self, user_id: Uuid, amount: &Money) -> Result<Bill, String> {
+        let mut bill = Bill::top_up(user_id, self.provider.name(), round_money(amount));
+
+        match self.provider.authorize(bill.id, user_id, &bill.authorized_amount).await {
+            Ok(response) => {
+                bill.reference = Some(response.reference);
+                if response.outcome == PaymentOutcome::Succeeded {
+                    bill.transition(BillStatus::Authorized)?;
+                }
+                println!(
+                    "💳 用户 {} 发起充值 {}元，交易号 {}，等待渠道确认",
+                    user_id,
+                    bill.authorized_amount,
+                    bill.reference.as_deref().unwrap_or_default()
+                );
+                Ok(self.store(bill).await)
+            }
+            Err(e) => {
+                bill.fail(&e.to_string())?;
+                self.store(bill).await;
+                println!("❌ 用户 {} 发起充值失败: {}", user_id, e);
+                Err(format!("发起充值失败: {}", e))
+            }
+        }
+    }
+
+    /// 充电完成后按实际费用扣款；预授权尚未确认时记下应付金额，待回调确认后再扣款
+    pub async fn capture(&self, request_id: Uuid, amount: &Money) -> Result<Bill, String> {
+        let mut bill = self
+            .bill(request_id)
+            .await
+            .ok_or_else(|| format!("请求 {} 没有支付账单", request_id))?;
+        bill.amount_due = Some(round_money(amount));
+
+        match bill.status {
+            BillStatus::Pending => Ok(self.store(bill).await),
+            BillStatus::Authorized => self.capture_authorized(bill).await,
+            status => Err(format!("账单状态为 {}，不能扣款", status.as_str())),
+        }
+    }
+
+    async fn capture_authorized(&self, mut bill: Bill) -> Result<Bill, String> {
+        let reference = bill.reference.clone().unwrap_or_default();
+        let amount = bill.amount_due.clone().unwrap_or_else(|| bill.authorized_amount.clone());
+
+        match self.provider.capture(&reference, &amount).await {
+            Ok(response) => {
+                if response.outcome == PaymentOutcome::Succeeded {
+                    bill.captured_amount = amount;
+                    bill.transition(BillStatus::Paid)?;
+                    println!("✅ 请求 {} 扣款 {}元", bill.request_id, bill.captured_amount);
+                } else {
+                    println!("⏳ 请求 {} 扣款已受理，等待渠道确认", bill.request_id);
+                }
+                Ok(self.store(bill).await)
+            }
+            Err(e) => {
+                bill.fail(&e.to_string())?;
+                let bill = self.store(bill).await;
+                println!("❌ 请求 {} 扣款失败: {}", bill.request_id, e);
+                Err(format!("扣款失败: {}", e))
+            }
+        }
+    }
+
+    /// 退还已扣款账单的部分或全部金额
+    pub async fn refund(&self, request_id: Uuid, amount: &Money) -> Result<Bill, String> {
+        let mut bill = self
+            .bill(request_id)
+            .await
+            .ok_or_else(|| format!("请求 {} 没有支付账单", request_id))?;
+        if bill.status != BillStatus::Paid {
+            return Err(format!("账单状态为 {}，不能退款", bill.status.as_str()));
+        }
+        let amount = round_money(amount);
+        if amount > bill.captured_amount {
+            return Err(format!("退款金额 {}元 超过已扣款金额 {}元", amount, bill.captured_amount));
+        }
+
+        let reference = bill.reference.clone().unwrap_or_default();
+        let response = self
+            .provider
+            .refund(&reference, &amount)
+            .await
+            .map_err(|e| format!("退款失败: {}", e))?;
+        bill.refunded_amount = amount;
+        if response.outcome == PaymentOutcome::Succeeded {
+            bill.transition(BillStatus::Refunded)?;
+        }
+        println!("↩️ 请求 {} 退款 {}元，状态 {}", request_id, bill.refunded_amount, bill.status.as_str());
+        Ok(self.store(bill).await)
+    }
+
+    /// 充电请求取消，释放预授权
+    pub async fn cancel(&self, request_id: Uuid) {
+        if self.fail(request_id, "充电请求已取消").await {
+            println!("💳 请求 {} 已取消，释放预授权", request_id);
+        }
+    }
+
+    /// 将尚未扣款的账单置为 Failed 并保存，返回是否变更
+    pub async fn fail(&self, request_id: Uuid, reason: &str) -> bool {
+        let Some(mut bill) = self.bill(request_id).await else {
+            return false;
+        };
+        if bill.fail(reason).is_err() {
+            return false;
+        }
+        self.store(bill).await;
+        true
+    }
+
+    /// 校验并处理渠道回调，返回更新后的账单
+    pub async fn handle_webhook(&self, payload: &[u8], signature: &str) -> Result<Bill, String> {
+        let event = self
+            .provider
+            .verify_webhook(payload, signature)
+            .map_err(|e| e.to_string())?;
+        let mut bill = self
+            .find_by_reference(&event.reference)
+            .await
+            .ok_or_else(|| format!("交易号 {} 没有对应账单", event.reference))?;
+
+        match (bill.kind, event.kind) {
+            (BillKind::TopUp, WebhookEventKind::Authorized) => {
+                bill.transition(BillStatus::Authorized)?;
+            }
+            (BillKind::TopUp, WebhookEventKind::Captured) => {
+                if bill.status == BillStatus::Pending {
+                    bill.transition(BillStatus::Authorized)?;
+                }
+                bill.captured_amount = bill.authorized_amount.clone();
+                bill.transition(BillStatus::Paid)?;
+                return self.credit_top_up(bill).await;
+            }
+            (BillKind::TopUp, WebhookEventKind::Refunded) => {
+                return Err(format!("充值账单 {} 不支持渠道退款", bill.id));
+            }
+            (_, WebhookEventKind::Authorized) => {
+                bill.transition(BillStatus::Authorized)?;
+                // 充电已完成的，确认预授权后立即扣款
+                if bill.amount_due.is_some() {
+                    return self.capture_authorized(bill).await;
+                }
+            }
+            (_, WebhookEventKind::Captured) => {
+                bill.captured_amount = bill
+                    .amount_due
+                    .clone()
+                    .unwrap_or_else(|| bill.authorized_amount.clone());
+                bill.transition(BillStatus::Paid)?;
+            }
+            (_, WebhookEventKind::Failed) => {
+                bill.fail(event.reason.as_deref().unwrap_or("渠道通知支付失败"))?;
+            }
+            (_, WebhookEventKind::Refunded) => {
+                bill.transition(BillStatus::Refunded)?;
+            }
+        }
+        println!("🔔 交易 {} 回调 {:?}，账单状态 {}", event.reference, event.kind, bill.status.as_str());
+        Ok(self.store(bill).await)
+    }
+
+    /// 充值到账：在同一事务中锁定账单、入账钱包并保存账单；失败时账单不变，渠道重发回调后再入账
+    async fn credit_top_up(&self, bill: Bill) -> Result<Bill, String> {
+        let pool = self.db_pool.read().await.clone().ok_or("未配置数据库，充值无法入账")?;
+        let result: Result<bool, sqlx::Error> = async {
+            let mut tx = pool.begin().await?;
+            // 重复回调并发到达时只入账一次
+            if Bill::lock_status(bill.id, &mut tx).await? == Some(BillStatus::Paid) {
+                return Ok(false);
+            }
+            Wallet::apply(
+                &mut tx,
+                bill.user_id,
+                WalletTransactionKind::TopUp,
+                bill.captured_amount.clone(),
+                None,
+                "钱包充值",
+            )
+            .await?;
+            bill.save(&mut *tx).await?;
+            tx.commit().await?;
+            Ok(true)
+        }
+        .await;
+
+        match result {
+            Ok(true) => {
+                println!("✅ 充值 {} 到账 {}元", bill.id, bill.captured_amount);
+                self.bills.write().await.insert(bill.request_id, bill.clone());
+                Ok(bill)
+            }
+            Ok(false) => Err(format!("充值 {} 已入账", bill.id)),
+            Err(e) => {
+                println!("❌ 充值 {} 入账失败: {}", bill.id, e);
+                metrics::db_error("payment");
+                Err(format!("充值入账失败: {}", e))
+            }
+        }
+    }
+
+    /// 查询请求的账单，内存中没有时查数据库
+    pub async fn bill(&self, request_id: Uuid) -> Option<Bill> {
+        if let Some(bill) = self.bills.read().await.get(&request_id) {
+            return Some(bill.clone());
+        }
+        let pool = self.db_pool.read().await.clone()?;
+        match Bill::find_by_request_id(request_id, &pool).await {
+            Ok(bill) => bill,
+            Err(e) => {
+                println!("❌ 查询支付账单失败: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn find_by_reference(&self, reference: &str) -> Option<Bill> {
+        if let Some(bill) = self
+            .bills
+            .read()
+            .await
+            .values()
+            .find(|bill| bill.reference.as_deref() == Some(reference))
+        {
+            return Some(bill.clone());
+        }
+        let pool = self.db_pool.read().await.clone()?;
+        match Bill::find_by_reference(reference, &pool).await {
+            Ok(bill) => bill,
+            Err(e) => {
+                println!("❌ 查询支付账单失败: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 更新内存中的账单并写入数据库
+    async fn store(&self, bill: Bill) -> Bill {
+        if let Some(pool) = self.db_pool.read().await.clone() {
+            if let Err(e) = bill.save(pool.as_ref()).await {
+                println!("❌ 保存支付账单失败: {}", e);
+            }
+        }
+        self.bills.write().await.insert(bill.request_id, bill.clone());
+        bill
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::zero_money;
+    use crate::payment::MockPaymentProvider;
+    use std::str::FromStr;
+
+    fn yuan(s: &str) -> Money {
+        Money::from_str(s).unwrap()
+    }
+
+    fn service() -> (Arc<MockPaymentProvider>, PaymentService) {
+        let provider = Arc::new(MockPaymentProvider::new("test-secret"));
+        let service = PaymentService::new(provider.clone());
+        (provider, service)
+    }
+
+    #[tokio::test]
+    async fn test_authorize_then_capture() {
+        let (_, service) = service();
+        let request_id = Uuid::new_v4();
+
+        let bill = service.pre_authorize(request_id, Uuid::new_v4(), &yuan("54.00")).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Authorized);
+
+        let bill = service.capture(request_id, &yuan("48.30")).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Paid);
+        assert_eq!(bill.captured_amount, yuan("48.30"));
+
+        let bill = service.refund(request_id, &yuan("48.30")).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Refunded);
+        assert!(service.refund(request_id, &yuan("1.00")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_declined_authorization_fails_bill() {
+        let (provider, service) = service();
+        provider.set_decline_authorizations(true);
+        let request_id = Uuid::new_v4();
+
+        assert!(service.pre_authorize(request_id, Uuid::new_v4(), &yuan("10.00")).await.is_err());
+        let bill = service.bill(request_id).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Failed);
+        assert!(bill.failure_reason.is_some());
+        assert!(service.capture(request_id, &yuan("10.00")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_declined_capture_fails_bill() {
+        let (provider, service) = service();
+        let request_id = Uuid::new_v4();
+        service.pre_authorize(request_id, Uuid::new_v4(), &yuan("10.00")).await.unwrap();
+
+        provider.set_decline_captures(true);
+        assert!(service.capture(request_id, &yuan("9.00")).await.is_err());
+        assert_eq!(service.bill(request_id).await.unwrap().status, BillStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_delayed_confirmation_via_webhook() {
+        let (provider, service) = service();
+        provider.set_delayed_confirmation(true);
+        let request_id = Uuid::new_v4();
+
+        let bill = service.pre_authorize(request_id, Uuid::new_v4(), &yuan("20.00")).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Pending);
+        let reference = bill.reference.unwrap();
+
+        // 预授权确认前充电已完成，只记录应付金额
+        let bill = service.capture(request_id, &yuan("18.50")).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Pending);
+
+        // 预授权确认后自动扣款，扣款仍需回调确认
+        let (payload, signature) = provider.webhook(&reference, WebhookEventKind::Authorized);
+        let bill = service.handle_webhook(&payload, &signature).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Authorized);
+
+        let (payload, signature) = provider.webhook(&reference, WebhookEventKind::Captured);
+        let bill = service.handle_webhook(&payload, &signature).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Paid);
+        assert_eq!(bill.captured_amount, yuan("18.50"));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_rejects_bad_signature() {
+        let (provider, service) = service();
+        provider.set_delayed_confirmation(true);
+        let request_id = Uuid::new_v4();
+        let bill = service.pre_authorize(request_id, Uuid::new_v4(), &yuan("20.00")).await.unwrap();
+
+        let (payload, _) = provider.webhook(bill.reference.as_deref().unwrap(), WebhookEventKind::Authorized);
+        assert!(service.handle_webhook(&payload, "forged").await.is_err());
+        assert_eq!(service.bill(request_id).await.unwrap().status, BillStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_top_up_waits_for_captured_webhook() {
+        let (provider, service) = service();
+        provider.set_delayed_confirmation(true);
+        let bill = service.start_top_up(Uuid::new_v4(), &yuan("100.004")).await.unwrap();
+        assert_eq!((bill.kind, bill.status), (BillKind::TopUp, BillStatus::Pending));
+        assert_eq!(bill.authorized_amount, yuan("100.00"));
+        let reference = bill.reference.unwrap();
+
+        // 预授权确认不入账，也不会自动扣款
+        let (payload, signature) = provider.webhook(&reference, WebhookEventKind::Authorized);
+        let confirmed = service.handle_webhook(&payload, &signature).await.unwrap();
+        assert_eq!(confirmed.status, BillStatus::Authorized);
+        assert_eq!(confirmed.captured_amount, zero_money());
+
+        // 扣款回调需在事务中入账钱包，入账失败时账单保持原状态，等待渠道重发
+        let (payload, signature) = provider.webhook(&reference, WebhookEventKind::Captured);
+        assert!(service.handle_webhook(&payload, &signature).await.is_err());
+        assert_eq!(service.bill(bill.request_id).await.unwrap().status, BillStatus::Authorized);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_releases_authorization() {
+        let (_, service) = service();
+        let request_id = Uuid::new_v4();
+        service.pre_authorize(request_id, Uuid::new_v4(), &yuan("20.00")).await.unwrap();
+
+        service.cancel(request_id).await;
+        let bill = service.bill(request_id).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Failed);
+        assert_eq!(bill.failure_reason.as_deref(), Some("充电请求已取消"));
+    }
+}
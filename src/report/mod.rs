@@ -0,0 +1,3 @@
+mod pile_report;
+
+pub use pile_report::{PileReport, PileReportRow, ReportPeriod};
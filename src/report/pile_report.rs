@@ -0,0 +1,202 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::models::{ChargingRecord, ChargingRecordFilter, ChargingRecordTotals, Money};
+
+/// 报表周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportPeriod {
+    Daily,   // 日报
+    Weekly,  // 周报（周一至周日）
+    Monthly, // 月报
+}
+
+impl ReportPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportPeriod::Daily => "Daily",
+            ReportPeriod::Weekly => "Weekly",
+            ReportPeriod::Monthly => "Monthly",
+        }
+    }
+
+    /// 某一日期所在周期的第一天
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            ReportPeriod::Daily => date,
+            ReportPeriod::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            ReportPeriod::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// 周期结束日期（不含）
+    pub fn period_end(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            ReportPeriod::Daily => start + Duration::days(1),
+            ReportPeriod::Weekly => start + Duration::days(7),
+            ReportPeriod::Monthly => start.checked_add_months(Months::new(1)).unwrap_or(start),
+        }
+    }
+}
+
+impl FromStr for ReportPeriod {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Daily" | "daily" | "day" => Ok(ReportPeriod::Daily),
+            "Weekly" | "weekly" | "week" => Ok(ReportPeriod::Weekly),
+            "Monthly" | "monthly" | "month" => Ok(ReportPeriod::Monthly),
+            _ => Err(format!("Invalid ReportPeriod: {}", s)),
+        }
+    }
+}
+
+/// 单个充电桩在报表周期内的运营数据
+#[derive(Debug, Clone, Serialize)]
+pub struct PileReportRow {
+    pub pile_id: String,
+    pub charge_count: i64,    // 充电次数
+    pub charging_time: f64,   // 充电总时长（小时）
+    pub charging_amount: f64, // 充电总电量（度）
+    pub charging_fee: Money,  // 电费
+    pub service_fee: Money,   // 服务费
+    pub idle_fee: Money,      // 超时占位费
+    pub discount_fee: Money,  // 优惠减免
+    pub total_fee: Money,     // 总费用
+    pub adjustment_fee: Money, // 账单调整退款
+    pub net_fee: Money,       // 实收金额
+}
+
+impl PileReportRow {
+    pub fn new(pile_id: String, totals: &ChargingRecordTotals) -> Self {
+        Self {
+            pile_id,
+            charge_count: totals.record_count,
+            charging_time: totals.charging_time,
+            charging_amount: totals.charging_amount,
+            charging_fee: totals.charging_fee.clone(),
+            service_fee: totals.service_fee.clone(),
+            idle_fee: totals.idle_fee.clone(),
+            discount_fee: totals.discount_fee.clone(),
+            total_fee: totals.total_fee.clone(),
+            adjustment_fee: totals.adjustment_fee.clone(),
+            net_fee: totals.net_fee(),
+        }
+    }
+}
+
+/// 充电桩运营报表：按充电开始时间（充电站当地日期）归属周期
+#[derive(Debug, Clone, Serialize)]
+pub struct PileReport {
+    pub period: ReportPeriod,
+    pub period_start: NaiveDate, // 周期开始日期（含）
+    pub period_end: NaiveDate,   // 周期结束日期（不含）
+    pub piles: Vec<PileReportRow>,
+    pub total: PileReportRow,    // 全部充电桩合计
+}
+
+impl PileReport {
+    /// 按充电桩汇总，pile_ids 中没有详单的充电桩以零值列出
+    pub fn from_totals(
+        period: ReportPeriod,
+        date: NaiveDate,
+        pile_ids: &[String],
+        by_pile: Vec<(String, ChargingRecordTotals)>,
+        total: &ChargingRecordTotals,
+    ) -> Self {
+        let period_start = period.period_start(date);
+        let mut piles: Vec<PileReportRow> = by_pile
+            .iter()
+            .map(|(pile_id, totals)| PileReportRow::new(pile_id.clone(), totals))
+            .collect();
+        for pile_id in pile_ids {
+            if !piles.iter().any(|p| &p.pile_id == pile_id) {
+                piles.push(PileReportRow::new(pile_id.clone(), &ChargingRecordTotals::empty()));
+            }
+        }
+        piles.sort_by(|a, b| a.pile_id.cmp(&b.pile_id));
+
+        Self {
+            period,
+            period_start,
+            period_end: period.period_end(period_start),
+            piles,
+            total: PileReportRow::new("合计".to_string(), total),
+        }
+    }
+
+    /// 生成包含日期 date 的周期报表，可只统计一个充电桩
+    pub async fn generate(
+        period: ReportPeriod,
+        date: NaiveDate,
+        pile_ids: &[String],
+        pile_id: Option<String>,
+        pool: &sqlx::MySqlPool,
+    ) -> Result<Self, sqlx::Error> {
+        let period_start = period.period_start(date);
+        let filter = ChargingRecordFilter {
+            pile_id: pile_id.clone(),
+            start_time: Some(period_start.and_time(NaiveTime::MIN)),
+            end_time: Some(period.period_end(period_start).and_time(NaiveTime::MIN)),
+            ..Default::default()
+        };
+        let by_pile = ChargingRecord::summarize_by_pile(&filter, pool).await?;
+        let total = ChargingRecord::summarize(&filter, pool).await?;
+
+        let pile_ids: Vec<String> = match pile_id {
+            Some(pile_id) => vec![pile_id],
+            None => pile_ids.to_vec(),
+        };
+        Ok(Self::from_totals(period, date, &pile_ids, by_pile, &total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_period_bounds() {
+        // 2024-03-06 为周三
+        let day = date(2024, 3, 6);
+        assert_eq!(ReportPeriod::Weekly.period_start(day), date(2024, 3, 4));
+        assert_eq!(ReportPeriod::Weekly.period_end(date(2024, 3, 4)), date(2024, 3, 11));
+        assert_eq!(ReportPeriod::Monthly.period_start(day), date(2024, 3, 1));
+        assert_eq!(ReportPeriod::Monthly.period_end(date(2024, 12, 1)), date(2025, 1, 1));
+        assert_eq!(ReportPeriod::Daily.period_end(day), date(2024, 3, 7));
+    }
+
+    #[test]
+    fn test_report_lists_idle_piles() {
+        let yuan = |s: &str| -> Money { s.parse().unwrap() };
+        let mut f1 = ChargingRecordTotals::empty();
+        f1.record_count = 2;
+        f1.charging_amount = 60.0;
+        f1.charging_time = 2.0;
+        f1.charging_fee = yuan("51.00");
+        f1.service_fee = yuan("48.00");
+        f1.total_fee = yuan("99.00");
+        f1.adjustment_fee = yuan("10.00");
+
+        let piles = ["F1".to_string(), "F2".to_string(), "T1".to_string()];
+        let report = PileReport::from_totals(
+            ReportPeriod::Daily,
+            date(2024, 3, 6),
+            &piles,
+            vec![("F1".to_string(), f1.clone())],
+            &f1,
+        );
+
+        let ids: Vec<_> = report.piles.iter().map(|p| p.pile_id.as_str()).collect();
+        assert_eq!(ids, ["F1", "F2", "T1"]);
+        assert_eq!(report.piles[0].charge_count, 2);
+        assert_eq!(report.piles[0].net_fee, yuan("89.00"));
+        assert_eq!(report.piles[1].charge_count, 0);
+        assert_eq!(report.total.total_fee, yuan("99.00"));
+    }
+}
@@ -0,0 +1,94 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::metrics;
+use charging_station::models::{zero_money, AdjustmentError, AuditAction, BillingAdjustment, Money};
+use crate::routes::audit_api::Auditor;
+use crate::routes::auth::UserPath;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct AdjustmentInput {
+    pub record_id: Uuid,
+    pub amount: Money,
+    pub reason: String,
+}
+
+/// 对充电详单退款，退款存入用户钱包，操作管理员取自访问令牌
+pub async fn create_adjustment(
+    pool: web::Data<MySqlPool>,
+    auditor: Auditor,
+    input: web::Json<AdjustmentInput>,
+) -> impl Responder {
+    // 审计日志记录该详单此前的累计退款
+    let refunded = BillingAdjustment::find_by_record_id(input.record_id, &pool)
+        .await
+        .ok()
+        .map(|adjustments| adjustments.iter().fold(zero_money(), |total, a| total + &a.amount));
+    match BillingAdjustment::create(input.record_id, &input.amount, &input.reason, auditor.user.user_id, &pool).await {
+        Ok(adjustment) => {
+            auditor
+                .record(AuditAction::Refund, adjustment.record_id, refunded.map(|r| json!({ "refunded": r })), &adjustment)
+                .await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": adjustment
+            }))
+        }
+        Err(e) => {
+            println!("❌ 账单调整失败: {}", e);
+            let body = json!({
+                "success": false,
+                "message": e.to_string()
+            });
+            match e {
+                AdjustmentError::Invalid(_) => HttpResponse::BadRequest().json(body),
+                AdjustmentError::Forbidden(_) => HttpResponse::Forbidden().json(body),
+                AdjustmentError::NotFound(_) => HttpResponse::NotFound().json(body),
+                AdjustmentError::Database(_) => {
+                    metrics::db_error("adjustment");
+                    HttpResponse::InternalServerError().json(body)
+                }
+            }
+        }
+    }
+}
+
+fn adjustments_response(result: Result<Vec<BillingAdjustment>, sqlx::Error>) -> HttpResponse {
+    match result {
+        Ok(adjustments) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": adjustments,
+            "count": adjustments.len()
+        })),
+        Err(e) => {
+            println!("❌ 查询账单调整失败: {}", e);
+            metrics::db_error("adjustment");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询账单调整失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 查询某条详单的调整记录
+pub async fn get_record_adjustments(pool: web::Data<MySqlPool>, path: web::Path<Uuid>) -> impl Responder {
+    adjustments_response(BillingAdjustment::find_by_record_id(path.into_inner(), &pool).await)
+}
+
+/// 查询用户的调整记录
+pub async fn get_user_adjustments(pool: web::Data<MySqlPool>, path: UserPath) -> impl Responder {
+    adjustments_response(BillingAdjustment::find_by_user_id(path.0, &pool).await)
+}
+
+/// 配置账单调整路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/adjustments")
+            .route("", web::post().to(create_adjustment))
+            .route("/record/{record_id}", web::get().to(get_record_adjustments))
+            .route("/user/{user_id}", web::get().to(get_user_adjustments))
+    );
+}
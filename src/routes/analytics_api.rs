@@ -0,0 +1,127 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::analytics::{UtilizationReport, WaitTimeReport};
+use charging_station::metrics;
+use charging_station::models::{to_station_local, ChargingMode};
+use charging_station::scheduler::ChargingScheduler;
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+
+// 未指定开始日期时默认统计最近7天
+const DEFAULT_RANGE_DAYS: i64 = 7;
+
+#[derive(Debug, Deserialize)]
+pub struct WaitTimeQuery {
+    pub start_date: Option<NaiveDate>, // 充电站当地日期，默认结束日期前6天
+    pub end_date: Option<NaiveDate>,   // 含当天，默认今天
+    pub mode: Option<String>,          // Fast / Slow，默认全部
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UtilizationQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub pile_id: Option<String>, // 只统计一个充电桩
+}
+
+fn bad_request(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+/// 解析统计日期区间，缺省时为截至今天的最近7天
+fn resolve_range(
+    scheduler: &ChargingScheduler,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<(NaiveDate, NaiveDate), String> {
+    let today = to_station_local(scheduler.queue_manager.time_system.current_time()).date();
+    let end_date = end_date.unwrap_or(today);
+    let start_date = start_date.unwrap_or(end_date - Duration::days(DEFAULT_RANGE_DAYS - 1));
+    if start_date > end_date {
+        return Err("开始日期不能晚于结束日期".to_string());
+    }
+    Ok((start_date, end_date))
+}
+
+/// 等候区和充电桩队列等待时长统计
+pub async fn get_wait_times(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    query: web::Query<WaitTimeQuery>,
+) -> impl Responder {
+    let (start_date, end_date) = match resolve_range(&scheduler, query.start_date, query.end_date) {
+        Ok(range) => range,
+        Err(message) => return bad_request(message),
+    };
+    let mode = match query.mode.as_deref().map(str::parse::<ChargingMode>).transpose() {
+        Ok(mode) => mode,
+        Err(message) => return bad_request(message),
+    };
+
+    match WaitTimeReport::generate(start_date, end_date, mode, &pool).await {
+        Ok(report) => {
+            println!(
+                "⏳ 等待时长统计 {} ~ {}: {} 个请求，等候区平均 {} 分钟，充电桩队列平均 {} 分钟",
+                start_date, end_date, report.waiting_area.count, report.waiting_area.average, report.pile_queue.average
+            );
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": report
+            }))
+        }
+        Err(e) => {
+            println!("❌ 统计等待时长失败: {}", e);
+            metrics::db_error("analytics");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("统计等待时长失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 充电桩分时段利用率统计
+pub async fn get_utilization(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    query: web::Query<UtilizationQuery>,
+) -> impl Responder {
+    let (start_date, end_date) = match resolve_range(&scheduler, query.start_date, query.end_date) {
+        Ok(range) => range,
+        Err(message) => return bad_request(message),
+    };
+    let now = scheduler.queue_manager.time_system.current_time();
+    let pile_ids: Vec<String> = scheduler.queue_manager.pile_infos.read().await.keys().cloned().collect();
+
+    match UtilizationReport::generate(start_date, end_date, now, &pile_ids, query.pile_id.clone(), &pool).await {
+        Ok(report) => {
+            println!("📈 充电桩利用率统计 {} ~ {}: {} 个充电桩", start_date, end_date, report.piles.len());
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": report
+            }))
+        }
+        Err(e) => {
+            println!("❌ 统计充电桩利用率失败: {}", e);
+            metrics::db_error("analytics");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("统计充电桩利用率失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 配置运营分析路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/analytics")
+            .route("/wait-times", web::get().to(get_wait_times))
+            .route("/utilization", web::get().to(get_utilization))
+    );
+}
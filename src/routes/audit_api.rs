@@ -0,0 +1,211 @@
+use actix_web::dev::Payload;
+use actix_web::{web, Error, FromRequest, HttpRequest, HttpResponse, Responder};
+use charging_station::metrics;
+use charging_station::models::{to_station_local, AuditAction, AuditLog, AuditLogFilter, ChargingRequest};
+use crate::routes::auth::{auth_user, AuthUser};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::env;
+use std::future::{ready, Ready};
+use std::net::IpAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+/// 受信任的反向代理地址，只有来自这些地址的请求才采用 X-Forwarded-For
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpAddr>);
+
+impl TrustedProxies {
+    /// 从环境变量 TRUSTED_PROXIES 读取，逗号分隔的 IP 地址，未设置时不信任任何代理
+    pub fn from_env() -> Self {
+        let proxies = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|addr| !addr.is_empty())
+            .filter_map(|addr| match addr.parse() {
+                Ok(ip) => Some(ip),
+                Err(_) => {
+                    println!("⚠️ 忽略无效的受信任代理地址: {}", addr);
+                    None
+                }
+            })
+            .collect();
+        TrustedProxies(proxies)
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.contains(ip)
+    }
+
+    /// 请求的来源 IP：连接对端不是受信任代理时即为对端地址；
+    /// 否则从 X-Forwarded-For 右侧起跳过受信任代理，取第一个不受信任的地址
+    fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        if !self.contains(&peer) {
+            return peer;
+        }
+        let mut client = peer;
+        for addr in forwarded_for.unwrap_or_default().rsplit(',').map(str::trim) {
+            let Ok(ip) = addr.parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !self.contains(&ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// 记录管理操作的审计日志，操作人取自访问令牌，来源 IP 取自连接对端（受信任代理转发时取 X-Forwarded-For）
+pub struct Auditor {
+    pub user: AuthUser,
+    source_ip: Option<String>,
+    pool: web::Data<MySqlPool>,
+}
+
+impl Auditor {
+    /// 写入一条审计日志；写入失败只打印错误，不影响已完成的操作
+    pub async fn record(&self, action: AuditAction, target_id: impl ToString, before: impl Serialize, after: impl Serialize) {
+        let entry = AuditLog::new(self.user.user_id, self.user.role, action, target_id)
+            .with_before(before)
+            .with_after(after)
+            .with_source_ip(self.source_ip.clone());
+        match entry.insert(&self.pool).await {
+            Ok(()) => println!(
+                "📝 审计: {}（{}）{} {}",
+                entry.actor_id,
+                entry.actor_role.as_str(),
+                entry.action.as_str(),
+                entry.target_id
+            ),
+            Err(e) => {
+                println!("❌ 写入审计日志失败: {} {}: {}", entry.action.as_str(), entry.target_id, e);
+                metrics::db_error("audit");
+            }
+        }
+    }
+
+    /// 管理人员修改或取消其他用户的充电请求时记录，车主操作自己的请求不记录
+    pub async fn record_request_change(
+        &self,
+        action: AuditAction,
+        before: Option<Arc<ChargingRequest>>,
+        after: Option<Arc<ChargingRequest>>,
+    ) {
+        if let Some(before) = before.filter(|r| r.user_id != self.user.user_id) {
+            self.record(action, before.id, &before, after).await;
+        }
+    }
+}
+
+impl FromRequest for Auditor {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let user = match auth_user(req) {
+            Ok(user) => user,
+            Err(e) => return ready(Err(e)),
+        };
+        let Some(pool) = req.app_data::<web::Data<MySqlPool>>().cloned() else {
+            return ready(Err(actix_web::error::ErrorInternalServerError("未配置数据库连接")));
+        };
+        let trusted_proxies = req
+            .app_data::<web::Data<TrustedProxies>>()
+            .map(|proxies| proxies.get_ref().clone())
+            .unwrap_or_default();
+        let forwarded_for = req.headers().get("X-Forwarded-For").and_then(|v| v.to_str().ok());
+        let source_ip = req
+            .peer_addr()
+            .map(|peer| trusted_proxies.client_ip(peer.ip(), forwarded_for).to_string());
+        ready(Ok(Auditor { user, source_ip, pool }))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub actor_id: Option<Uuid>,
+    pub action: Option<String>,      // 如 pile_shutdown、tariff_update
+    pub target_type: Option<String>, // pile / scheduler / tariff / charging_record 等
+    pub target_id: Option<String>,   // 充电桩编号、电价方案ID等
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub page: Option<u32>,      // 页码，从1开始
+    pub page_size: Option<u32>, // 每页条数，默认50，最多200
+}
+
+/// 按操作人、操作、对象和时间范围查询审计日志，最新的在前
+pub async fn get_audit_logs(
+    pool: web::Data<MySqlPool>,
+    query: web::Query<AuditLogQuery>,
+) -> impl Responder {
+    let action = match query.action.as_deref().map(str::parse::<AuditAction>).transpose() {
+        Ok(action) => action,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": message
+            }))
+        }
+    };
+    let filter = AuditLogFilter {
+        actor_id: query.actor_id,
+        action,
+        target_type: query.target_type.clone(),
+        target_id: query.target_id.clone(),
+        start_time: query.start_time.map(to_station_local),
+        end_time: query.end_time.map(to_station_local),
+    };
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    match AuditLog::find(&filter, page_size, (page - 1).saturating_mul(page_size), &pool).await {
+        Ok(logs) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "count": logs.len(),
+            "data": logs,
+            "page": page,
+            "page_size": page_size
+        })),
+        Err(e) => {
+            println!("❌ 查询审计日志失败: {}", e);
+            metrics::db_error("audit");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询审计日志失败: {}", e)
+            }))
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/admin/audit-logs").route("", web::get().to(get_audit_logs)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ip() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let proxies = TrustedProxies(vec![ip("10.0.0.1"), ip("10.0.0.2")]);
+
+        // 非受信任对端伪造的 X-Forwarded-For 不采用
+        assert_eq!(proxies.client_ip(ip("203.0.113.9"), Some("1.2.3.4")), ip("203.0.113.9"));
+        assert_eq!(TrustedProxies::default().client_ip(ip("10.0.0.1"), Some("1.2.3.4")), ip("10.0.0.1"));
+
+        // 经受信任代理转发时跳过代理链，客户端自带的伪造前缀不影响结果
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), Some("6.6.6.6, 198.51.100.7")), ip("198.51.100.7"));
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), Some("198.51.100.7, 10.0.0.2")), ip("198.51.100.7"));
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), None), ip("10.0.0.1"));
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), Some("garbage")), ip("10.0.0.1"));
+    }
+}
@@ -0,0 +1,238 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::{header, Method};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use charging_station::auth::{TokenKind, TokenSigner};
+use charging_station::models::{Permission, Role};
+use charging_station::scheduler::ChargingScheduler;
+use chrono::Utc;
+use serde_json::json;
+use std::future::{ready, Ready};
+use uuid::Uuid;
+
+// 无需令牌的路由：登录注册、Prometheus 抓取，支付渠道回调由签名校验
+const PUBLIC_PATHS: &[&str] = &["/login", "/register", "/refresh", "/metrics", "/api/payments/webhook"];
+
+/// 通过访问令牌认证的调用者
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub user_id: Uuid,
+    pub role: Role,
+}
+
+impl AuthUser {
+    /// 调用者本人或车主以外的角色可以访问该用户的数据
+    pub fn can_access(&self, user_id: Uuid) -> bool {
+        self.role.is_staff() || self.user_id == user_id
+    }
+
+    /// 查询条件中的用户：管理角色可查任意用户或全部，车主只能查自己
+    pub fn scope_user(&self, requested: Option<Uuid>) -> Result<Option<Uuid>, HttpResponse> {
+        match requested {
+            _ if self.role.is_staff() => Ok(requested),
+            Some(user_id) if user_id != self.user_id => Err(forbidden()),
+            _ => Ok(Some(self.user_id)),
+        }
+    }
+
+    /// 检查充电请求是否属于调用者；请求不存在时交由后续处理返回错误
+    pub async fn check_request(&self, scheduler: &ChargingScheduler, request_id: Uuid) -> Result<(), HttpResponse> {
+        match scheduler.request_owner(request_id).await {
+            Some(owner) if !self.can_access(owner) => Err(forbidden()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn unauthorized(message: &str) -> HttpResponse {
+    HttpResponse::Unauthorized().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+pub fn forbidden() -> HttpResponse {
+    HttpResponse::Forbidden().json(json!({
+        "success": false,
+        "message": "无权访问其他用户的数据"
+    }))
+}
+
+fn permission_denied(permission: Permission) -> HttpResponse {
+    HttpResponse::Forbidden().json(json!({
+        "success": false,
+        "message": "当前角色没有该操作的权限",
+        "required_permission": permission
+    }))
+}
+
+/// 接口的访问要求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Authenticated,        // 登录即可
+    Requires(Permission), // 角色须具有该权限
+    Unlisted,             // 未登记的修改类接口，一律拒绝
+}
+
+/// 各接口的访问要求：管理接口按权限分组，车主可调用的修改类接口逐一登记，
+/// 其余修改类接口默认拒绝，新增接口须在这里登记后才能调用
+fn route_access(method: &Method, path: &str) -> Access {
+    let read_only = *method == Method::GET;
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let permission = match (read_only, segments.as_slice()) {
+        (false, ["piles", _, "start" | "shutdown"]) => Some(Permission::PileControl),
+        (false, ["api", "scheduler", "piles", _, "fault" | "recovery" | "unplug"]) => Some(Permission::PileControl),
+        (false, ["api", "scheduler", "start" | "stop"]) => Some(Permission::SchedulerControl),
+        (true, ["api", "charging-requests"]) => Some(Permission::SchedulerControl),
+        (_, ["api", "admin", "reports" | "analytics", ..]) => Some(Permission::Reports),
+        (_, ["api", "admin", "audit-logs", ..]) => Some(Permission::Audit),
+        (_, ["api", "export", "reports", ..]) => Some(Permission::Reports),
+        (false, ["api", "admin", "tariffs" | "service-rates", ..]) => Some(Permission::Billing),
+        (_, ["api", "admin", "promotions" | "adjustments", ..]) => Some(Permission::Billing),
+        (false, ["api", "bills", "generate"]) => Some(Permission::Billing),
+        (false, ["api", "wallet", _, "top-up"]) => Some(Permission::Billing),
+        (true, ["users"]) => Some(Permission::UserManagement),
+        (false, ["users", user_id, ..]) if *user_id != "me" => Some(Permission::UserManagement),
+        _ => None,
+    };
+    if let Some(permission) = permission {
+        return Access::Requires(permission);
+    }
+    if read_only {
+        return Access::Authenticated;
+    }
+    match segments.as_slice() {
+        ["users", "me", "password"]
+        | ["api", "charging-requests", ..]
+        | ["api", "scheduler", "submit" | "update" | "cancel", ..]
+        | ["api", "wallet", _, "top-up-payments"] => Access::Authenticated,
+        _ => Access::Unlisted,
+    }
+}
+
+/// 校验 Authorization: Bearer 访问令牌
+fn authenticate(req: &HttpRequest) -> Result<AuthUser, String> {
+    let signer = req
+        .app_data::<web::Data<TokenSigner>>()
+        .ok_or("未配置令牌签发")?;
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or("缺少访问令牌")?;
+    let claims = signer.verify(token.trim(), TokenKind::Access, Utc::now())?;
+    Ok(AuthUser { user_id: claims.sub, role: claims.role })
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(auth_user(req))
+    }
+}
+
+/// 取认证中间件的结果，未经过中间件的公开路由在这里校验令牌
+pub(crate) fn auth_user(req: &HttpRequest) -> Result<AuthUser, Error> {
+    let result = match req.extensions().get::<AuthUser>() {
+        Some(user) => Ok(*user),
+        None => authenticate(req),
+    };
+    result.map_err(|message| InternalError::from_response(message.clone(), unauthorized(&message)).into())
+}
+
+/// 路径中的 {user_id}，只允许本人或管理员访问
+#[derive(Debug, Clone, Copy)]
+pub struct UserPath(pub Uuid);
+
+impl FromRequest for UserPath {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let user = match auth_user(req) {
+            Ok(user) => user,
+            Err(e) => return ready(Err(e)),
+        };
+        let result = match req.match_info().get("user_id").map(Uuid::parse_str) {
+            Some(Ok(user_id)) if user.can_access(user_id) => Ok(UserPath(user_id)),
+            Some(Ok(_)) => Err(InternalError::from_response("forbidden", forbidden()).into()),
+            _ => Err(actix_web::error::ErrorBadRequest("无效的用户ID")),
+        };
+        ready(result)
+    }
+}
+
+/// 认证中间件：除公开路由外都需要有效的访问令牌，管理接口还要求角色具有对应权限
+pub async fn require_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if req.method() == Method::OPTIONS || PUBLIC_PATHS.contains(&req.path()) {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+    let user = match authenticate(req.request()) {
+        Ok(user) => user,
+        Err(message) => {
+            println!("🔒 拒绝未认证的请求 {}: {}", req.path(), message);
+            return Ok(req.into_response(unauthorized(&message)).map_into_right_body());
+        }
+    };
+    match route_access(req.method(), req.path()) {
+        Access::Authenticated => {}
+        Access::Requires(permission) if user.role.has(permission) => {}
+        Access::Requires(permission) => {
+            println!("🔒 用户 {}（{}）无权调用 {} {}", user.user_id, user.role.as_str(), req.method(), req.path());
+            return Ok(req.into_response(permission_denied(permission)).map_into_right_body());
+        }
+        Access::Unlisted => {
+            println!("🔒 拒绝调用未登记的接口 {} {}", req.method(), req.path());
+            let response = HttpResponse::Forbidden().json(json!({
+                "success": false,
+                "message": "接口未登记访问权限"
+            }));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+    req.extensions_mut().insert(user);
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_access() {
+        let post = Method::POST;
+        let get = Method::GET;
+        let requires = Access::Requires;
+        assert_eq!(route_access(&post, "/piles/P1/shutdown"), requires(Permission::PileControl));
+        assert_eq!(route_access(&post, "/api/scheduler/piles/F1/fault"), requires(Permission::PileControl));
+        assert_eq!(route_access(&post, "/api/scheduler/stop"), requires(Permission::SchedulerControl));
+        assert_eq!(route_access(&get, "/api/admin/analytics/utilization"), requires(Permission::Reports));
+        assert_eq!(route_access(&Method::PUT, "/api/admin/tariffs/1"), requires(Permission::Billing));
+        assert_eq!(route_access(&get, "/users"), requires(Permission::UserManagement));
+        assert_eq!(route_access(&get, "/api/admin/audit-logs"), requires(Permission::Audit));
+        assert_eq!(route_access(&Method::PUT, "/users/42/role"), requires(Permission::UserManagement));
+        assert_eq!(route_access(&post, "/api/wallet/42/top-up"), requires(Permission::Billing));
+
+        // 车主自己的操作和只读的公共信息不需要管理权限
+        assert_eq!(route_access(&post, "/users/me/password"), Access::Authenticated);
+        assert_eq!(route_access(&get, "/users/42/charging_records"), Access::Authenticated);
+        assert_eq!(route_access(&post, "/api/scheduler/submit"), Access::Authenticated);
+        assert_eq!(route_access(&Method::DELETE, "/api/charging-requests/42"), Access::Authenticated);
+        assert_eq!(route_access(&post, "/api/wallet/42/top-up-payments"), Access::Authenticated);
+        assert_eq!(route_access(&get, "/api/admin/tariffs"), Access::Authenticated);
+        assert_eq!(route_access(&get, "/piles"), Access::Authenticated);
+
+        // 未登记的修改类接口默认拒绝
+        assert_eq!(route_access(&post, "/api/api/charging-records/test-insert"), Access::Unlisted);
+        assert_eq!(route_access(&post, "/api/scheduler/test-completion"), Access::Unlisted);
+        assert_eq!(route_access(&Method::DELETE, "/api/wallet/42"), Access::Unlisted);
+    }
+}
@@ -1,16 +1,30 @@
 use actix_web::{web, HttpResponse, Responder};
-use charging_station::billing::{BillingRecord, FeeCalculator};
-use charging_station::models::ChargingRecord;
+use charging_station::billing::BillingRecord;
+use charging_station::metrics;
+use charging_station::models::{
+    to_station_local, ChargingRecord, ChargingRecordFilter, ChargingRecordQuery, Money, RecordSortField,
+};
+use crate::routes::auth::{AuthUser, UserPath};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::MySqlPool;
 use chrono::{DateTime, Utc};
 
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
 #[derive(Debug, Serialize)]
 pub struct BillingSummary {
     pub total_records: usize,
     pub total_charge_amount: f64,
-    pub total_electricity_fee: f64,
-    pub total_service_fee: f64,
+    pub total_electricity_fee: Money,
+    pub total_service_fee: Money,
+    pub total_idle_fee: Money,
+    pub total_discount_fee: Money,
+    pub total_fee: Money,
+    pub total_adjustment_fee: Money, // 管理员累计退款
+    pub net_fee: Money,              // 扣除退款后的实收金额
     pub records: Vec<BillingRecord>,
 }
 
@@ -20,34 +34,187 @@ pub struct BillingQuery {
     pub pile_id: Option<String>,
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
+    pub page: Option<u32>,         // 页码，从1开始
+    pub page_size: Option<u32>,    // 每页条数，默认20，最多100
+    pub sort_by: Option<String>,   // start_time / end_time / charging_amount / total_fee / created_at
+    pub order: Option<String>,     // asc / desc，默认 desc
+}
+
+impl BillingQuery {
+    /// 转换为详单查询：时间换算为充电站当地时间，校验分页和排序参数
+    fn to_record_query(&self) -> Result<ChargingRecordQuery, String> {
+        let sort_by = match &self.sort_by {
+            Some(field) => field.parse()?,
+            None => RecordSortField::default(),
+        };
+        let descending = match self.order.as_deref() {
+            None | Some("desc") => true,
+            Some("asc") => false,
+            Some(order) => return Err(format!("不支持的排序方向: {}", order)),
+        };
+        let page = self.page.unwrap_or(1).max(1);
+        let page_size = self.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+        Ok(ChargingRecordQuery {
+            filter: ChargingRecordFilter {
+                user_id: self.user_id,
+                pile_id: self.pile_id.clone(),
+                start_time: self.start_time.map(to_station_local),
+                end_time: self.end_time.map(to_station_local),
+            },
+            sort_by,
+            descending,
+            limit: page_size,
+            offset: (page - 1).saturating_mul(page_size),
+        })
+    }
+}
+
+fn bad_request(message: String) -> HttpResponse {
+    println!("❌ 账单查询参数错误: {}", message);
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+/// 查询一页详单及符合条件的总数
+async fn query_records(query: &ChargingRecordQuery, pool: &MySqlPool) -> HttpResponse {
+    let result = async {
+        let totals = ChargingRecord::summarize(&query.filter, pool).await?;
+        let records = ChargingRecord::find(query, pool).await?;
+        Ok::<_, sqlx::Error>((totals.record_count, records))
+    }
+    .await;
+
+    match result {
+        Ok((total, records)) => {
+            println!("✅ 查询到 {} 条账单记录（共 {} 条）", records.len(), total);
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": records,
+                "count": records.len(),
+                "total": total,
+                "page": query.offset / query.limit + 1,
+                "page_size": query.limit
+            }))
+        }
+        Err(e) => {
+            println!("❌ 查询账单记录失败: {}", e);
+            metrics::db_error("billing");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询账单记录失败: {}", e)
+            }))
+        }
+    }
 }
 
 pub async fn get_billing_records(
+    auth: AuthUser,
     query: web::Query<BillingQuery>,
+    pool: web::Data<MySqlPool>,
 ) -> impl Responder {
-    // TODO: 实现从数据库查询账单记录
-    HttpResponse::Ok().json("获取账单记录")
+    match query.to_record_query() {
+        Ok(mut record_query) => {
+            record_query.filter.user_id = match auth.scope_user(record_query.filter.user_id) {
+                Ok(user_id) => user_id,
+                Err(response) => return response,
+            };
+            query_records(&record_query, &pool).await
+        }
+        Err(message) => bad_request(message),
+    }
 }
 
 pub async fn get_user_billing_records(
-    user_id: web::Path<Uuid>,
+    user_id: UserPath,
+    query: web::Query<BillingQuery>,
+    pool: web::Data<MySqlPool>,
 ) -> impl Responder {
-    // TODO: 实现从数据库查询用户账单记录
-    HttpResponse::Ok().json("获取用户账单记录")
+    match query.to_record_query() {
+        Ok(mut record_query) => {
+            record_query.filter.user_id = Some(user_id.0);
+            query_records(&record_query, &pool).await
+        }
+        Err(message) => bad_request(message),
+    }
 }
 
 pub async fn get_pile_billing_records(
+    auth: AuthUser,
     pile_id: web::Path<String>,
+    query: web::Query<BillingQuery>,
+    pool: web::Data<MySqlPool>,
 ) -> impl Responder {
-    // TODO: 实现从数据库查询充电桩账单记录
-    HttpResponse::Ok().json("获取充电桩账单记录")
+    match query.to_record_query() {
+        Ok(mut record_query) => {
+            record_query.filter.user_id = match auth.scope_user(record_query.filter.user_id) {
+                Ok(user_id) => user_id,
+                Err(response) => return response,
+            };
+            record_query.filter.pile_id = Some(pile_id.into_inner());
+            query_records(&record_query, &pool).await
+        }
+        Err(message) => bad_request(message),
+    }
 }
 
+/// 账单统计：合计覆盖全部符合条件的详单，records 为按分页参数取出的一页
 pub async fn get_billing_summary(
+    auth: AuthUser,
     query: web::Query<BillingQuery>,
+    pool: web::Data<MySqlPool>,
 ) -> impl Responder {
-    // TODO: 实现从数据库查询账单统计信息
-    HttpResponse::Ok().json("获取账单统计信息")
+    let mut record_query = match query.to_record_query() {
+        Ok(record_query) => record_query,
+        Err(message) => return bad_request(message),
+    };
+    record_query.filter.user_id = match auth.scope_user(record_query.filter.user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+
+    let result = async {
+        let totals = ChargingRecord::summarize(&record_query.filter, &pool).await?;
+        let records = ChargingRecord::find(&record_query, &pool).await?;
+        Ok::<_, sqlx::Error>((totals, records))
+    }
+    .await;
+
+    match result {
+        Ok((totals, records)) => {
+            let net_fee = totals.net_fee();
+            let summary = BillingSummary {
+                total_records: totals.record_count as usize,
+                total_charge_amount: totals.charging_amount,
+                total_electricity_fee: totals.charging_fee,
+                total_service_fee: totals.service_fee,
+                total_idle_fee: totals.idle_fee,
+                total_discount_fee: totals.discount_fee,
+                total_fee: totals.total_fee,
+                total_adjustment_fee: totals.adjustment_fee,
+                net_fee,
+                records: records.iter().map(BillingRecord::from).collect(),
+            };
+            println!(
+                "✅ 账单统计: {} 条记录，总费用 {}元，实收 {}元",
+                summary.total_records, summary.total_fee, summary.net_fee
+            );
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": summary
+            }))
+        }
+        Err(e) => {
+            println!("❌ 查询账单统计失败: {}", e);
+            metrics::db_error("billing");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询账单统计失败: {}", e)
+            }))
+        }
+    }
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
@@ -58,4 +225,4 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("/pile/{pile_id}", web::get().to(get_pile_billing_records))
             .route("/summary", web::get().to(get_billing_summary))
     );
-} 
\ No newline at end of file
+}
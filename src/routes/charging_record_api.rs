@@ -1,15 +1,17 @@
 use actix_web::{web, HttpResponse, Result};
+use charging_station::metrics;
 use sqlx::MySqlPool;
 use uuid::Uuid;
-use crate::models::ChargingRecord;
+use charging_station::models::{ChargingRecord, MeterSample};
+use crate::routes::auth::{forbidden, AuthUser, UserPath};
 use serde_json::json;
 
 /// 根据用户ID获取充电详单
 pub async fn get_user_charging_records(
-    path: web::Path<Uuid>,
+    path: UserPath,
     pool: web::Data<MySqlPool>,
 ) -> Result<HttpResponse> {
-    let user_id = path.into_inner();
+    let user_id = path.0;
     
     match ChargingRecord::find_by_user_id(user_id, &pool).await {
         Ok(records) => {
@@ -22,6 +24,7 @@ pub async fn get_user_charging_records(
         }
         Err(e) => {
             println!("❌ 查询充电详单失败: {}", e);
+            metrics::db_error("charging_record");
             Ok(HttpResponse::InternalServerError().json(json!({
                 "success": false,
                 "message": format!("查询充电详单失败: {}", e)
@@ -30,40 +33,40 @@ pub async fn get_user_charging_records(
     }
 }
 
-/// 测试插入充电详单（仅用于调试）
-pub async fn test_insert_record(
+/// 获取某条充电详单的电表采样曲线
+pub async fn get_record_meter_values(
+    path: web::Path<Uuid>,
     pool: web::Data<MySqlPool>,
-) -> Result<HttpResponse, actix_web::Error> {
-    use crate::models::ChargingMode;
-    use chrono::Utc;
-    
-    // 创建一个测试充电详单
-    let test_record = ChargingRecord::new(
-        Uuid::new_v4(), // 随机用户ID
-        "TEST_PILE".to_string(),
-        ChargingMode::Fast,
-        10.0, // 充电量
-        0.5,  // 充电时长
-        8.0,  // 充电费用
-        2.0,  // 服务费用
-        Utc::now() - chrono::Duration::hours(1), // 开始时间
-        Utc::now(), // 结束时间
-    );
-    
-    match test_record.insert(&pool).await {
-        Ok(_) => {
-            println!("✅ 测试充电详单插入成功");
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    let record_id = path.into_inner();
+
+    let result = async {
+        // 详单不存在时返回空曲线
+        let records = ChargingRecord::find_by_ids(&[record_id], &pool).await?;
+        if records.iter().any(|record| !auth.can_access(record.user_id)) {
+            return Ok::<_, sqlx::Error>(None);
+        }
+        MeterSample::find_by_record_id(record_id, &pool).await.map(Some)
+    }
+    .await;
+
+    match result {
+        Ok(None) => Ok(forbidden()),
+        Ok(Some(samples)) => {
+            println!("✅ 查询到充电详单 {} 的 {} 个电表采样", record_id, samples.len());
             Ok(HttpResponse::Ok().json(json!({
                 "success": true,
-                "message": "测试充电详单插入成功",
-                "record_id": test_record.id
+                "data": samples,
+                "count": samples.len()
             })))
         }
         Err(e) => {
-            println!("❌ 测试充电详单插入失败: {}", e);
+            println!("❌ 查询电表采样失败: {}", e);
+            metrics::db_error("charging_record");
             Ok(HttpResponse::InternalServerError().json(json!({
                 "success": false,
-                "message": format!("测试插入失败: {}", e)
+                "message": format!("查询电表采样失败: {}", e)
             })))
         }
     }
@@ -74,6 +77,6 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api/charging-records")
             .route("/user/{user_id}", web::get().to(get_user_charging_records))
-            .route("/test-insert", web::post().to(test_insert_record))
+            .route("/{id}/meter-values", web::get().to(get_record_meter_values))
     );
 } 
\ No newline at end of file
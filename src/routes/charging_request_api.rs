@@ -1,7 +1,9 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
-use charging_station::models::{ChargingMode, ChargingRequest, RequestStatus};
+use charging_station::models::{AuditAction, ChargingMode, ChargingRequest, RequestStatus};
+use crate::routes::audit_api::Auditor;
+use crate::routes::auth::{AuthUser, UserPath};
 use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
 use chrono::Utc;
 use lazy_static::lazy_static;
@@ -13,9 +15,9 @@ use uuid::Uuid;
 // 请求结构体
 #[derive(Debug, Deserialize)]
 pub struct CreateChargingRequestPayload {
-    pub user_id: Uuid,
     pub mode: ChargingMode,
     pub amount: f64,
+    pub coupon_code: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -67,14 +69,16 @@ fn generate_queue_number(mode: ChargingMode, position: usize) -> String {
 #[post("/charging-requests")]
 pub async fn create_charging_request(
     scheduler: web::Data<Arc<charging_station::scheduler::ChargingScheduler>>,
+    auth: AuthUser,
     payload: web::Json<CreateChargingRequestPayload>,
 ) -> impl Responder {
     let request = charging_station::models::ChargingRequest::new(
-        payload.user_id,
+        auth.user_id,
         payload.mode,
         payload.amount,
         "".to_string(),
-    );
+    )
+    .with_coupon(payload.coupon_code.clone());
     match scheduler.submit_request(request.clone()).await {
         Ok(_) => HttpResponse::Ok().json(ApiResponse::success(request, "充电请求创建成功")),
         Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e)),
@@ -118,12 +122,21 @@ pub async fn update_charging_mode(
     path: web::Path<Uuid>,
     payload: web::Json<UpdateChargingModePayload>,
     scheduler: web::Data<Arc<charging_station::scheduler::ChargingScheduler>>,
+    auditor: Auditor,
 ) -> impl Responder {
     let request_id = path.into_inner();
+    if let Err(response) = auditor.user.check_request(&scheduler, request_id).await {
+        return response;
+    }
     let new_mode = payload.mode;
     let new_queue_number = payload.queue_number.clone();
+    let before = scheduler.find_request(request_id).await;
     match scheduler.update_request_mode(request_id, new_mode, new_queue_number).await {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::success((), "充电模式修改成功")),
+        Ok(_) => {
+            let after = scheduler.find_request(request_id).await;
+            auditor.record_request_change(AuditAction::RequestUpdate, before, after).await;
+            HttpResponse::Ok().json(ApiResponse::success((), "充电模式修改成功"))
+        }
         Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e)),
     }
 }
@@ -134,11 +147,20 @@ pub async fn update_charging_amount(
     path: web::Path<Uuid>,
     payload: web::Json<UpdateChargingAmountPayload>,
     scheduler: web::Data<Arc<charging_station::scheduler::ChargingScheduler>>,
+    auditor: Auditor,
 ) -> impl Responder {
     let request_id = path.into_inner();
+    if let Err(response) = auditor.user.check_request(&scheduler, request_id).await {
+        return response;
+    }
     let new_amount = payload.amount;
+    let before = scheduler.find_request(request_id).await;
     match scheduler.update_request_amount(request_id, new_amount).await {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::success((), "充电量修改成功")),
+        Ok(_) => {
+            let after = scheduler.find_request(request_id).await;
+            auditor.record_request_change(AuditAction::RequestUpdate, before, after).await;
+            HttpResponse::Ok().json(ApiResponse::success((), "充电量修改成功"))
+        }
         Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e)),
     }
 }
@@ -148,10 +170,18 @@ pub async fn update_charging_amount(
 pub async fn cancel_charging_request(
     scheduler: web::Data<Arc<charging_station::scheduler::ChargingScheduler>>,
     path: web::Path<Uuid>,
+    auditor: Auditor,
 ) -> impl Responder {
     let request_id = path.into_inner();
+    if let Err(response) = auditor.user.check_request(&scheduler, request_id).await {
+        return response;
+    }
+    let before = scheduler.find_request(request_id).await;
     match scheduler.cancel_request(request_id).await {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::success((), "充电请求已取消")),
+        Ok(_) => {
+            auditor.record_request_change(AuditAction::RequestCancel, before, None).await;
+            HttpResponse::Ok().json(ApiResponse::success((), "充电请求已取消"))
+        }
         Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(&e)),
     }
 }
@@ -160,9 +190,9 @@ pub async fn cancel_charging_request(
 #[get("/users/{user_id}/charging-requests")]
 pub async fn get_user_charging_requests(
     scheduler: web::Data<Arc<charging_station::scheduler::ChargingScheduler>>,
-    path: web::Path<Uuid>,
+    path: UserPath,
 ) -> impl Responder {
-    let user_id = path.into_inner();
+    let user_id = path.0;
     // 汇总等候区和所有桩队列的请求
     let mut result = Vec::new();
     let queue_manager = &scheduler.queue_manager;
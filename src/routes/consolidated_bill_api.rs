@@ -0,0 +1,129 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::billing::{BillPeriod, ConsolidatedBill};
+use charging_station::metrics;
+use charging_station::models::{AuditAction, ChargingRecord};
+use crate::routes::audit_api::Auditor;
+use crate::routes::auth::{forbidden, AuthUser};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateBillsInput {
+    pub period: BillPeriod,
+    pub date: NaiveDate, // 周期内任意一天（充电站当地日期）
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BillListQuery {
+    pub user_id: Option<Uuid>,
+    pub period: Option<BillPeriod>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// 生成指定周期的账单
+pub async fn generate_bills(
+    pool: web::Data<MySqlPool>,
+    auditor: Auditor,
+    input: web::Json<GenerateBillsInput>,
+) -> impl Responder {
+    match ConsolidatedBill::generate(input.period, input.date, &pool).await {
+        Ok(bills) => {
+            let target = format!("{}/{}", input.period.as_str(), input.date);
+            auditor
+                .record(AuditAction::BillGenerate, target, None::<()>, json!({ "count": bills.len() }))
+                .await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": bills,
+                "count": bills.len()
+            }))
+        }
+        Err(e) => {
+            println!("❌ 生成账单失败: {}", e);
+            metrics::db_error("consolidated_bill");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("生成账单失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 分页查询账单
+pub async fn list_bills(
+    pool: web::Data<MySqlPool>,
+    auth: AuthUser,
+    query: web::Query<BillListQuery>,
+) -> impl Responder {
+    let user_id = match auth.scope_user(query.user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+    let limit = query.limit.unwrap_or(20).min(100);
+    let offset = query.offset.unwrap_or(0);
+    match ConsolidatedBill::find(user_id, query.period, limit, offset, &pool).await {
+        Ok(bills) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": bills,
+            "count": bills.len()
+        })),
+        Err(e) => {
+            println!("❌ 查询账单失败: {}", e);
+            metrics::db_error("consolidated_bill");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询账单失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 查询账单及其包含的充电详单
+pub async fn get_bill(pool: web::Data<MySqlPool>, auth: AuthUser, path: web::Path<String>) -> impl Responder {
+    let bill_no = path.into_inner();
+    let result = async {
+        let Some(bill) = ConsolidatedBill::find_by_bill_no(&bill_no, &pool).await? else {
+            return Ok(None);
+        };
+        let records = ChargingRecord::find_by_ids(&bill.record_ids, &pool).await?;
+        Ok::<_, sqlx::Error>(Some((bill, records)))
+    }
+    .await;
+
+    match result {
+        Ok(Some((bill, _))) if !auth.can_access(bill.user_id) => forbidden(),
+        Ok(Some((bill, records))) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": {
+                "bill": bill,
+                "records": records
+            }
+        })),
+        Ok(None) => HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": format!("账单 {} 不存在", bill_no)
+        })),
+        Err(e) => {
+            println!("❌ 查询账单失败: {}", e);
+            metrics::db_error("consolidated_bill");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询账单失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 配置账单路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/bills")
+            .route("", web::get().to(list_bills))
+            .route("/generate", web::post().to(generate_bills))
+            .route("/{bill_no}", web::get().to(get_bill))
+    );
+}
@@ -0,0 +1,184 @@
+use actix_web::http::header::{self, ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use charging_station::billing::{BillPeriod, ConsolidatedBill};
+use charging_station::export::{self, ExportFormat, ExportRow, Locale};
+use charging_station::metrics;
+use charging_station::models::{to_station_local, ChargingRecord, ChargingRecordFilter};
+use charging_station::report::{PileReport, ReportPeriod};
+use charging_station::scheduler::ChargingScheduler;
+use crate::routes::auth::AuthUser;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::stream::{BoxStream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct RecordExportQuery {
+    pub format: Option<String>, // csv / xlsx，默认 csv
+    pub lang: Option<String>,   // zh / en，默认按 Accept-Language
+    pub user_id: Option<Uuid>,
+    pub pile_id: Option<String>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BillExportQuery {
+    pub format: Option<String>,
+    pub lang: Option<String>,
+    pub user_id: Option<Uuid>,
+    pub period: Option<BillPeriod>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportExportQuery {
+    pub format: Option<String>,
+    pub lang: Option<String>,
+    pub period: Option<String>,
+    pub date: Option<NaiveDate>,
+    pub pile_id: Option<String>,
+}
+
+fn bad_request(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+fn parse_format(format: Option<&str>) -> Result<ExportFormat, String> {
+    format.map(str::parse).transpose().map(|f| f.unwrap_or(ExportFormat::Csv))
+}
+
+fn locale(req: &HttpRequest, lang: Option<&str>) -> Locale {
+    let accept_language = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok());
+    Locale::negotiate(lang, accept_language)
+}
+
+/// 以附件形式流式返回导出文件
+fn export_response<T>(
+    format: ExportFormat,
+    locale: Locale,
+    rows: BoxStream<'static, Result<T, sqlx::Error>>,
+) -> HttpResponse
+where
+    T: ExportRow + Send + 'static,
+{
+    let filename = format!("{}.{}", T::FILE_STEM, format.extension());
+    let body = export::encode(format, locale, rows).map(|chunk| match chunk {
+        Ok(bytes) => Ok(Bytes::from(bytes)),
+        Err(e) => {
+            println!("❌ 导出 {} 中断: {}", T::FILE_STEM, e);
+            metrics::db_error("export");
+            Err(actix_web::error::ErrorInternalServerError(e))
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .streaming(body)
+}
+
+/// 导出充电详单
+pub async fn export_records(
+    req: HttpRequest,
+    pool: web::Data<MySqlPool>,
+    auth: AuthUser,
+    query: web::Query<RecordExportQuery>,
+) -> impl Responder {
+    let format = match parse_format(query.format.as_deref()) {
+        Ok(format) => format,
+        Err(message) => return bad_request(message),
+    };
+    let user_id = match auth.scope_user(query.user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+    let filter = ChargingRecordFilter {
+        user_id,
+        pile_id: query.pile_id.clone(),
+        start_time: query.start_time.map(to_station_local),
+        end_time: query.end_time.map(to_station_local),
+    };
+    println!("📤 导出充电详单 ({})", format.extension());
+    let rows = ChargingRecord::stream(filter, pool.get_ref().clone());
+    export_response(format, locale(&req, query.lang.as_deref()), rows)
+}
+
+/// 导出账单列表
+pub async fn export_bills(
+    req: HttpRequest,
+    pool: web::Data<MySqlPool>,
+    auth: AuthUser,
+    query: web::Query<BillExportQuery>,
+) -> impl Responder {
+    let format = match parse_format(query.format.as_deref()) {
+        Ok(format) => format,
+        Err(message) => return bad_request(message),
+    };
+    let user_id = match auth.scope_user(query.user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+    println!("📤 导出账单 ({})", format.extension());
+    let rows = ConsolidatedBill::stream(user_id, query.period, pool.get_ref().clone());
+    export_response(format, locale(&req, query.lang.as_deref()), rows)
+}
+
+/// 导出充电桩运营报表（末行为合计）
+pub async fn export_pile_report(
+    req: HttpRequest,
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    query: web::Query<ReportExportQuery>,
+) -> impl Responder {
+    let format = match parse_format(query.format.as_deref()) {
+        Ok(format) => format,
+        Err(message) => return bad_request(message),
+    };
+    let period = match query.period.as_deref().map(str::parse).transpose() {
+        Ok(period) => period.unwrap_or(ReportPeriod::Daily),
+        Err(message) => return bad_request(message),
+    };
+    let now = scheduler.queue_manager.time_system.current_time();
+    let date = query.date.unwrap_or_else(|| to_station_local(now).date());
+    let pile_ids: Vec<String> = scheduler.queue_manager.pile_infos.read().await.keys().cloned().collect();
+
+    match PileReport::generate(period, date, &pile_ids, query.pile_id.clone(), &pool).await {
+        Ok(report) => {
+            println!("📤 导出充电桩运营报表 {} {} ({})", report.period_start, period.as_str(), format.extension());
+            let mut rows = report.piles;
+            rows.push(report.total);
+            export_response(format, locale(&req, query.lang.as_deref()), export::from_rows(rows))
+        }
+        Err(e) => {
+            println!("❌ 生成运营报表失败: {}", e);
+            metrics::db_error("export");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("生成运营报表失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 配置导出路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/export")
+            .route("/records", web::get().to(export_records))
+            .route("/bills", web::get().to(export_bills))
+            .route("/reports/piles", web::get().to(export_pile_report))
+    );
+}
@@ -0,0 +1,14 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::metrics;
+
+/// Prometheus 抓取接口
+pub async fn get_metrics() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(metrics::render())
+}
+
+/// 配置监控指标路由（不在 /api 下）
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/metrics", web::get().to(get_metrics));
+}
@@ -3,4 +3,17 @@ pub mod pile_api;
 pub mod charging_request_api;
 pub mod scheduler_api;
 pub mod billing_api;
-pub mod charging_record_api;
\ No newline at end of file
+pub mod charging_record_api;
+pub mod tariff_api;
+pub mod wallet_api;
+pub mod payment_api;
+pub mod promotion_api;
+pub mod adjustment_api;
+pub mod consolidated_bill_api;
+pub mod service_rate_api;
+pub mod report_api;
+pub mod export_api;
+pub mod analytics_api;
+pub mod metrics_api;
+pub mod auth;
+pub mod audit_api;
\ No newline at end of file
@@ -0,0 +1,100 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use charging_station::metrics;
+use charging_station::payment::Bill;
+use charging_station::scheduler::ChargingScheduler;
+use crate::routes::auth::{forbidden, AuthUser, UserPath};
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn payment_not_configured() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "未配置支付渠道"
+    }))
+}
+
+/// 支付渠道回调，签名放在 X-Signature 请求头中
+pub async fn webhook(
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> impl Responder {
+    let Some(payment_service) = scheduler.queue_manager.payment_service.read().await.clone() else {
+        return payment_not_configured();
+    };
+    let signature = req
+        .headers()
+        .get("X-Signature")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    match payment_service.handle_webhook(&body, signature).await {
+        Ok(bill) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": bill
+        })),
+        Err(e) => {
+            println!("❌ 处理支付回调失败: {}", e);
+            HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": e
+            }))
+        }
+    }
+}
+
+/// 查询充电请求的支付账单
+pub async fn get_bill(
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auth: AuthUser,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let Some(payment_service) = scheduler.queue_manager.payment_service.read().await.clone() else {
+        return payment_not_configured();
+    };
+    let request_id = path.into_inner();
+
+    match payment_service.bill(request_id).await {
+        Some(bill) if !auth.can_access(bill.user_id) => forbidden(),
+        Some(bill) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": bill
+        })),
+        None => HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": format!("请求 {} 没有支付账单", request_id)
+        })),
+    }
+}
+
+/// 查询用户的全部支付账单
+pub async fn get_user_bills(pool: web::Data<MySqlPool>, path: UserPath) -> impl Responder {
+    let user_id = path.0;
+    match Bill::find_by_user_id(user_id, &pool).await {
+        Ok(bills) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": bills,
+            "count": bills.len()
+        })),
+        Err(e) => {
+            println!("❌ 查询支付账单失败: {}", e);
+            metrics::db_error("payment");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询支付账单失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 配置支付路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/payments")
+            .route("/webhook", web::post().to(webhook))
+            .route("/bills/{request_id}", web::get().to(get_bill))
+            .route("/users/{user_id}/bills", web::get().to(get_user_bills))
+    );
+}
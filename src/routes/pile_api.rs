@@ -1,6 +1,9 @@
-use crate::models::charging_pile::{ChargingPile, PileStatus};
-use crate::models::ChargingRequest;
+use charging_station::models::{ChargingPile, PileStatus};
+use charging_station::metrics;
+use charging_station::models::AuditAction;
+use crate::routes::audit_api::Auditor;
 use actix_web::{get, post, web, HttpResponse, Responder};
+use serde_json::json;
 use sqlx::MySqlPool;
 use uuid::Uuid;
 
@@ -10,13 +13,14 @@ pub async fn get_all_piles(pool: web::Data<MySqlPool>) -> impl Responder {
         Ok(piles) => HttpResponse::Ok().json(piles),
         Err(err) => {
             eprintln!("Error fetching piles: {:?}", err); // 打印错误
+            metrics::db_error("pile");
             HttpResponse::InternalServerError().body("获取充电桩信息失败")
         }
     }
 }
 
 #[post("/piles/{id}/start")]
-pub async fn start_pile(pool: web::Data<MySqlPool>, path: web::Path<Uuid>) -> impl Responder {
+pub async fn start_pile(pool: web::Data<MySqlPool>, auditor: Auditor, path: web::Path<Uuid>) -> impl Responder {
     let id = path.into_inner();
     let mut piles = ChargingPile::get_all(pool.get_ref())
         .await
@@ -28,6 +32,9 @@ pub async fn start_pile(pool: web::Data<MySqlPool>, path: web::Path<Uuid>) -> im
             pile.status = PileStatus::Available;
             pile.started_at = Some(chrono::Utc::now());
             let _ = pile.update_status(pool.get_ref()).await;
+            auditor
+                .record(AuditAction::PileStart, &pile.number, json!({ "status": PileStatus::Shutdown }), json!({ "status": pile.status }))
+                .await;
             HttpResponse::Ok().body("充电桩已启动")
         } else if pile.status == PileStatus::Available {
             HttpResponse::BadRequest().body("充电桩已启动")
@@ -40,7 +47,7 @@ pub async fn start_pile(pool: web::Data<MySqlPool>, path: web::Path<Uuid>) -> im
 }
 
 #[post("/piles/{id}/shutdown")]
-pub async fn shutdown_pile(pool: web::Data<MySqlPool>, path: web::Path<Uuid>) -> impl Responder {
+pub async fn shutdown_pile(pool: web::Data<MySqlPool>, auditor: Auditor, path: web::Path<Uuid>) -> impl Responder {
     let id = path.into_inner();
     let mut piles = ChargingPile::get_all(pool.get_ref())
         .await
@@ -52,6 +59,9 @@ pub async fn shutdown_pile(pool: web::Data<MySqlPool>, path: web::Path<Uuid>) ->
             pile.status = PileStatus::Shutdown;
             pile.started_at = None;
             let _ = pile.update_status(pool.get_ref()).await;
+            auditor
+                .record(AuditAction::PileShutdown, &pile.number, json!({ "status": PileStatus::Available }), json!({ "status": pile.status }))
+                .await;
             HttpResponse::Ok().body("充电桩已关闭")
         } else if pile.status == PileStatus::Charging {
             HttpResponse::BadRequest().body("充电桩正在充电，无法关闭")
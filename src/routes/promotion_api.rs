@@ -0,0 +1,226 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::billing::{Coupon, Discount, FeeComponent, Promotion, PromotionConditions};
+use charging_station::metrics;
+use charging_station::models::AuditAction;
+use crate::routes::audit_api::Auditor;
+use charging_station::scheduler::ChargingScheduler;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const MAX_COUPONS_PER_BATCH: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct PromotionInput {
+    pub name: String,
+    #[serde(default)]
+    pub conditions: PromotionConditions,
+    pub component: FeeComponent,
+    pub discount: Discount,
+    pub max_energy_kwh: Option<f64>,
+    #[serde(default)]
+    pub requires_coupon: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActiveInput {
+    pub active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CouponBatchInput {
+    pub count: usize,
+}
+
+/// 获取全部促销规则
+pub async fn get_promotions(pool: web::Data<MySqlPool>) -> impl Responder {
+    match Promotion::find_all(&pool).await {
+        Ok(promotions) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": promotions,
+            "count": promotions.len()
+        })),
+        Err(e) => {
+            println!("❌ 查询促销规则失败: {}", e);
+            metrics::db_error("promotion");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询促销规则失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 新增促销规则
+pub async fn create_promotion(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    input: web::Json<PromotionInput>,
+) -> impl Responder {
+    let input = input.into_inner();
+    let promotion = Promotion::new(
+        input.name,
+        input.conditions,
+        input.component,
+        input.discount,
+        input.max_energy_kwh,
+        input.requires_coupon,
+    );
+    if let Err(e) = promotion.validate() {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": e
+        }));
+    }
+
+    match promotion.insert(&pool).await {
+        Ok(_) => {
+            println!("✅ 新增促销规则: {}", promotion.name);
+            scheduler.queue_manager.reload_promotions().await;
+            auditor.record(AuditAction::PromotionCreate, promotion.id, None::<()>, &promotion).await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": promotion
+            }))
+        }
+        Err(e) => {
+            println!("❌ 新增促销规则失败: {}", e);
+            metrics::db_error("promotion");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("新增促销规则失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 启用或停用促销规则
+pub async fn set_promotion_active(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    path: web::Path<Uuid>,
+    input: web::Json<ActiveInput>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let before = Promotion::find_all(&pool)
+        .await
+        .ok()
+        .and_then(|promotions| promotions.into_iter().find(|p| p.id == id))
+        .map(|p| json!({ "active": p.active }));
+    match Promotion::set_active(id, input.active, &pool).await {
+        Ok(true) => {
+            println!("✅ 促销规则 {} 已{}", id, if input.active { "启用" } else { "停用" });
+            scheduler.queue_manager.reload_promotions().await;
+            auditor
+                .record(AuditAction::PromotionUpdate, id, before, json!({ "active": input.active }))
+                .await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "message": if input.active { "促销规则已启用" } else { "促销规则已停用" }
+            }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "未找到促销规则"
+        })),
+        Err(e) => {
+            println!("❌ 修改促销规则失败: {}", e);
+            metrics::db_error("promotion");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("修改促销规则失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 为需凭券使用的促销生成一批一次性优惠券
+pub async fn create_coupons(
+    pool: web::Data<MySqlPool>,
+    auditor: Auditor,
+    path: web::Path<Uuid>,
+    input: web::Json<CouponBatchInput>,
+) -> impl Responder {
+    let promotion_id = path.into_inner();
+    if input.count == 0 || input.count > MAX_COUPONS_PER_BATCH {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": format!("每批优惠券数量须在1到{}之间", MAX_COUPONS_PER_BATCH)
+        }));
+    }
+
+    let result = async {
+        let promotion = Promotion::find_all(&pool).await?.into_iter().find(|p| p.id == promotion_id);
+        match promotion {
+            Some(promotion) if promotion.requires_coupon => {
+                let coupons = Coupon::generate(promotion_id, input.count);
+                Coupon::insert_batch(&coupons, &pool).await?;
+                Ok(Ok(coupons))
+            }
+            Some(_) => Ok(Err("该促销无需优惠券".to_string())),
+            None => Ok::<_, sqlx::Error>(Err("未找到促销规则".to_string())),
+        }
+    }
+    .await;
+
+    match result {
+        Ok(Ok(coupons)) => {
+            println!("✅ 为促销 {} 生成 {} 张优惠券", promotion_id, coupons.len());
+            auditor
+                .record(AuditAction::CouponCreate, promotion_id, None::<()>, json!({ "count": coupons.len() }))
+                .await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": coupons,
+                "count": coupons.len()
+            }))
+        }
+        Ok(Err(message)) => HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        })),
+        Err(e) => {
+            println!("❌ 生成优惠券失败: {}", e);
+            metrics::db_error("promotion");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("生成优惠券失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 查询促销下的优惠券及核销情况
+pub async fn get_coupons(pool: web::Data<MySqlPool>, path: web::Path<Uuid>) -> impl Responder {
+    match Coupon::find_by_promotion(path.into_inner(), &pool).await {
+        Ok(coupons) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": coupons,
+            "count": coupons.len()
+        })),
+        Err(e) => {
+            println!("❌ 查询优惠券失败: {}", e);
+            metrics::db_error("promotion");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询优惠券失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 配置促销管理路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/promotions")
+            .route("", web::get().to(get_promotions))
+            .route("", web::post().to(create_promotion))
+            .route("/{id}/active", web::put().to(set_promotion_active))
+            .route("/{id}/coupons", web::get().to(get_coupons))
+            .route("/{id}/coupons", web::post().to(create_coupons))
+    );
+}
@@ -0,0 +1,69 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::metrics;
+use charging_station::models::to_station_local;
+use charging_station::report::{PileReport, ReportPeriod};
+use charging_station::scheduler::ChargingScheduler;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct PileReportQuery {
+    pub period: Option<String>,  // Daily / Weekly / Monthly，默认 Daily
+    pub date: Option<NaiveDate>, // 周期内任意一天（充电站当地日期），默认今天
+    pub pile_id: Option<String>, // 只统计一个充电桩
+}
+
+/// 充电桩运营报表
+pub async fn get_pile_report(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    query: web::Query<PileReportQuery>,
+) -> impl Responder {
+    let period = match query.period.as_deref().map(str::parse).transpose() {
+        Ok(period) => period.unwrap_or(ReportPeriod::Daily),
+        Err(message) => {
+            return HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": message
+            }))
+        }
+    };
+    let now = scheduler.queue_manager.time_system.current_time();
+    let date = query.date.unwrap_or_else(|| to_station_local(now).date());
+    let pile_ids: Vec<String> = scheduler.queue_manager.pile_infos.read().await.keys().cloned().collect();
+
+    match PileReport::generate(period, date, &pile_ids, query.pile_id.clone(), &pool).await {
+        Ok(report) => {
+            println!(
+                "📊 充电桩运营报表 {} {}: {} 个充电桩，总费用 {}元",
+                report.period_start,
+                period.as_str(),
+                report.piles.len(),
+                report.total.total_fee
+            );
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": report
+            }))
+        }
+        Err(e) => {
+            println!("❌ 生成运营报表失败: {}", e);
+            metrics::db_error("report");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("生成运营报表失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 配置报表路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/reports")
+            .route("/piles", web::get().to(get_pile_report))
+    );
+}
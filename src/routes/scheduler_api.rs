@@ -1,6 +1,8 @@
 use actix_web::{web, HttpResponse, Responder};
 use charging_station::scheduler::ChargingScheduler;
-use charging_station::models::{ChargingRequest, ChargingMode, RequestStatus};
+use charging_station::models::{AuditAction, ChargingRequest, ChargingMode, RequestStatus, RequestTimeline};
+use crate::routes::audit_api::Auditor;
+use crate::routes::auth::{AuthUser, UserPath};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -27,9 +29,9 @@ pub struct PileStatus {
 
 #[derive(Debug, Deserialize)]
 pub struct StartChargingRequest {
-    pub user_id: Uuid,
     pub mode: String,
     pub amount: f64,
+    pub coupon_code: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,22 +53,35 @@ pub async fn get_system_status(scheduler: web::Data<Arc<ChargingScheduler>>) ->
     }))
 }
 
-pub async fn start_scheduler(scheduler: web::Data<Arc<ChargingScheduler>>) -> impl Responder {
+pub async fn start_scheduler(scheduler: web::Data<Arc<ChargingScheduler>>, auditor: Auditor) -> impl Responder {
+    let before = scheduler.get_scheduler_status().await;
     match scheduler.start().await {
-        Ok(_) => HttpResponse::Ok().json("调度系统已启动"),
+        Ok(_) => {
+            auditor
+                .record(AuditAction::SchedulerStart, "scheduler", before, scheduler.get_scheduler_status().await)
+                .await;
+            HttpResponse::Ok().json("调度系统已启动")
+        }
         Err(e) => HttpResponse::InternalServerError().json(e),
     }
 }
 
-pub async fn stop_scheduler(scheduler: web::Data<Arc<ChargingScheduler>>) -> impl Responder {
+pub async fn stop_scheduler(scheduler: web::Data<Arc<ChargingScheduler>>, auditor: Auditor) -> impl Responder {
+    let before = scheduler.get_scheduler_status().await;
     match scheduler.stop().await {
-        Ok(_) => HttpResponse::Ok().json("调度系统已停止"),
+        Ok(_) => {
+            auditor
+                .record(AuditAction::SchedulerStop, "scheduler", before, scheduler.get_scheduler_status().await)
+                .await;
+            HttpResponse::Ok().json("调度系统已停止")
+        }
         Err(e) => HttpResponse::InternalServerError().json(e),
     }
 }
 
 pub async fn submit_charging_request(
     scheduler: web::Data<Arc<ChargingScheduler>>,
+    auth: AuthUser,
     request: web::Json<StartChargingRequest>,
 ) -> impl Responder {
     let mode = match request.mode.as_str() {
@@ -77,14 +92,17 @@ pub async fn submit_charging_request(
 
     let charging_request = ChargingRequest {
         id: Uuid::new_v4(),
-        user_id: request.user_id,
+        user_id: auth.user_id,
         mode: mode.to_string(),
         amount: request.amount,
         queue_number: String::new(),
         status: RequestStatus::Waiting.to_string(),
         created_at: Utc::now(),
         updated_at: Utc::now(),
-    };
+        coupon_code: None,
+        timeline: RequestTimeline::default(),
+    }
+    .with_coupon(request.coupon_code.clone());
 
     match scheduler.submit_request(charging_request.clone()).await {
         Ok(_) => HttpResponse::Ok().json(charging_request),
@@ -98,7 +116,13 @@ pub async fn get_pile_status(scheduler: web::Data<Arc<ChargingScheduler>>) -> im
         PileStatus {
             number: info.pile_number,
             mode: info.pile_mode.to_string(),
-            status: if info.is_idle { "空闲".to_string() } else { "充电中".to_string() },
+            status: if info.is_idle {
+                "空闲".to_string()
+            } else if info.awaiting_unplug {
+                "待拔枪".to_string()
+            } else {
+                "充电中".to_string()
+            },
             current_user: info.current_charging_user.map(|id| id.to_string()),
             queue_count: info.queue_count,
             charging_progress: info.charging_progress,
@@ -117,12 +141,21 @@ pub async fn get_waiting_queue(scheduler: web::Data<Arc<ChargingScheduler>>) ->
 pub async fn cancel_charging_request(
     scheduler: web::Data<Arc<ChargingScheduler>>,
     request_id: web::Path<Uuid>,
+    auditor: Auditor,
 ) -> impl Responder {
-    match scheduler.cancel_request(request_id.into_inner()).await {
-        Ok(_) => HttpResponse::Ok().json(json!({
-            "message": "请求已取消",
-            "success": true
-        })),
+    let request_id = request_id.into_inner();
+    if let Err(response) = auditor.user.check_request(&scheduler, request_id).await {
+        return response;
+    }
+    let before = scheduler.find_request(request_id).await;
+    match scheduler.cancel_request(request_id).await {
+        Ok(_) => {
+            auditor.record_request_change(AuditAction::RequestCancel, before, None).await;
+            HttpResponse::Ok().json(json!({
+                "message": "请求已取消",
+                "success": true
+            }))
+        }
         Err(e) => {
             println!("取消请求失败: {}", e);
             HttpResponse::BadRequest().json(json!({
@@ -136,14 +169,18 @@ pub async fn cancel_charging_request(
 /// 通过用户ID取消充电请求
 pub async fn cancel_charging_request_by_user(
     scheduler: web::Data<Arc<ChargingScheduler>>,
-    user_id: web::Path<Uuid>,
+    user_id: UserPath,
+    auditor: Auditor,
 ) -> impl Responder {
-    let user_id = user_id.into_inner();
+    let user_id = user_id.0;
     println!("收到取消用户 {} 的充电请求", user_id);
     
     match scheduler.cancel_request_by_user(user_id).await {
         Ok(_) => {
             println!("成功取消用户 {} 的充电请求", user_id);
+            if user_id != auditor.user.user_id {
+                auditor.record(AuditAction::RequestCancel, user_id, None::<()>, None::<()>).await;
+            }
             HttpResponse::Ok().json(json!({
                 "message": "用户请求已取消",
                 "success": true
@@ -163,16 +200,25 @@ pub async fn cancel_charging_request_by_user(
 pub async fn update_charging_amount(
     scheduler: web::Data<Arc<ChargingScheduler>>,
     path: web::Path<Uuid>,
+    auditor: Auditor,
     request: web::Json<serde_json::Value>,
 ) -> impl Responder {
     let request_id = path.into_inner();
+    if let Err(response) = auditor.user.check_request(&scheduler, request_id).await {
+        return response;
+    }
     
     if let Some(amount) = request.get("amount").and_then(|v| v.as_f64()) {
+        let before = scheduler.find_request(request_id).await;
         match scheduler.update_request_amount(request_id, amount).await {
-            Ok(_) => HttpResponse::Ok().json(json!({
-                "message": "充电量更新成功",
-                "success": true
-            })),
+            Ok(_) => {
+                let after = scheduler.find_request(request_id).await;
+                auditor.record_request_change(AuditAction::RequestUpdate, before, after).await;
+                HttpResponse::Ok().json(json!({
+                    "message": "充电量更新成功",
+                    "success": true
+                }))
+            }
             Err(e) => HttpResponse::BadRequest().json(json!({
                 "message": e,
                 "success": false
@@ -190,9 +236,13 @@ pub async fn update_charging_amount(
 pub async fn update_charging_mode(
     scheduler: web::Data<Arc<ChargingScheduler>>,
     path: web::Path<Uuid>,
+    auditor: Auditor,
     request: web::Json<serde_json::Value>,
 ) -> impl Responder {
     let request_id = path.into_inner();
+    if let Err(response) = auditor.user.check_request(&scheduler, request_id).await {
+        return response;
+    }
     
     if let (Some(mode_str), Some(queue_number)) = (
         request.get("mode").and_then(|v| v.as_str()),
@@ -207,11 +257,16 @@ pub async fn update_charging_mode(
             }))
         };
         
+        let before = scheduler.find_request(request_id).await;
         match scheduler.update_request_mode(request_id, mode, queue_number.to_string()).await {
-            Ok(_) => HttpResponse::Ok().json(json!({
-                "message": "充电模式更新成功，已重新排队",
-                "success": true
-            })),
+            Ok(_) => {
+                let after = scheduler.find_request(request_id).await;
+                auditor.record_request_change(AuditAction::RequestUpdate, before, after).await;
+                HttpResponse::Ok().json(json!({
+                    "message": "充电模式更新成功，已重新排队",
+                    "success": true
+                }))
+            }
             Err(e) => HttpResponse::BadRequest().json(json!({
                 "message": e,
                 "success": false
@@ -236,19 +291,86 @@ pub async fn update_charging_request(
     }))
 }
 
-/// 测试充电完成（仅用于调试）
-pub async fn test_charging_completion(
+/// 上报充电桩故障
+pub async fn report_pile_fault(
     scheduler: web::Data<Arc<ChargingScheduler>>,
-) -> Result<HttpResponse, actix_web::Error> {
-    println!("🧪 手动触发充电完成检查");
-    
-    // 手动调用tick来检查充电完成
-    scheduler.manual_tick().await;
-    
-    Ok(HttpResponse::Ok().json(json!({
-        "success": true,
-        "message": "充电完成检查已触发"
-    })))
+    auditor: Auditor,
+    pile_id: web::Path<String>,
+) -> impl Responder {
+    let pile_id = pile_id.into_inner();
+    let Some(before) = scheduler.pile_state(&pile_id).await else {
+        return HttpResponse::NotFound().json(json!({
+            "message": format!("充电桩 {} 不存在", pile_id),
+            "success": false
+        }));
+    };
+    match scheduler.handle_pile_fault(&pile_id).await {
+        Ok(_) => {
+            let after = scheduler.pile_state(&pile_id).await;
+            auditor
+                .record(AuditAction::PileFault, &pile_id, json!({ "status": before }), json!({ "status": after }))
+                .await;
+            HttpResponse::Ok().json(json!({
+                "message": format!("充电桩 {} 已标记为故障", pile_id),
+                "success": true
+            }))
+        }
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "message": e,
+            "success": false
+        })),
+    }
+}
+
+/// 上报充电桩故障恢复
+pub async fn report_pile_recovery(
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    pile_id: web::Path<String>,
+) -> impl Responder {
+    let pile_id = pile_id.into_inner();
+    let before = scheduler.pile_state(&pile_id).await;
+    match scheduler.handle_pile_recovery(&pile_id).await {
+        Ok(_) => {
+            let after = scheduler.pile_state(&pile_id).await;
+            auditor
+                .record(AuditAction::PileRecovery, &pile_id, json!({ "status": before }), json!({ "status": after }))
+                .await;
+            HttpResponse::Ok().json(json!({
+                "message": format!("充电桩 {} 已恢复", pile_id),
+                "success": true
+            }))
+        }
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "message": e,
+            "success": false
+        })),
+    }
+}
+
+/// 车辆拔枪（启用超时占位模式时结算充电详单）
+pub async fn unplug_vehicle(
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    pile_id: web::Path<String>,
+) -> impl Responder {
+    let pile_id = pile_id.into_inner();
+    match scheduler.unplug_vehicle(&pile_id).await {
+        Ok(record) => {
+            auditor
+                .record(AuditAction::PileUnplug, &pile_id, None::<()>, json!({ "record_id": record.id, "user_id": record.user_id }))
+                .await;
+            HttpResponse::Ok().json(json!({
+                "message": format!("车辆已从充电桩 {} 拔枪", pile_id),
+                "success": true,
+                "data": record
+            }))
+        }
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "message": e,
+            "success": false
+        })),
+    }
 }
 
 /// 配置服务路由
@@ -260,12 +382,14 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("/stop", web::post().to(stop_scheduler))
             .route("/submit", web::post().to(submit_charging_request))
             .route("/piles", web::get().to(get_pile_status))
+            .route("/piles/{pile_id}/fault", web::post().to(report_pile_fault))
+            .route("/piles/{pile_id}/recovery", web::post().to(report_pile_recovery))
+            .route("/piles/{pile_id}/unplug", web::post().to(unplug_vehicle))
             .route("/waiting", web::get().to(get_waiting_queue))
             .route("/cancel/{request_id}", web::post().to(cancel_charging_request))
             .route("/cancel/user/{user_id}", web::post().to(cancel_charging_request_by_user))
             .route("/update", web::post().to(update_charging_request))
             .route("/update/{request_id}/amount", web::put().to(update_charging_amount))
             .route("/update/{request_id}/mode", web::put().to(update_charging_mode))
-            .route("/test-completion", web::post().to(test_charging_completion))
     );
 } 
\ No newline at end of file
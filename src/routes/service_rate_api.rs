@@ -0,0 +1,126 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::billing::ServiceRate;
+use charging_station::metrics;
+use charging_station::models::{deserialize_rate, AuditAction, ChargingMode, Money};
+use crate::routes::audit_api::Auditor;
+use charging_station::scheduler::ChargingScheduler;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceRateInput {
+    pub mode: ChargingMode,
+    pub pile_id: Option<String>,
+    #[serde(deserialize_with = "deserialize_rate")]
+    pub rate: Money,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceRateKey {
+    pub mode: ChargingMode,
+    pub pile_id: Option<String>,
+}
+
+/// 审计日志中的费率对象：单桩费率为充电桩编号，否则为充电模式
+fn rate_target(mode: ChargingMode, pile_id: Option<&str>) -> String {
+    pile_id.map(str::to_string).unwrap_or_else(|| mode.to_string())
+}
+
+/// 修改或删除前的服务费率，用于审计日志
+async fn current_rate(scheduler: &ChargingScheduler, mode: ChargingMode, pile_id: Option<&str>) -> Option<ServiceRate> {
+    let rates = scheduler.queue_manager.service_rates.read().await;
+    rates.rates().iter().find(|r| r.mode == mode && r.pile_id.as_deref() == pile_id).cloned()
+}
+
+/// 获取当前生效的服务费率
+pub async fn get_service_rates(scheduler: web::Data<Arc<ChargingScheduler>>) -> impl Responder {
+    let rates = scheduler.queue_manager.service_rates.read().await;
+    HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": rates.rates(),
+        "count": rates.rates().len()
+    }))
+}
+
+/// 设置充电模式或单个充电桩的服务费率
+pub async fn set_service_rate(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    input: web::Json<ServiceRateInput>,
+) -> impl Responder {
+    let input = input.into_inner();
+    let rate = ServiceRate::new(input.mode, input.pile_id, input.rate);
+    if let Err(e) = rate.validate() {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": e
+        }));
+    }
+
+    let before = current_rate(&scheduler, rate.mode, rate.pile_id.as_deref()).await;
+    match rate.upsert(&pool).await {
+        Ok(()) => {
+            scheduler.queue_manager.reload_service_rates().await;
+            let target = rate_target(rate.mode, rate.pile_id.as_deref());
+            auditor.record(AuditAction::ServiceRateSet, target, before, &rate).await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": rate
+            }))
+        }
+        Err(e) => {
+            println!("❌ 设置服务费率失败: {}", e);
+            metrics::db_error("service_rate");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("设置服务费率失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 删除服务费率
+pub async fn delete_service_rate(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    query: web::Query<ServiceRateKey>,
+) -> impl Responder {
+    let before = current_rate(&scheduler, query.mode, query.pile_id.as_deref()).await;
+    match ServiceRate::delete(query.mode, query.pile_id.as_deref(), &pool).await {
+        Ok(true) => {
+            scheduler.queue_manager.reload_service_rates().await;
+            let target = rate_target(query.mode, query.pile_id.as_deref());
+            auditor.record(AuditAction::ServiceRateDelete, target, before, None::<()>).await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "message": "服务费率已删除"
+            }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "服务费率不存在"
+        })),
+        Err(e) => {
+            println!("❌ 删除服务费率失败: {}", e);
+            metrics::db_error("service_rate");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("删除服务费率失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 配置服务费率路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/service-rates")
+            .route("", web::get().to(get_service_rates))
+            .route("", web::put().to(set_service_rate))
+            .route("", web::delete().to(delete_service_rate))
+    );
+}
@@ -0,0 +1,204 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::billing::{Tariff, TariffPeriod};
+use charging_station::metrics;
+use charging_station::models::AuditAction;
+use crate::routes::audit_api::Auditor;
+use charging_station::scheduler::ChargingScheduler;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct TariffInput {
+    pub name: String,
+    pub effective_from: DateTime<Utc>,
+    pub effective_to: Option<DateTime<Utc>>,
+    pub periods: Vec<TariffPeriod>,
+}
+
+impl TariffInput {
+    fn into_tariff(self) -> Result<Tariff, String> {
+        let tariff = Tariff::new(self.name, self.effective_from, self.effective_to, self.periods);
+        tariff.validate()?;
+        Ok(tariff)
+    }
+}
+
+/// 获取全部电价方案
+pub async fn get_tariffs(pool: web::Data<MySqlPool>) -> impl Responder {
+    match Tariff::find_all(&pool).await {
+        Ok(tariffs) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": tariffs,
+            "count": tariffs.len()
+        })),
+        Err(e) => {
+            println!("❌ 查询电价方案失败: {}", e);
+            metrics::db_error("tariff");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询电价方案失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 获取当前生效的电价方案
+pub async fn get_current_tariff(scheduler: web::Data<Arc<ChargingScheduler>>) -> impl Responder {
+    let now = scheduler.queue_manager.time_system.current_time();
+    let schedule = scheduler.queue_manager.tariff_schedule.read().await;
+    HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": schedule.period_at(now).0
+    }))
+}
+
+/// 查询某天在电价日历中的日类型、季节和时段布局
+pub async fn get_calendar_day(
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    path: web::Path<NaiveDate>,
+) -> impl Responder {
+    let schedule = scheduler.queue_manager.tariff_schedule.read().await;
+    HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": schedule.calendar().describe(path.into_inner())
+    }))
+}
+
+/// 修改或删除前的电价方案，用于审计日志
+async fn find_tariff(id: Uuid, pool: &MySqlPool) -> Option<Tariff> {
+    Tariff::find_all(pool).await.ok()?.into_iter().find(|tariff| tariff.id == id)
+}
+
+/// 新增电价方案
+pub async fn create_tariff(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    input: web::Json<TariffInput>,
+) -> impl Responder {
+    let tariff = match input.into_inner().into_tariff() {
+        Ok(tariff) => tariff,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": e
+            }))
+        }
+    };
+
+    match tariff.insert(&pool).await {
+        Ok(_) => {
+            println!("✅ 新增电价方案: {}", tariff.name);
+            scheduler.queue_manager.reload_tariffs().await;
+            auditor.record(AuditAction::TariffCreate, tariff.id, None::<()>, &tariff).await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": tariff
+            }))
+        }
+        Err(e) => {
+            println!("❌ 新增电价方案失败: {}", e);
+            metrics::db_error("tariff");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("新增电价方案失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 修改电价方案
+pub async fn update_tariff(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    path: web::Path<Uuid>,
+    input: web::Json<TariffInput>,
+) -> impl Responder {
+    let mut tariff = match input.into_inner().into_tariff() {
+        Ok(tariff) => tariff,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": e
+            }))
+        }
+    };
+    tariff.id = path.into_inner();
+    let before = find_tariff(tariff.id, &pool).await;
+
+    match tariff.update(&pool).await {
+        Ok(true) => {
+            println!("✅ 修改电价方案: {}", tariff.name);
+            scheduler.queue_manager.reload_tariffs().await;
+            auditor.record(AuditAction::TariffUpdate, tariff.id, before, &tariff).await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": tariff
+            }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "未找到电价方案"
+        })),
+        Err(e) => {
+            println!("❌ 修改电价方案失败: {}", e);
+            metrics::db_error("tariff");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("修改电价方案失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 删除电价方案
+pub async fn delete_tariff(
+    pool: web::Data<MySqlPool>,
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    auditor: Auditor,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let before = find_tariff(id, &pool).await;
+    match Tariff::delete(id, &pool).await {
+        Ok(true) => {
+            println!("✅ 删除电价方案: {}", id);
+            scheduler.queue_manager.reload_tariffs().await;
+            auditor.record(AuditAction::TariffDelete, id, before, None::<()>).await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "message": "电价方案已删除"
+            }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "未找到电价方案"
+        })),
+        Err(e) => {
+            println!("❌ 删除电价方案失败: {}", e);
+            metrics::db_error("tariff");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("删除电价方案失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 配置电价管理路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/tariffs")
+            .route("", web::get().to(get_tariffs))
+            .route("", web::post().to(create_tariff))
+            .route("/current", web::get().to(get_current_tariff))
+            .route("/calendar/{date}", web::get().to(get_calendar_day))
+            .route("/{id}", web::put().to(update_tariff))
+            .route("/{id}", web::delete().to(delete_tariff))
+    );
+}
@@ -1,18 +1,22 @@
-use actix_web::{get, post, web, HttpResponse, Responder};
-use crate::models::user::User;
-use crate::models::ChargingRecord;
+use actix_web::{get, post, put, web, HttpResponse, Responder};
+use charging_station::auth::{
+    dummy_password_hash, hash_password, temporary_password, validate_password, verify_password, PasswordMatch,
+    TokenKind, TokenSigner,
+};
+use charging_station::metrics;
+use charging_station::models::{AuditAction, ChargingRecord, Role, User};
+use crate::routes::audit_api::Auditor;
+use crate::routes::auth::{unauthorized, AuthUser, UserPath};
 use sqlx::MySqlPool;
 use serde_json::json;
 use serde::Deserialize;
-use sha2::{Digest, Sha256};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 
 #[derive(Debug, Deserialize)]
 pub struct NewUser {
     pub username: String,
     pub password: String,
-    pub is_admin: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,6 +25,22 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignRoleRequest {
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub old_password: String,
+    pub new_password: String,
+}
+
 #[get("/users")]
 async fn get_users(db_pool: web::Data<MySqlPool>) -> impl Responder {
     match User::get_all(db_pool.get_ref()).await {
@@ -36,11 +56,19 @@ async fn register_user(
 ) -> impl Responder {
     let new_user = new_user.into_inner();
 
-    let mut hasher = Sha256::new();
-    hasher.update(new_user.password);
-    let password_hash = format!("{:x}", hasher.finalize());
+    if let Err(message) = validate_password(&new_user.password) {
+        return HttpResponse::BadRequest().body(message);
+    }
+    let password_hash = match hash_password_blocking(new_user.password.clone()).await {
+        Ok(password_hash) => password_hash,
+        Err(e) => {
+            eprintln!("注册失败: {}", e);
+            return HttpResponse::InternalServerError().body("注册失败");
+        }
+    };
 
-    let user = User::new(new_user.username, password_hash, new_user.is_admin);
+    // 注册用户一律为车主，其他角色只能由超级管理员分配
+    let user = User::new(new_user.username, password_hash, Role::Driver);
     match user.insert(db_pool.get_ref()).await {
         Ok(_) => HttpResponse::Created().body("注册成功"),
         Err(_) => HttpResponse::InternalServerError().body("注册失败"),
@@ -50,52 +78,241 @@ async fn register_user(
 #[post("/login")]
 async fn login_user(
     db_pool: web::Data<MySqlPool>,
+    signer: web::Data<TokenSigner>,
     login: web::Json<LoginRequest>,
 ) -> impl Responder {
     let login = login.into_inner();
+    let result = User::find_by_username(&login.username, db_pool.get_ref()).await;
 
-    let mut hasher = Sha256::new();
-    hasher.update(login.password);
-    let password_hash = format!("{:x}", hasher.finalize());
-
-    let result = sqlx::query_as!(
-        User,
-        r#"
-        SELECT 
-            id as "id: Uuid", 
-            username, 
-            password_hash, 
-            is_admin as "is_admin: bool", 
-            created_at as "created_at: chrono::DateTime<chrono::Utc>"
-        FROM users
-        WHERE username = ? AND password_hash = ?
-        "#,
-        login.username,
-        password_hash
-    )
-    .fetch_optional(db_pool.get_ref())
-    .await;
+    // 用户名不存在时也校验一次固定哈希，响应时间不暴露用户名是否存在
+    let stored_hash = match &result {
+        Ok(Some(user)) => user.password_hash.clone(),
+        _ => dummy_password_hash().to_string(),
+    };
+    let matched = verify_password_blocking(login.password.clone(), stored_hash).await;
 
     match result {
-        Ok(Some(_user)) => HttpResponse::Ok().json(_user),
-        Ok(None) => HttpResponse::Unauthorized().body("用户名或密码错误"),
+        Ok(Some(user)) => {
+            match matched {
+                PasswordMatch::Invalid => return unauthorized("用户名或密码错误"),
+                PasswordMatch::Valid => {}
+                // 旧版 SHA-256 哈希在登录成功后透明升级，升级失败不影响本次登录
+                PasswordMatch::Legacy => match hash_password_blocking(login.password.clone()).await {
+                    Ok(password_hash) => match User::update_password_hash(user.id, &password_hash, db_pool.get_ref()).await {
+                        Ok(_) => println!("🔐 用户 {} 的密码哈希已升级为 Argon2id", user.username),
+                        Err(e) => {
+                            eprintln!("升级密码哈希失败: {:?}", e);
+                            metrics::db_error("user");
+                        }
+                    },
+                    Err(e) => eprintln!("升级密码哈希失败: {}", e),
+                },
+            }
+            let tokens = signer.issue(user.id, user.role, Utc::now());
+            println!("🔑 用户 {} 登录成功", user.username);
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": {
+                    "user": user,
+                    "access_token": tokens.access_token,
+                    "refresh_token": tokens.refresh_token,
+                    "token_type": tokens.token_type,
+                    "expires_in": tokens.expires_in
+                }
+            }))
+        }
+        Ok(None) => unauthorized("用户名或密码错误"),
         Err(e) => {
             eprintln!("登录失败: {:?}", e);
+            metrics::db_error("user");
+            HttpResponse::InternalServerError().body("服务器异常")
+        }
+    }
+}
+
+/// 用刷新令牌换取新的一对令牌，重新读取用户以反映权限变化
+#[post("/refresh")]
+async fn refresh_token(
+    db_pool: web::Data<MySqlPool>,
+    signer: web::Data<TokenSigner>,
+    input: web::Json<RefreshRequest>,
+) -> impl Responder {
+    let claims = match signer.verify(&input.refresh_token, TokenKind::Refresh, Utc::now()) {
+        Ok(claims) => claims,
+        Err(message) => return unauthorized(&message),
+    };
+
+    match User::find_by_id(claims.sub, db_pool.get_ref()).await {
+        Ok(Some(user)) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": signer.issue(user.id, user.role, Utc::now())
+        })),
+        Ok(None) => unauthorized("用户不存在"),
+        Err(e) => {
+            eprintln!("刷新令牌失败: {:?}", e);
+            metrics::db_error("user");
+            HttpResponse::InternalServerError().body("服务器异常")
+        }
+    }
+}
+
+/// 修改本人密码，需验证旧密码
+#[post("/users/me/password")]
+async fn change_password(
+    db_pool: web::Data<MySqlPool>,
+    auth: AuthUser,
+    input: web::Json<ChangePasswordRequest>,
+) -> impl Responder {
+    let user = match User::find_by_id(auth.user_id, db_pool.get_ref()).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return unauthorized("用户不存在"),
+        Err(e) => {
+            eprintln!("修改密码失败: {:?}", e);
+            metrics::db_error("user");
+            return HttpResponse::InternalServerError().body("服务器异常");
+        }
+    };
+    let matched = verify_password_blocking(input.old_password.clone(), user.password_hash.clone()).await;
+    if matched == PasswordMatch::Invalid {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "旧密码错误"
+        }));
+    }
+    if let Err(message) = validate_password(&input.new_password) {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        }));
+    }
+
+    match save_password(user.id, &input.new_password, &db_pool).await {
+        Ok(()) => {
+            println!("🔐 用户 {} 已修改密码", user.username);
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "message": "密码修改成功"
+            }))
+        }
+        Err(response) => response,
+    }
+}
+
+/// 管理员重置用户密码，返回一次性显示的临时密码
+#[post("/users/{user_id}/password/reset")]
+async fn reset_password(
+    db_pool: web::Data<MySqlPool>,
+    auditor: Auditor,
+    path: UserPath,
+) -> impl Responder {
+    let user_id = path.0;
+    let password = temporary_password();
+
+    match save_password(user_id, &password, &db_pool).await {
+        Ok(()) => {
+            println!("🔐 管理员 {} 重置了用户 {} 的密码", auditor.user.user_id, user_id);
+            // 审计日志不记录密码
+            auditor.record(AuditAction::PasswordReset, user_id, None::<()>, None::<()>).await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": {
+                    "user_id": user_id,
+                    "temporary_password": password
+                }
+            }))
+        }
+        Err(response) => response,
+    }
+}
+
+/// 超级管理员分配用户角色，新角色在用户下次登录或刷新令牌时生效
+#[put("/users/{user_id}/role")]
+async fn assign_role(
+    db_pool: web::Data<MySqlPool>,
+    auditor: Auditor,
+    path: web::Path<Uuid>,
+    input: web::Json<AssignRoleRequest>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    if user_id == auditor.user.user_id {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "不能修改自己的角色"
+        }));
+    }
+
+    let before = User::find_by_id(user_id, db_pool.get_ref()).await.ok().flatten().map(|user| user.role);
+    match User::set_role(user_id, input.role, db_pool.get_ref()).await {
+        Ok(true) => {
+            println!("👤 超级管理员 {} 将用户 {} 的角色设为 {}", auditor.user.user_id, user_id, input.role.as_str());
+            auditor
+                .record(AuditAction::RoleAssign, user_id, before.map(|role| json!({ "role": role })), json!({ "role": input.role }))
+                .await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": {
+                    "user_id": user_id,
+                    "role": input.role
+                }
+            }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": format!("用户 {} 不存在", user_id)
+        })),
+        Err(e) => {
+            eprintln!("分配角色失败: {:?}", e);
+            metrics::db_error("user");
             HttpResponse::InternalServerError().body("服务器异常")
         }
     }
 }
 
+/// 以 Argon2id 哈希保存新密码
+async fn save_password(user_id: Uuid, password: &str, pool: &MySqlPool) -> Result<(), HttpResponse> {
+    let password_hash = hash_password_blocking(password.to_string()).await.map_err(|e| {
+        eprintln!("{}", e);
+        HttpResponse::InternalServerError().body("服务器异常")
+    })?;
+    match User::update_password_hash(user_id, &password_hash, pool).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": format!("用户 {} 不存在", user_id)
+        }))),
+        Err(e) => {
+            eprintln!("保存密码失败: {:?}", e);
+            metrics::db_error("user");
+            Err(HttpResponse::InternalServerError().body("服务器异常"))
+        }
+    }
+}
+
+/// 在阻塞线程池中校验密码，Argon2 的计算不占用 actix 工作线程
+async fn verify_password_blocking(password: String, stored_hash: String) -> PasswordMatch {
+    web::block(move || verify_password(&password, &stored_hash))
+        .await
+        .unwrap_or(PasswordMatch::Invalid)
+}
+
+/// 在阻塞线程池中生成密码哈希
+async fn hash_password_blocking(password: String) -> Result<String, String> {
+    web::block(move || hash_password(&password))
+        .await
+        .map_err(|e| format!("密码哈希失败: {}", e))?
+}
+
 #[get("/users/{user_id}/charging_records")] // 根据用户ID获取充电详单
 async fn get_user_charging_records(
     db_pool: web::Data<MySqlPool>,
-    path: web::Path<Uuid>, // 从路径中获取 user_id
+    path: UserPath, // 从路径中获取 user_id，只能查询本人
 ) -> impl Responder {
-    let user_id = path.into_inner();
+    let user_id = path.0;
     match ChargingRecord::find_by_user_id(user_id, db_pool.get_ref()).await { // 调用 ChargingRecord 的查询方法
         Ok(records) => HttpResponse::Ok().json(records),
         Err(e) => {
             eprintln!("Error fetching charging records: {:?}", e);
+            metrics::db_error("user");
             HttpResponse::InternalServerError().body("Failed to fetch charging records")
         },
     }
@@ -105,5 +322,9 @@ pub fn user_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_users)
        .service(register_user)
        .service(login_user)
+       .service(refresh_token)
+       .service(change_password)
+       .service(reset_password)
+       .service(assign_role)
        .service(get_user_charging_records); 
 }
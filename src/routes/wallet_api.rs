@@ -0,0 +1,181 @@
+use actix_web::{web, HttpResponse, Responder};
+use charging_station::metrics;
+use charging_station::models::{
+    round_money, zero_money, AuditAction, ChargingMode, Money, Wallet, WalletTransaction,
+};
+use crate::routes::audit_api::Auditor;
+use charging_station::scheduler::ChargingScheduler;
+use crate::routes::auth::UserPath;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct TopUpInput {
+    pub amount: Money,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuoteQuery {
+    pub mode: ChargingMode,
+    pub amount: f64,
+}
+
+/// 查询钱包余额
+pub async fn get_balance(pool: web::Data<MySqlPool>, path: UserPath) -> impl Responder {
+    let user_id = path.0;
+    match Wallet::find(user_id, &pool).await {
+        Ok(wallet) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": wallet
+        })),
+        Err(e) => {
+            println!("❌ 查询钱包余额失败: {}", e);
+            metrics::db_error("wallet");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询钱包余额失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 管理人员直接为用户充值（线下收款、补偿等），不经支付渠道，每次都记录审计日志
+pub async fn top_up(
+    pool: web::Data<MySqlPool>,
+    auditor: Auditor,
+    path: UserPath,
+    input: web::Json<TopUpInput>,
+) -> impl Responder {
+    let user_id = path.0;
+    let amount = round_money(&input.amount);
+    if amount <= zero_money() {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "充值金额必须大于0"
+        }));
+    }
+
+    match Wallet::top_up(user_id, &amount, &pool).await {
+        Ok(transaction) => {
+            let before = json!({ "balance": &transaction.balance_after - &transaction.amount });
+            auditor.record(AuditAction::WalletTopUp, user_id, before, &transaction).await;
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": transaction
+            }))
+        }
+        Err(e) => {
+            println!("❌ 钱包充值失败: {}", e);
+            metrics::db_error("wallet");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("钱包充值失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 车主自助充值：向支付渠道发起收款，渠道回调确认扣款后余额才到账
+pub async fn create_top_up_payment(
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    path: UserPath,
+    input: web::Json<TopUpInput>,
+) -> impl Responder {
+    let user_id = path.0;
+    let amount = round_money(&input.amount);
+    if amount <= zero_money() {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "充值金额必须大于0"
+        }));
+    }
+    let Some(payment_service) = scheduler.queue_manager.payment_service.read().await.clone() else {
+        return HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "未配置支付渠道"
+        }));
+    };
+
+    match payment_service.start_top_up(user_id, &amount).await {
+        Ok(bill) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": bill,
+            "message": "请在支付渠道完成付款，到账后余额自动更新"
+        })),
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": e
+        })),
+    }
+}
+
+/// 查询钱包流水
+pub async fn get_transactions(
+    pool: web::Data<MySqlPool>,
+    path: UserPath,
+    query: web::Query<HistoryQuery>,
+) -> impl Responder {
+    let user_id = path.0;
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    match WalletTransaction::find_by_user_id(user_id, page_size, (page - 1).saturating_mul(page_size), &pool).await {
+        Ok(transactions) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": transactions,
+            "count": transactions.len(),
+            "page": page,
+            "page_size": page_size
+        })),
+        Err(e) => {
+            println!("❌ 查询钱包流水失败: {}", e);
+            metrics::db_error("wallet");
+            HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "message": format!("查询钱包流水失败: {}", e)
+            }))
+        }
+    }
+}
+
+/// 按当前电价预估充电费用，提交请求时余额需不低于该报价
+pub async fn get_quote(
+    scheduler: web::Data<Arc<ChargingScheduler>>,
+    query: web::Query<QuoteQuery>,
+) -> impl Responder {
+    if query.amount <= 0.0 {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "充电量必须大于0"
+        }));
+    }
+    let quote = scheduler.queue_manager.quote_fee(query.mode, query.amount).await;
+    HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": {
+            "mode": query.mode,
+            "amount": query.amount,
+            "estimated_fee": quote
+        }
+    }))
+}
+
+/// 配置钱包路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/wallet")
+            .route("/quote", web::get().to(get_quote))
+            .route("/{user_id}", web::get().to(get_balance))
+            .route("/{user_id}/top-up", web::post().to(top_up))
+            .route("/{user_id}/top-up-payments", web::post().to(create_top_up_payment))
+            .route("/{user_id}/transactions", web::get().to(get_transactions))
+    );
+}
@@ -1,10 +1,12 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
-use crate::models::{ChargingMode, ChargingPile, ChargingRequest};
+use crate::models::{ChargingMode, ChargingPile, ChargingRequest, PileStatus};
 use crate::scheduler::queue_manager::{QueueManager, PileInfo};
 use std::collections::HashMap;
+use std::time::Instant;
 use chrono::Utc;
+use crate::metrics;
 
 /// 调度器
 pub struct Dispatcher {
@@ -41,6 +43,8 @@ impl Dispatcher {
 
     /// 系统tick
     pub async fn tick(&self) {
+        let started = Instant::now();
+
         // 检查充电完成并启动下一辆车
         self.queue_manager.tick().await;
         
@@ -48,6 +52,8 @@ impl Dispatcher {
         if self.is_calling().await {
             self.dispatch_waiting_vehicles().await;
         }
+
+        metrics::TICK_DURATION.observe(started.elapsed().as_secs_f64());
     }
 
     /// 调度等候车辆
@@ -64,7 +70,8 @@ impl Dispatcher {
             let mut best_pile: Option<(String, f64)> = None;
             for (pile_number, pile_info) in pile_infos.iter() {
                 let pile = pile_info.pile.read().await;
-                if pile.mode.to_string() == request.mode && pile_info.queue.len() < 1 {
+                let in_service = matches!(pile.status, PileStatus::Available | PileStatus::Charging);
+                if pile.mode.to_string() == request.mode && in_service && pile_info.queue.len() < 1 {
                     // 计算完成时间
                     let completion_time = pile_info.calculate_completion_time(&request, &self.queue_manager.time_system).await;
                     if best_pile.is_none() || completion_time < best_pile.as_ref().unwrap().1 {
@@ -76,7 +83,12 @@ impl Dispatcher {
                 if let Some(pile_info) = pile_infos.get_mut(&best_pile_number) {
                     // 从等候区移除
                     if let Some(idx) = waiting_queue.iter().position(|r| r.id == request.id) {
-                        let request_arc = waiting_queue.remove(idx).unwrap();
+                        let mut dispatched = (*waiting_queue.remove(idx).unwrap()).clone();
+                        dispatched.timeline.dispatched_at = Some(now);
+                        if let Some(submitted_at) = dispatched.timeline.submitted_at {
+                            metrics::DISPATCH_LATENCY.observe((now - submitted_at).num_seconds().max(0) as f64);
+                        }
+                        let request_arc = Arc::new(dispatched);
                         pile_info.queue.push_back(request_arc.clone());
                         println!("✅ 用户 {} 已加入充电桩 {} 队列", request_arc.user_id, best_pile_number);
 
@@ -95,12 +107,15 @@ impl Dispatcher {
                                 .await
                             {
                                 println!("⚠️ 无法更新充电桩 {} 状态为 Charging: {}", best_pile_number, e);
+                                metrics::db_error("dispatcher");
                             } else {
                                 println!("🔄 数据库已更新充电桩 {} 状态为 Charging", best_pile_number);
                             }
                         }
                         // 立即开始充电（如果当前没人充电）
-                        pile_info.start_next_charging(now).await;
+                        if let Some(started) = pile_info.start_next_charging(now).await {
+                            self.queue_manager.notify_charging_started(pile_info, &started).await;
+                        }
                     }
                 }
             }
@@ -134,21 +149,216 @@ impl Dispatcher {
         best_pile
     }
 
-    /// 处理充电桩故障
-    pub async fn handle_pile_fault(&self, pile_id: &str) {
-        // 更新充电桩状态
-        if let Some(pile_info) = self.queue_manager.pile_infos.read().await.get(pile_id) {
-            let mut pile = pile_info.pile.blocking_write();
-            pile.report_fault();
+    /// 处理充电桩故障：中断并结算正在充电的车辆，排队车辆回到等候区
+    pub async fn handle_pile_fault(&self, pile_id: &str) -> Result<(), String> {
+        let now = self.queue_manager.time_system.current_time();
+        let (pile, was_faulted, interrupted) = {
+            let mut pile_infos = self.queue_manager.pile_infos.write().await;
+            let pile_info = pile_infos
+                .get_mut(pile_id)
+                .ok_or_else(|| format!("充电桩 {} 不存在", pile_id))?;
+
+            // 更新充电桩状态
+            let was_faulted = {
+                let mut pile = pile_info.pile.write().await;
+                let was_faulted = pile.status == PileStatus::Fault;
+                pile.report_fault();
+                was_faulted
+            };
+
+            // 模拟充电桩按已充电量结束本次充电，接入OCPP的充电桩由桩端上报 StopTransaction 结算
+            let interrupted = if pile_info.ocpp_connected {
+                None
+            } else {
+                pile_info.interrupt_charging(&self.queue_manager.time_system).await
+            };
+
+            // 排队中的车辆回到等候区队首，重新调度
+            let mut waiting_queue = self.queue_manager.waiting_queue.write().await;
+            while let Some(request) = pile_info.queue.pop_back() {
+                println!("↩️ 充电桩 {} 故障，车辆 {} 回到等候区", pile_id, request.user_id);
+                waiting_queue.push_front(request);
+            }
+            (pile_info.pile.clone(), was_faulted, interrupted)
+        };
+
+        // 结算和故障时段的数据库读写在释放 pile_infos 锁后进行
+        if !was_faulted {
+            self.queue_manager.record_pile_fault(pile_id, true, now).await;
+        }
+        if let Some(session) = interrupted {
+            self.queue_manager.settle_session(&pile, &session, session.end_time).await;
         }
+        Ok(())
     }
 
     /// 处理充电桩恢复
     pub async fn handle_pile_recovery(&self, pile_id: &str) {
         // 更新充电桩状态
         if let Some(pile_info) = self.queue_manager.pile_infos.read().await.get(pile_id) {
-            let mut pile = pile_info.pile.blocking_write();
-            pile.repair().unwrap_or_else(|e| println!("修复充电桩失败: {}", e));
+            let repaired = pile_info.pile.write().await.repair();
+            match repaired {
+                Ok(()) => {
+                    let now = self.queue_manager.time_system.current_time();
+                    self.queue_manager.record_pile_fault(pile_id, false, now).await;
+                }
+                Err(e) => println!("修复充电桩失败: {}", e),
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fault_requeues_and_skips_pile() {
+        let queue_manager = Arc::new(QueueManager::new());
+        for number in ["F1", "F2"] {
+            queue_manager
+                .add_pile(Arc::new(RwLock::new(ChargingPile::new(number.to_string(), ChargingMode::Fast))))
+                .await;
+        }
+        let dispatcher = Dispatcher::new(queue_manager.clone());
+
+        // F1 上有一辆排队车辆
+        let queued = Arc::new(ChargingRequest::new(Uuid::new_v4(), ChargingMode::Fast, 30.0, "F1".to_string()));
+        queue_manager.pile_infos.write().await.get_mut("F1").unwrap().queue.push_back(queued.clone());
+
+        // 故障后排队车辆回到等候区，并被调度到 F2
+        dispatcher.handle_pile_fault("F1").await.unwrap();
+        assert_eq!(queue_manager.waiting_queue.read().await.len(), 1);
+        dispatcher.start_calling().await;
+        dispatcher.dispatch_waiting_vehicles().await;
+        {
+            let pile_infos = queue_manager.pile_infos.read().await;
+            assert!(pile_infos["F1"].is_idle());
+            let charging = pile_infos["F2"].current_charging.as_ref().unwrap();
+            assert_eq!(charging.id, queued.id);
+            assert!(charging.timeline.dispatched_at.is_some());
+            assert_eq!(charging.timeline.started_at, charging.timeline.dispatched_at);
         }
+
+        dispatcher.handle_pile_recovery("F1").await;
+        let pile_infos = queue_manager.pile_infos.read().await;
+        assert_eq!(pile_infos["F1"].pile.read().await.status, PileStatus::Available);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_fault_settles_current_charging() {
+        let queue_manager = Arc::new(QueueManager::new());
+        queue_manager
+            .add_pile(Arc::new(RwLock::new(ChargingPile::new("F1".to_string(), ChargingMode::Fast))))
+            .await;
+        let dispatcher = Dispatcher::new(queue_manager.clone());
+        assert!(dispatcher.handle_pile_fault("F9").await.is_err());
+
+        // F1 正在为请求60度的车辆充电，已充电1小时
+        let now = queue_manager.time_system.current_time();
+        let charging = Arc::new(ChargingRequest::new(Uuid::new_v4(), ChargingMode::Fast, 60.0, "F1".to_string()));
+        {
+            let mut pile_infos = queue_manager.pile_infos.write().await;
+            let pile_info = pile_infos.get_mut("F1").unwrap();
+            pile_info.current_charging = Some(charging);
+            pile_info.charging_start_time = Some(now - chrono::Duration::hours(1));
+        }
+
+        // 故障时按已充的约30度结算，充电桩不再占用
+        dispatcher.handle_pile_fault("F1").await.unwrap();
+        let pile_infos = queue_manager.pile_infos.read().await;
+        let pile_info = &pile_infos["F1"];
+        assert!(pile_info.current_charging.is_none());
+        let pile = pile_info.pile.read().await;
+        assert_eq!(pile.status, PileStatus::Fault);
+        assert_eq!(pile.total_charge_count, 1);
+        assert!((pile.total_charge_amount - 30.0).abs() < 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_overstay_keeps_pile_busy_until_unplug() {
+        let queue_manager = Arc::new(QueueManager::new());
+        queue_manager
+            .add_pile(Arc::new(RwLock::new(ChargingPile::new("F1".to_string(), ChargingMode::Fast))))
+            .await;
+        queue_manager.set_overstay_enabled(true).await;
+        let dispatcher = Dispatcher::new(queue_manager.clone());
+        dispatcher.start_calling().await;
+
+        // F1 上的车辆已充满，另有一辆车排队
+        let now = queue_manager.time_system.current_time();
+        let charging = Arc::new(ChargingRequest::new(Uuid::new_v4(), ChargingMode::Fast, 30.0, "F1".to_string()));
+        let queued = Arc::new(ChargingRequest::new(Uuid::new_v4(), ChargingMode::Fast, 30.0, "F2".to_string()));
+        {
+            let mut pile_infos = queue_manager.pile_infos.write().await;
+            let pile_info = pile_infos.get_mut("F1").unwrap();
+            pile_info.current_charging = Some(charging.clone());
+            pile_info.charging_start_time = Some(now - chrono::Duration::hours(2));
+            pile_info.queue.push_back(queued.clone());
+        }
+
+        // 充电结束后车辆未拔枪，充电桩保持占用
+        dispatcher.tick().await;
+        let end_time = {
+            let pile_infos = queue_manager.pile_infos.read().await;
+            let pile_info = &pile_infos["F1"];
+            assert!(pile_info.current_charging.is_none());
+            assert!(!pile_info.is_idle());
+            assert_eq!(pile_info.queue.len(), 1);
+            let session = pile_info.plugged_in.as_ref().unwrap();
+            assert_eq!(session.request.id, charging.id);
+            session.end_time
+        };
+
+        // 超出免费时长10分钟后拔枪，收取占位费并开始下一辆车
+        let record = queue_manager
+            .unplug("F1", end_time + chrono::Duration::minutes(25))
+            .await
+            .unwrap();
+        let idle_fee: crate::models::Money = "5.00".parse().unwrap();
+        assert_eq!(record.idle_fee, idle_fee);
+        assert_eq!(record.total_fee, &record.charging_fee + &record.service_fee + &idle_fee);
+
+        // 分时段明细小计之和等于电费加服务费
+        assert!(!record.line_items.is_empty());
+        let subtotal: crate::models::Money = record.line_items.iter().map(|item| &item.subtotal).sum();
+        assert_eq!(subtotal, &record.charging_fee + &record.service_fee);
+        {
+            let pile_infos = queue_manager.pile_infos.read().await;
+            assert_eq!(pile_infos["F1"].current_charging.as_ref().unwrap().id, queued.id);
+        }
+        assert!(queue_manager.unplug("F1", end_time).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_outstanding_quote_sums_unsettled_requests() {
+        use crate::billing::{Tariff, TariffPeriod, TariffSchedule};
+        use crate::models::zero_money;
+        use std::str::FromStr;
+
+        // 单一电价 0.5 元/度、服务费 0.6 元/度，报价与当前时间无关
+        let yuan = |value| bigdecimal::BigDecimal::from_str(value).unwrap();
+        let flat = Tariff::new(
+            "单一电价".to_string(),
+            Utc::now() - chrono::Duration::days(1),
+            None,
+            vec![TariffPeriod::new("全天", 0, 0, yuan("0.5"), yuan("0.6"))],
+        );
+        let queue_manager = QueueManager::new();
+        *queue_manager.tariff_schedule.write().await = TariffSchedule::new(vec![flat]);
+        queue_manager
+            .add_pile(Arc::new(RwLock::new(ChargingPile::new("F1".to_string(), ChargingMode::Fast))))
+            .await;
+
+        // 用户在等候区和 F1 上各有一个请求，另一用户的请求不计入
+        let user_id = Uuid::new_v4();
+        let waiting = Arc::new(ChargingRequest::new(user_id, ChargingMode::Fast, 30.0, "F1".to_string()));
+        let charging = Arc::new(ChargingRequest::new(user_id, ChargingMode::Fast, 20.0, "F2".to_string()));
+        let other = Arc::new(ChargingRequest::new(Uuid::new_v4(), ChargingMode::Fast, 10.0, "F3".to_string()));
+        queue_manager.add_to_waiting_queue(waiting).await.unwrap();
+        queue_manager.add_to_waiting_queue(other).await.unwrap();
+        queue_manager.pile_infos.write().await.get_mut("F1").unwrap().current_charging = Some(charging);
+
+        assert_eq!(queue_manager.outstanding_quote(user_id).await, yuan("55.00"));
+        assert_eq!(queue_manager.outstanding_quote(Uuid::new_v4()).await, zero_money());
+    }
+}
@@ -4,12 +4,13 @@ pub mod queue_manager;
 
 pub use dispatcher::Dispatcher;
 pub use number_generator::QueueNumberGenerator;
-pub use queue_manager::{QueueManager, PileStatusInfo};
+pub use queue_manager::{FinishedSession, QueueManager, PileStatusInfo};
 
-use crate::models::{ChargingMode, ChargingPile, ChargingRequest};
+use crate::billing::Coupon;
+use crate::models::{ChargingMode, ChargingPile, ChargingRecord, ChargingRequest, Wallet};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tokio::time;
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
@@ -22,6 +23,7 @@ pub struct ChargingScheduler {
     dispatcher: Arc<Dispatcher>,
     is_running: Arc<RwLock<bool>>,
     db_pool: Option<Arc<sqlx::MySqlPool>>,
+    submit_lock: Mutex<()>, // 串行化余额校验与入队，避免并发提交重复占用同一笔余额
 }
 
 impl ChargingScheduler {
@@ -36,6 +38,7 @@ impl ChargingScheduler {
             dispatcher,
             is_running: Arc::new(RwLock::new(false)),
             db_pool: None,
+            submit_lock: Mutex::new(()),
         }
     }
 
@@ -56,6 +59,9 @@ impl ChargingScheduler {
         if let Some(pool) = &self.db_pool {
             self.queue_manager.set_db_pool(pool.clone()).await;
             println!("✅ 数据库连接池已设置到队列管理器");
+            self.queue_manager.reload_tariffs().await;
+            self.queue_manager.reload_service_rates().await;
+            self.queue_manager.reload_promotions().await;
         } else {
             println!("⚠️ 调度器没有数据库连接池");
         }
@@ -112,22 +118,63 @@ impl ChargingScheduler {
 
     /// 提交充电请求
     pub async fn submit_request(&self, mut request: ChargingRequest) -> Result<(), String> {
+        // 优惠券须存在、未使用且所属促销启用中（在预授权之前校验，校验失败无需释放预授权）
+        if let (Some(code), Some(pool)) = (&request.coupon_code, &self.db_pool) {
+            let coupon = Coupon::find(code, pool)
+                .await
+                .map_err(|e| format!("查询优惠券失败: {}", e))?
+                .filter(|coupon| coupon.redeemed_by.is_none())
+                .ok_or_else(|| format!("优惠券 {} 无效或已使用", code))?;
+            if !self.queue_manager.promotions.read().await.iter().any(|p| p.id == coupon.promotion_id) {
+                return Err(format!("优惠券 {} 所属活动已结束", code));
+            }
+        }
+
+        let mode: ChargingMode = request.mode.parse()?;
+
+        // 配置了支付渠道时按报价预授权，否则钱包余额需覆盖本次及该用户未结算请求的预估费用
+        let _submit_guard = self.submit_lock.lock().await;
+        let payment_service = self.queue_manager.payment_service.read().await.clone();
+        if let Some(payment_service) = &payment_service {
+            let quote = self.queue_manager.quote_fee(mode, request.amount).await;
+            payment_service.pre_authorize(request.id, request.user_id, &quote).await?;
+        } else if let Some(pool) = &self.db_pool {
+            let quote = self.queue_manager.quote_fee(mode, request.amount).await;
+            let outstanding = self.queue_manager.outstanding_quote(request.user_id).await;
+            let wallet = Wallet::find(request.user_id, pool)
+                .await
+                .map_err(|e| format!("查询钱包余额失败: {}", e))?;
+            let available = &wallet.balance - &outstanding;
+            if available < quote {
+                return Err(format!(
+                    "余额不足：预估费用 {}元，当前余额 {}元，其中 {}元已被未结算的请求占用",
+                    quote, wallet.balance, outstanding
+                ));
+            }
+        }
+
         // 生成排队号码
-        let queue_number = self.number_generator.generate(request.mode.parse()?);
+        let queue_number = self.number_generator.generate(mode);
         request.queue_number = queue_number;
         
         println!("生成排队号码: {} 用户: {}", request.queue_number, request.user_id);
+        request.timeline.submitted_at = Some(self.queue_manager.time_system.current_time());
         
-        // 添加到等候区
-        self.queue_manager.add_to_waiting_queue(Arc::new(request)).await?;
+        // 添加到等候区，失败时释放预授权
+        let request_id = request.id;
+        if let Err(e) = self.queue_manager.add_to_waiting_queue(Arc::new(request)).await {
+            if let Some(payment_service) = &payment_service {
+                payment_service.cancel(request_id).await;
+            }
+            return Err(e);
+        }
         
         Ok(())
     }
 
     /// 处理充电桩故障
     pub async fn handle_pile_fault(&self, pile_id: &str) -> Result<(), String> {
-        self.dispatcher.handle_pile_fault(pile_id).await;
-        Ok(())
+        self.dispatcher.handle_pile_fault(pile_id).await
     }
 
     /// 处理充电桩恢复
@@ -136,6 +183,12 @@ impl ChargingScheduler {
         Ok(())
     }
 
+    /// 车辆拔枪，结算充电详单
+    pub async fn unplug_vehicle(&self, pile_id: &str) -> Result<ChargingRecord, String> {
+        let now = self.queue_manager.time_system.current_time();
+        self.queue_manager.unplug(pile_id, now).await
+    }
+
     /// 获取系统状态（前端接口）
     pub async fn get_system_status(&self) -> SystemStatus {
         let queue_status = self.queue_manager.get_status().await;
@@ -144,6 +197,7 @@ impl ChargingScheduler {
                 pile_number: p.pile_number,
                 pile_mode: p.pile_mode,
                 is_idle: p.is_idle,
+                awaiting_unplug: p.awaiting_unplug,
                 current_charging_user: p.current_charging_user,
                 current_request: p.current_request,
                 queue_count: p.queue_count,
@@ -165,13 +219,49 @@ impl ChargingScheduler {
         }
     }
 
-    /// 手动触发系统tick（用于测试）
-    pub async fn manual_tick(&self) {
-        self.queue_manager.tick().await;
+    /// 查询充电桩当前状态（用于审计记录操作前后的状态）
+    pub async fn pile_state(&self, pile_id: &str) -> Option<crate::models::PileStatus> {
+        let pile = self.queue_manager.pile_infos.read().await.get(pile_id)?.pile.clone();
+        let status = pile.read().await.status;
+        Some(status)
     }
 
-    /// 取消充电请求
+    /// 查找等候区或充电桩上的充电请求所属用户
+    pub async fn request_owner(&self, request_id: Uuid) -> Option<Uuid> {
+        self.find_request(request_id).await.map(|r| r.user_id)
+    }
+
+    /// 查找等候区或充电桩上的充电请求
+    pub async fn find_request(&self, request_id: Uuid) -> Option<Arc<ChargingRequest>> {
+        if let Some(request) = self.queue_manager.waiting_queue.read().await.iter().find(|r| r.id == request_id) {
+            return Some(request.clone());
+        }
+        let pile_infos = self.queue_manager.pile_infos.read().await;
+        pile_infos
+            .values()
+            .flat_map(|pile_info| pile_info.current_charging.iter().chain(pile_info.queue.iter()))
+            .find(|r| r.id == request_id)
+            .cloned()
+    }
+
+    /// 取消充电请求，并释放其预授权
     pub async fn cancel_request(&self, request_id: Uuid) -> Result<(), String> {
+        self.remove_request(request_id).await?;
+        self.release_authorizations(&[request_id]).await;
+        Ok(())
+    }
+
+    /// 释放已取消请求的预授权
+    async fn release_authorizations(&self, request_ids: &[Uuid]) {
+        if let Some(payment_service) = self.queue_manager.payment_service.read().await.clone() {
+            for request_id in request_ids {
+                payment_service.cancel(*request_id).await;
+            }
+        }
+    }
+
+    /// 从等候区或充电桩队列中移除充电请求
+    async fn remove_request(&self, request_id: Uuid) -> Result<(), String> {
         let mut queue_manager = self.queue_manager.clone();
         
         // 从等候区移除
@@ -319,10 +409,12 @@ impl ChargingScheduler {
     pub async fn cancel_request_by_user(&self, user_id: Uuid) -> Result<(), String> {
         let mut queue_manager = self.queue_manager.clone();
         let mut found = false;
+        let mut cancelled = Vec::new();
         
         // 从等候区移除该用户的所有请求
         {
             let mut waiting_queue = queue_manager.waiting_queue.write().await;
+            cancelled.extend(waiting_queue.iter().filter(|r| r.user_id == user_id).map(|r| r.id));
             let original_len = waiting_queue.len();
             waiting_queue.retain(|r| r.user_id != user_id);
             let removed_count = original_len - waiting_queue.len();
@@ -340,6 +432,7 @@ impl ChargingScheduler {
                 if let Some(ref current) = pile_info.current_charging {
                     if current.user_id == user_id {
                         println!("取消用户 {} 的当前充电请求: {}", user_id, current.id);
+                        cancelled.push(current.id);
                         pile_info.current_charging = None;
                         pile_info.charging_start_time = None;
                         found = true;
@@ -349,6 +442,7 @@ impl ChargingScheduler {
                 }
                 
                 // 检查队列中的车辆
+                cancelled.extend(pile_info.queue.iter().filter(|r| r.user_id == user_id).map(|r| r.id));
                 let original_len = pile_info.queue.len();
                 pile_info.queue.retain(|r| r.user_id != user_id);
                 let removed_count = original_len - pile_info.queue.len();
@@ -360,6 +454,7 @@ impl ChargingScheduler {
         }
         
         if found {
+            self.release_authorizations(&cancelled).await;
             Ok(())
         } else {
             Err("未找到该用户的充电请求".to_string())
@@ -380,6 +475,7 @@ pub struct ChargingRequestInput {
     pub user_id: Uuid,
     pub mode: String, // "Fast" or "Slow"
     pub amount: f64,
+    pub coupon_code: Option<String>,
 }
 
 /// 前端响应结构
@@ -446,7 +542,7 @@ pub async fn api_submit_request(input: ChargingRequestInput) -> Result<ChargingR
     };
 
     let scheduler = get_global_scheduler();
-    let request = ChargingRequest::new(input.user_id, mode, input.amount, "".to_string());
+    let request = ChargingRequest::new(input.user_id, mode, input.amount, "".to_string()).with_coupon(input.coupon_code);
     scheduler.submit_request(request.clone()).await?;
     
     Ok(ChargingRequestResponse::from(request))
@@ -490,6 +586,7 @@ pub struct PileStatus {
     pub pile_number: String,
     pub pile_mode: ChargingMode,
     pub is_idle: bool,
+    pub awaiting_unplug: bool,
     pub current_charging_user: Option<Uuid>,
     pub current_request: Option<ChargingRequest>,
     pub queue_count: usize,
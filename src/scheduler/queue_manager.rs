@@ -1,3 +1,4 @@
+use bigdecimal::ToPrimitive;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
@@ -6,11 +7,17 @@ use std::time::Instant;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::billing::FeeCalculator;
+use crate::billing::{
+    BillingRecord, Coupon, FeeCalculator, Promotion, PromotionContext, ServiceRates, TariffCalendar, TariffSchedule, UserSegment,
+    IDLE_GRACE_MINUTES,
+};
+use crate::metrics;
+use crate::ocpp::CentralSystem;
+use crate::payment::PaymentService;
 use crate::models::{
-    ChargingMode, ChargingPile, ChargingRecord, ChargingRequest, PileStatus as ModelsPileStatus,
-    RequestStatus, FAST_CHARGING_POWER, PILE_QUEUE_CAPACITY, SLOW_CHARGING_POWER,
-    WAITING_AREA_CAPACITY,
+    ChargingMode, ChargingPile, ChargingRecord, ChargingRequest, MeterSample, NewChargingRecord, PileFault,
+    PileStatus as ModelsPileStatus, RequestStatus, RequestTimeline, FAST_CHARGING_POWER, METER_SAMPLE_INTERVAL,
+    PILE_QUEUE_CAPACITY, SLOW_CHARGING_POWER, WAITING_AREA_CAPACITY, zero_money, Money,
 };
 
 /// 时间系统 - 30倍加速
@@ -47,6 +54,16 @@ impl TimeSystem {
     }
 }
 
+/// 已结束充电、等待结算的一次充电
+#[derive(Debug, Clone)]
+pub struct FinishedSession {
+    pub request: Arc<ChargingRequest>,
+    pub charge_amount: f64,              // 实际充电量（度）
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,         // 充电结束时间（超时占位费由此起算）
+    pub meter_samples: Vec<MeterSample>,
+}
+
 /// 充电桩状态信息
 #[derive(Debug, Clone)]
 pub struct PileInfo {
@@ -54,6 +71,9 @@ pub struct PileInfo {
     pub queue: VecDeque<Arc<ChargingRequest>>,
     pub current_charging: Option<Arc<ChargingRequest>>,
     pub charging_start_time: Option<DateTime<Utc>>,
+    pub ocpp_connected: bool, // 是否为通过OCPP接入的真实充电桩
+    pub meter_samples: Vec<MeterSample>, // 当前充电的电表采样
+    pub plugged_in: Option<FinishedSession>, // 充电结束但尚未拔枪的车辆
 }
 
 impl PileInfo {
@@ -63,6 +83,9 @@ impl PileInfo {
             queue: VecDeque::new(),
             current_charging: None,
             charging_start_time: None,
+            ocpp_connected: false,
+            meter_samples: Vec::new(),
+            plugged_in: None,
         }
     }
 
@@ -92,7 +115,13 @@ impl PileInfo {
 
         let queue_amount: f64 = self.queue.iter().map(|r| r.amount).sum();
 
-        (remaining_amount + queue_amount + new_request.amount) / power
+        // 尚未拔枪的车辆按剩余免费占位时长估算
+        let overstay_hours = self.plugged_in.as_ref().map_or(0.0, |session| {
+            let idle_hours = time_system.get_elapsed_hours(session.end_time);
+            (IDLE_GRACE_MINUTES as f64 / 60.0 - idle_hours).max(0.0)
+        });
+
+        (remaining_amount + queue_amount + new_request.amount) / power + overstay_hours
     }
 
     /// 检查是否有空间
@@ -102,14 +131,14 @@ impl PileInfo {
 
     /// 检查是否空闲
     pub fn is_idle(&self) -> bool {
-        self.current_charging.is_none() && self.queue.is_empty()
+        self.current_charging.is_none() && self.plugged_in.is_none() && self.queue.is_empty()
     }
 
     /// 检查充电完成
     pub async fn check_charging_completion(
         &mut self,
         time_system: &TimeSystem,
-    ) -> Option<(Arc<ChargingRequest>, DateTime<Utc>)> {
+    ) -> Option<FinishedSession> {
         if let (Some(ref charging), Some(start_time)) =
             (&self.current_charging, self.charging_start_time)
         {
@@ -118,35 +147,57 @@ impl PileInfo {
             let required_hours = charging.amount / power;
 
             if elapsed_hours >= required_hours {
-                // 充电完成，保存开始时间然后清除状态
-                let charging_start_time = start_time;
-                let completed = self.current_charging.take().unwrap();
-                self.charging_start_time = None;
-
-                // 克隆并更新状态
-                let mut completed_request = (*completed).clone();
-                if let Err(e) = completed_request.complete_charging() {
-                    println!("⚠️ 更新充电完成状态失败: {}", e);
-                } else {
-                    println!("✅ 请求状态已更新为已完成: {}", completed_request.user_id);
-                }
-
+                let charge_amount = charging.amount;
+                let session = self.take_session(time_system.current_time(), charge_amount)?;
                 println!(
                     "🎉 车辆 {} 在充电桩 {} 完成充电! (充电量: {}度)",
-                    completed_request.user_id,
+                    session.request.user_id,
                     self.pile.read().await.number,
-                    completed_request.amount
+                    charge_amount
                 );
+                return Some(session);
+            }
+        }
+        None
+    }
 
-                let completed_arc = Arc::new(completed_request);
+    /// 充电桩故障时中断当前充电，按已充电时长折算实际充电量
+    pub async fn interrupt_charging(&mut self, time_system: &TimeSystem) -> Option<FinishedSession> {
+        let start_time = self.charging_start_time?;
+        let requested = self.current_charging.as_ref()?.amount;
+        let power = self.get_charging_power().await;
+        let charge_amount = (time_system.get_elapsed_hours(start_time) * power).clamp(0.0, requested);
+        let session = self.take_session(time_system.current_time(), charge_amount)?;
+        println!(
+            "⚡ 车辆 {} 在充电桩 {} 充电中断 (已充电量: {:.2}度)",
+            session.request.user_id,
+            self.pile.read().await.number,
+            charge_amount
+        );
+        Some(session)
+    }
 
-                // 立即开始下一辆车充电
-                self.start_next_charging(time_system.current_time()).await;
+    /// 结束当前充电并清除状态，返回待结算的会话
+    fn take_session(&mut self, end_time: DateTime<Utc>, charge_amount: f64) -> Option<FinishedSession> {
+        let charging_start_time = self.charging_start_time.take()?;
+        let completed = self.current_charging.take()?;
 
-                return Some((completed_arc, charging_start_time));
-            }
+        // 克隆并更新状态
+        let mut completed_request = (*completed).clone();
+        completed_request.timeline.completed_at = Some(end_time);
+        if let Err(e) = completed_request.complete_charging() {
+            println!("⚠️ 更新充电完成状态失败: {}", e);
+        } else {
+            println!("✅ 请求状态已更新为已完成: {}", completed_request.user_id);
         }
-        None
+
+        Some(FinishedSession {
+            charge_amount,
+            request: Arc::new(completed_request),
+            start_time: charging_start_time,
+            end_time,
+            meter_samples: std::mem::take(&mut self.meter_samples),
+        })
     }
 
     /// 开始为下一辆车充电
@@ -154,20 +205,23 @@ impl PileInfo {
         &mut self,
         current_time: DateTime<Utc>,
     ) -> Option<Arc<ChargingRequest>> {
-        if self.current_charging.is_none() && !self.queue.is_empty() {
+        if self.current_charging.is_none() && self.plugged_in.is_none() && !self.queue.is_empty() {
             let next_request = self.queue.pop_front().unwrap();
 
             // 克隆请求并更新状态为"充电中"
             let mut charging_request = (*next_request).clone();
+            charging_request.timeline.started_at = Some(current_time);
             if let Err(e) = charging_request.start_charging() {
                 println!("⚠️ 更新充电状态失败: {}", e);
             } else {
                 println!("✅ 请求状态已更新为充电中: {}", charging_request.user_id);
             }
 
+            metrics::SESSIONS_STARTED.inc(&charging_request.mode);
             let charging_request_arc = Arc::new(charging_request);
             self.current_charging = Some(charging_request_arc.clone());
             self.charging_start_time = Some(current_time);
+            self.meter_samples.clear();
 
             println!(
                 "🔌 车辆 {} 在充电桩 {} 开始充电 (充电量: {}度)",
@@ -181,6 +235,35 @@ impl PileInfo {
         None
     }
 
+    /// 模拟电表采样：距上次采样满 METER_SAMPLE_INTERVAL 分钟时记录一个计量点
+    pub async fn sample_meter(&mut self, time_system: &TimeSystem) -> Option<MeterSample> {
+        let (Some(charging), Some(start_time)) = (&self.current_charging, self.charging_start_time) else {
+            return None;
+        };
+
+        let now = time_system.current_time();
+        let last_time = self
+            .meter_samples
+            .last()
+            .map(|s| s.timestamp.and_utc())
+            .unwrap_or(start_time);
+        if now - last_time < Duration::minutes(METER_SAMPLE_INTERVAL) {
+            return None;
+        }
+
+        let power = self.get_charging_power().await;
+        let energy = (time_system.get_elapsed_hours(start_time) * power).min(charging.amount);
+        let sample = MeterSample::new(
+            charging.id,
+            self.pile.read().await.number.clone(),
+            now,
+            energy,
+            power,
+        );
+        self.meter_samples.push(sample.clone());
+        Some(sample)
+    }
+
     /// 获取充电进度
     pub async fn get_charging_progress(&self, time_system: &TimeSystem) -> Option<f64> {
         if let (Some(ref charging), Some(start_time)) =
@@ -208,6 +291,24 @@ pub struct QueueManager {
 
     // 数据库连接池
     pub db_pool: RwLock<Option<Arc<sqlx::MySqlPool>>>,
+
+    // OCPP中央系统（真实充电桩接入）
+    pub central_system: RwLock<Option<Arc<CentralSystem>>>,
+
+    // 是否启用超时占位模式（充电结束后车辆拔枪前充电桩保持占用）
+    pub overstay_enabled: RwLock<bool>,
+
+    // 电价表
+    pub tariff_schedule: RwLock<TariffSchedule>,
+
+    // 按充电模式和充电桩设置的服务费率
+    pub service_rates: RwLock<ServiceRates>,
+
+    // 生效中的促销规则
+    pub promotions: RwLock<Vec<Promotion>>,
+
+    // 支付服务（未设置时从预付费钱包扣费）
+    pub payment_service: RwLock<Option<Arc<PaymentService>>>,
 }
 
 impl QueueManager {
@@ -217,6 +318,12 @@ impl QueueManager {
             pile_infos: RwLock::new(HashMap::new()),
             time_system: TimeSystem::new(),
             db_pool: RwLock::new(None),
+            central_system: RwLock::new(None),
+            overstay_enabled: RwLock::new(false),
+            tariff_schedule: RwLock::new(TariffSchedule::default()),
+            service_rates: RwLock::new(ServiceRates::default()),
+            promotions: RwLock::new(Vec::new()),
+            payment_service: RwLock::new(None),
         }
     }
 
@@ -227,6 +334,92 @@ impl QueueManager {
         println!("✅ 队列管理器数据库连接池已设置");
     }
 
+    /// 设置支付服务，设置后充电费用通过支付渠道预授权和扣款
+    pub async fn set_payment_service(&self, service: Arc<PaymentService>) {
+        println!("✅ 支付渠道已设置: {}", service.provider_name());
+        *self.payment_service.write().await = Some(service);
+    }
+
+    /// 设置电价日历，按日类型和季节选择时段布局
+    pub async fn set_tariff_calendar(&self, calendar: TariffCalendar) {
+        let mut schedule = self.tariff_schedule.write().await;
+        *schedule = schedule.clone().with_calendar(calendar);
+        println!("✅ 电价日历已设置");
+    }
+
+    /// 从数据库重新加载电价表，保留当前的电价日历
+    pub async fn reload_tariffs(&self) {
+        let Some(pool) = self.db_pool.read().await.clone() else {
+            println!("⚠️ 数据库连接池未设置，使用默认电价");
+            return;
+        };
+        match TariffSchedule::load(&pool).await {
+            Ok(schedule) => {
+                println!("✅ 已加载 {} 个电价方案", schedule.tariffs().len());
+                let mut current = self.tariff_schedule.write().await;
+                *current = schedule.with_calendar(current.calendar().clone());
+            }
+            Err(e) => println!("⚠️ 加载电价方案失败，继续使用当前电价: {}", e),
+        }
+    }
+
+    /// 从数据库重新加载服务费率表
+    pub async fn reload_service_rates(&self) {
+        let Some(pool) = self.db_pool.read().await.clone() else {
+            return;
+        };
+        match ServiceRates::load(&pool).await {
+            Ok(rates) => {
+                println!("✅ 已加载 {} 条服务费率", rates.rates().len());
+                *self.service_rates.write().await = rates;
+            }
+            Err(e) => println!("⚠️ 加载服务费率失败，继续使用当前费率: {}", e),
+        }
+    }
+
+    /// 从数据库重新加载启用中的促销规则
+    pub async fn reload_promotions(&self) {
+        let Some(pool) = self.db_pool.read().await.clone() else {
+            return;
+        };
+        match Promotion::find_all(&pool).await {
+            Ok(promotions) => {
+                let active: Vec<_> = promotions.into_iter().filter(|p| p.active).collect();
+                println!("✅ 已加载 {} 条促销规则", active.len());
+                *self.promotions.write().await = active;
+            }
+            Err(e) => println!("⚠️ 加载促销规则失败，继续使用当前规则: {}", e),
+        }
+    }
+
+    /// 确定本次充电的优惠条件：用户是否首次充电，请求附带的优惠券是否可用（只查询，写入详单时才核销）
+    async fn promotion_context(&self, request: &ChargingRequest) -> PromotionContext {
+        let mut context = PromotionContext {
+            mode: request.mode.parse().unwrap_or(ChargingMode::Slow),
+            user_segment: UserSegment::Returning,
+            coupon_promotion_id: None,
+        };
+        let Some(pool) = self.db_pool.read().await.clone() else {
+            return context;
+        };
+
+        match ChargingRecord::count_by_user_id(request.user_id, &pool).await {
+            Ok(0) => context.user_segment = UserSegment::NewUser,
+            Ok(_) => {}
+            Err(e) => println!("⚠️ 查询用户充电记录失败: {}", e),
+        }
+        if let Some(code) = &request.coupon_code {
+            match Coupon::find(code, &pool).await {
+                Ok(Some(coupon)) if coupon.redeemed_by.is_none() => {
+                    context.coupon_promotion_id = Some(coupon.promotion_id);
+                }
+                Ok(_) => println!("⚠️ 优惠券 {} 不存在或已被使用", code),
+                Err(e) => println!("⚠️ 查询优惠券 {} 失败: {}", code, e),
+            }
+        }
+        context
+    }
+
     /// 初始化充电桩
     pub async fn initialize_piles(&self) {
         let mut pile_infos = self.pile_infos.write().await;
@@ -281,100 +474,400 @@ impl QueueManager {
     /// 系统tick - 检查充电完成并启动下一辆车
     pub async fn tick(&self) {
         let current_time = self.time_system.current_time();
+        // 持锁只更新内存状态，结算详单和保存电表采样的数据库读写在释放锁后进行
+        let mut finished = Vec::new();
+        let mut samples = Vec::new();
+        {
+            let mut pile_infos = self.pile_infos.write().await;
+            for (number, pile_info) in pile_infos.iter_mut() {
+                // 检查充电完成（接入OCPP的真实充电桩由桩端上报 StopTransaction 结束充电）
+                let completion = if pile_info.ocpp_connected {
+                    None
+                } else {
+                    pile_info.check_charging_completion(&self.time_system).await
+                };
+                if let Some(session) = completion {
+                    println!("🎯 检测到充电完成，开始生成详单...");
+                    if let Some(session) = self.end_session(pile_info, session).await {
+                        // 结算后再为下一辆车开始充电
+                        finished.push((number.clone(), pile_info.pile.clone(), session));
+                        continue;
+                    }
+                } else if !pile_info.ocpp_connected {
+                    // 模拟充电桩的电表采样
+                    if let Some(sample) = pile_info.sample_meter(&self.time_system).await {
+                        samples.push(sample);
+                    }
+                }
+
+                // 充电桩空出后立即为下一辆车开始充电
+                if let Some(next) = pile_info.start_next_charging(current_time).await {
+                    self.notify_charging_started(pile_info, &next).await;
+                }
+            }
+
+            self.update_gauges(&pile_infos).await;
+        }
+
+        for sample in &samples {
+            self.save_meter_sample(sample).await;
+        }
+        for (number, pile, session) in finished {
+            let end_time = session.end_time;
+            self.settle_session(&pile, &session, end_time).await;
+            self.start_next_on_pile(&number).await;
+        }
+    }
+
+    /// 更新等候区、充电桩队列和充电桩状态的监控指标
+    async fn update_gauges(&self, pile_infos: &HashMap<String, PileInfo>) {
+        let waiting_queue = self.waiting_queue.read().await;
+        for mode in [ChargingMode::Fast, ChargingMode::Slow] {
+            let mode = mode.to_string();
+            let length = waiting_queue.iter().filter(|r| r.mode == mode).count();
+            metrics::WAITING_AREA_LENGTH.set(&mode, length as f64);
+        }
+        drop(waiting_queue);
+
+        let mut by_status: HashMap<String, f64> = [
+            ModelsPileStatus::Available,
+            ModelsPileStatus::Charging,
+            ModelsPileStatus::Fault,
+            ModelsPileStatus::Shutdown,
+        ]
+        .iter()
+        .map(|status| (status.to_string(), 0.0))
+        .collect();
+        let mut queue_lengths = Vec::new();
+        for (number, info) in pile_infos {
+            *by_status.entry(info.pile.read().await.status.to_string()).or_insert(0.0) += 1.0;
+            queue_lengths.push((number.clone(), info.queue.len() as f64));
+        }
+        metrics::PILES_BY_STATUS.replace(by_status);
+        metrics::PILE_QUEUE_LENGTH.replace(queue_lengths);
+    }
+
+    /// 设置是否启用超时占位模式
+    pub async fn set_overstay_enabled(&self, enabled: bool) {
+        *self.overstay_enabled.write().await = enabled;
+        println!("✅ 超时占位模式已{}", if enabled { "启用" } else { "关闭" });
+    }
+
+    /// 充电结束时只更新内存状态：启用超时占位模式时车辆留在桩上等待拔枪并返回 None，否则返回待结算的会话
+    pub async fn end_session(&self, pile_info: &mut PileInfo, session: FinishedSession) -> Option<FinishedSession> {
+        if !*self.overstay_enabled.read().await {
+            return Some(session);
+        }
+        println!(
+            "🅿️ 车辆 {} 在充电桩 {} 充电结束，等待拔枪后结算",
+            session.request.user_id,
+            pile_info.pile.read().await.number
+        );
+        pile_info.plugged_in = Some(session);
+        None
+    }
+
+    /// 车辆拔枪：结算含超时占位费的详单，并为下一辆车开始充电
+    pub async fn unplug(&self, pile_number: &str, unplug_time: DateTime<Utc>) -> Result<ChargingRecord, String> {
+        // 持锁只取出等待拔枪的会话，结算的数据库读写在释放锁后进行
+        let (pile, session) = {
+            let mut pile_infos = self.pile_infos.write().await;
+            let pile_info = pile_infos
+                .get_mut(pile_number)
+                .ok_or_else(|| format!("充电桩 {} 不存在", pile_number))?;
+            let session = pile_info
+                .plugged_in
+                .take()
+                .ok_or_else(|| format!("充电桩 {} 没有等待拔枪的车辆", pile_number))?;
+            (pile_info.pile.clone(), session)
+        };
+
+        println!("🔓 车辆 {} 已从充电桩 {} 拔枪", session.request.user_id, pile_number);
+        let record = self.settle_session(&pile, &session, unplug_time).await;
+        self.start_next_on_pile(pile_number).await;
+        Ok(record)
+    }
+
+    /// 充电桩空闲时为队列中的下一辆车开始充电
+    pub async fn start_next_on_pile(&self, pile_number: &str) {
         let mut pile_infos = self.pile_infos.write().await;
+        if let Some(pile_info) = pile_infos.get_mut(pile_number) {
+            let now = self.time_system.current_time();
+            if let Some(next) = pile_info.start_next_charging(now).await {
+                self.notify_charging_started(pile_info, &next).await;
+            }
+        }
+    }
 
-        for pile_info in pile_infos.values_mut() {
-            // 检查充电完成
-            if let Some((completed, start_time)) =
-                pile_info.check_charging_completion(&self.time_system).await
-            {
-                println!("🎯 检测到充电完成，开始生成详单...");
-                // 生成充电详单
-                let end_time = current_time;
-                let charging_time = self.time_system.get_elapsed_hours(start_time);
-
-                // 计算费用
-                let pile_number = pile_info.pile.read().await.number.clone();
-                let billing_record = FeeCalculator::calculate_fee(
-                    completed.user_id,
-                    pile_number.clone(),
-                    completed.amount,
-                    start_time,
-                    end_time,
-                );
+    /// 按当前电价表预估一次充电的费用
+    pub async fn quote_fee(&self, mode: ChargingMode, amount: f64) -> Money {
+        let power = match mode {
+            ChargingMode::Fast => FAST_CHARGING_POWER,
+            ChargingMode::Slow => SLOW_CHARGING_POWER,
+        };
+        let tariff_schedule = self.tariff_schedule.read().await;
+        let service_rate = self.service_rates.read().await.rate_for_mode(mode);
+        FeeCalculator::estimate_fee(amount, power, self.time_system.current_time(), &tariff_schedule, service_rate)
+    }
 
-                // 创建充电详单
-                let charging_record = ChargingRecord::new(
-                    completed.user_id,
-                    pile_number.clone(),
-                    completed.mode.parse().unwrap_or(ChargingMode::Slow),
-                    completed.amount,
-                    charging_time,
-                    billing_record.electricity_fee,
-                    billing_record.service_fee,
-                    start_time,
-                    end_time,
-                );
+    /// 用户尚未结算的请求（等候区、充电桩队列、充电中、待拔枪）按当前电价表报价之和
+    pub async fn outstanding_quote(&self, user_id: Uuid) -> Money {
+        let mut requests: Vec<Arc<ChargingRequest>> = self
+            .waiting_queue
+            .read()
+            .await
+            .iter()
+            .filter(|r| r.user_id == user_id)
+            .cloned()
+            .collect();
+        for pile_info in self.pile_infos.read().await.values() {
+            let current = pile_info.current_charging.iter();
+            let plugged_in = pile_info.plugged_in.iter().map(|session| &session.request);
+            requests.extend(current.chain(plugged_in).chain(pile_info.queue.iter()).filter(|r| r.user_id == user_id).cloned());
+        }
 
-                // 保存充电详单到数据库
-                println!(
-                    "🔍 准备保存充电详单: 用户 {}, 充电桩 {}",
-                    completed.user_id, pile_number
-                );
-                if let Some(pool) = self.db_pool.read().await.as_ref() {
-                    println!("✅ 数据库连接池可用，开始保存充电详单");
-                    if let Err(e) = charging_record.insert(pool).await {
-                        println!("⚠️ 保存充电详单到数据库失败: {}", e);
-                    }
-                } else {
-                    println!("⚠️ 数据库连接池未设置，无法保存充电详单");
-                }
+        let mut total = zero_money();
+        for request in requests {
+            if let Ok(mode) = request.mode.parse::<ChargingMode>() {
+                total += self.quote_fee(mode, request.amount).await;
+            }
+        }
+        total
+    }
 
-                // 更新充电桩统计信息
-                let mut pile = pile_info.pile.write().await;
-                pile.total_charge_count += 1;
-                pile.total_charge_time += charging_time;
-                pile.total_charge_amount += completed.amount;
-                pile.total_charging_fee += billing_record.electricity_fee;
-                pile.total_service_fee += billing_record.service_fee;
-
-                // 保存统计信息回数据库
-                if let Some(pool_arc) = self.db_pool.read().await.as_ref() {
-                    let pool: &sqlx::MySqlPool = &**pool_arc; // 解引用 Arc -> Pool -> &Pool
-
-                    let query = r#"
-                        UPDATE charging_piles
-                        SET 
-                            status = 'Available',
-                            total_charge_count = ?,
-                            total_charge_time = ?,
-                            total_charge_amount = ?,
-                            total_charging_fee = ?,
-                            total_service_fee = ?
-                        WHERE number = ?
-                    "#;
-
-                    if let Err(e) = sqlx::query(query)
-                        .bind(pile.total_charge_count)
-                        .bind(pile.total_charge_time)
-                        .bind(pile.total_charge_amount)
-                        .bind(pile.total_charging_fee)
-                        .bind(pile.total_service_fee)
-                        .bind(&pile.number)
-                        .execute(pool)
-                        .await
-                    {
-                        println!("⚠️ 无法更新充电桩统计信息: {}", e);
-                    } else {
-                        println!("📦 成功更新充电桩 {} 的统计信息", &pile.number);
-                    }
-                } else {
-                    println!("⚠️ 数据库连接池未设置，无法更新充电桩信息");
+    /// 结算一次充电：按电量曲线计算费用、计入超时占位费、保存详单并更新充电桩统计信息
+    pub async fn settle_session(
+        &self,
+        pile: &RwLock<ChargingPile>,
+        session: &FinishedSession,
+        unplug_time: DateTime<Utc>,
+    ) -> ChargingRecord {
+        let completed = &session.request;
+        let mode = completed.mode.parse().unwrap_or(ChargingMode::Slow);
+        let (charge_amount, start_time, end_time) = (session.charge_amount, session.start_time, session.end_time);
+        let charging_time = (end_time - start_time).num_seconds() as f64 / 3600.0;
+
+        // 计算费用
+        let pile_number = pile.read().await.number.clone();
+        let curve: Vec<_> = session.meter_samples.iter().map(|s| s.curve_point()).collect();
+        let tariff_schedule = self.tariff_schedule.read().await;
+        let billing_record = FeeCalculator::calculate_fee_from_curve(
+            completed.user_id,
+            pile_number.clone(),
+            charge_amount,
+            start_time,
+            end_time,
+            &curve,
+            &tariff_schedule,
+        )
+        .with_idle_fee(FeeCalculator::calculate_idle_fee(end_time, unplug_time));
+        drop(tariff_schedule);
+        let billing_record =
+            FeeCalculator::apply_service_rates(billing_record, &*self.service_rates.read().await, mode);
+        if billing_record.idle_fee > zero_money() {
+            println!("⏱️ 车辆 {} 超时占位，收取占位费 {}元", completed.user_id, billing_record.idle_fee);
+        }
+
+        // 计算优惠并创建充电详单
+        let mut context = self.promotion_context(completed).await;
+        let new_record = NewChargingRecord {
+            user_id: completed.user_id,
+            pile_id: pile_number.clone(),
+            mode,
+            charging_amount: charge_amount,
+            charging_time,
+            charging_fee: billing_record.electricity_fee.clone(),
+            service_fee: billing_record.service_fee.clone(),
+            start_time,
+            end_time,
+        };
+        let mut charging_record = self.build_record(completed, &new_record, &billing_record, &context).await;
+
+        // 保存充电详单到数据库
+        println!(
+            "🔍 准备保存充电详单: 用户 {}, 充电桩 {}",
+            completed.user_id, pile_number
+        );
+        let payment_service = self.payment_service.read().await.clone();
+        let mut record_saved = false;
+        if let Some(pool) = self.db_pool.read().await.as_ref() {
+            println!("✅ 数据库连接池可用，开始保存充电详单");
+            let mut result = Self::insert_record(&charging_record, pool, payment_service.is_none()).await;
+            // 优惠券在结算期间被其他详单核销，去掉优惠券重新计算
+            if let Ok(false) = result {
+                context.coupon_promotion_id = None;
+                charging_record = self.build_record(completed, &new_record, &billing_record, &context).await;
+                result = Self::insert_record(&charging_record, pool, payment_service.is_none()).await;
+            }
+            match result {
+                Ok(saved) => record_saved = saved,
+                Err(e) => {
+                    println!("⚠️ 保存充电详单到数据库失败: {}", e);
+                    metrics::db_error("scheduler");
                 }
+            }
+            if let Err(e) = RequestTimeline::insert(completed, &pile_number, pool).await {
+                println!("⚠️ 保存请求时间线失败: {}", e);
+                metrics::db_error("scheduler");
+            }
+        } else {
+            println!("⚠️ 数据库连接池未设置，无法保存充电详单");
+        }
+
+        if let Some(payment_service) = payment_service {
+            self.capture_payment(&payment_service, &charging_record, record_saved).await;
+        }
+
+        metrics::SESSIONS_COMPLETED.inc(&completed.mode);
+        metrics::ENERGY_DELIVERED.inc_by(&completed.mode, charge_amount);
+        metrics::REVENUE.inc_by(&completed.mode, charging_record.total_fee.to_f64().unwrap_or(0.0));
+
+        // 更新充电桩统计信息
+        let mut pile = pile.write().await;
+        pile.total_charge_count += 1;
+        pile.total_charge_time += charging_time;
+        pile.total_charge_amount += charge_amount;
+        pile.total_charging_fee += &billing_record.electricity_fee;
+        pile.total_service_fee += &billing_record.service_fee;
+
+        // 保存统计信息回数据库
+        if let Some(pool_arc) = self.db_pool.read().await.as_ref() {
+            let pool: &sqlx::MySqlPool = &**pool_arc; // 解引用 Arc -> Pool -> &Pool
+
+            let query = r#"
+                UPDATE charging_piles
+                SET 
+                    status = 'Available',
+                    total_charge_count = ?,
+                    total_charge_time = ?,
+                    total_charge_amount = ?,
+                    total_charging_fee = ?,
+                    total_service_fee = ?
+                WHERE number = ?
+            "#;
+
+            if let Err(e) = sqlx::query(query)
+                .bind(pile.total_charge_count)
+                .bind(pile.total_charge_time)
+                .bind(pile.total_charge_amount)
+                .bind(&pile.total_charging_fee)
+                .bind(&pile.total_service_fee)
+                .bind(&pile.number)
+                .execute(pool)
+                .await
+            {
+                println!("⚠️ 无法更新充电桩统计信息: {}", e);
+                metrics::db_error("scheduler");
             } else {
-                // 只有在没有充电完成的情况下，才尝试启动下一辆车
-                pile_info.start_next_charging(current_time).await;
+                println!("📦 成功更新充电桩 {} 的统计信息", &pile.number);
             }
+        } else {
+            println!("⚠️ 数据库连接池未设置，无法更新充电桩信息");
+        }
+
+        charging_record
+    }
+
+    /// 按优惠条件计算减免并生成充电详单（详单ID与充电请求ID一致，用于关联电表采样）；
+    /// 优惠券对应的促销实际产生减免时才在详单上附带券码，写入详单时核销
+    async fn build_record(
+        &self,
+        request: &ChargingRequest,
+        new_record: &NewChargingRecord,
+        billing_record: &BillingRecord,
+        context: &PromotionContext,
+    ) -> ChargingRecord {
+        let billing_record =
+            FeeCalculator::apply_promotions(billing_record.clone(), &self.promotions.read().await, context);
+        if billing_record.discount_fee > zero_money() {
+            println!("🏷️ 车辆 {} 享受优惠 {}元", request.user_id, billing_record.discount_fee);
+        }
+        let coupon_applied = context
+            .coupon_promotion_id
+            .is_some_and(|id| billing_record.discounts.iter().any(|d| d.promotion_id == id));
+
+        let mut record = ChargingRecord::new(new_record.clone())
+            .with_service_rate(billing_record.service_rate.clone())
+            .with_idle_fee(billing_record.idle_fee.clone())
+            .with_discount(billing_record.discount_fee.clone())
+            .with_line_items(billing_record.line_items())
+            .with_coupon(request.coupon_code.clone().filter(|_| coupon_applied));
+        record.id = request.id;
+        record
+    }
+
+    /// 保存详单，未接入支付渠道时在同一事务中从钱包扣费
+    async fn insert_record(record: &ChargingRecord, pool: &sqlx::MySqlPool, deduct_wallet: bool) -> Result<bool, sqlx::Error> {
+        if deduct_wallet {
+            record.insert(pool).await
+        } else {
+            record.insert_without_wallet(pool).await
+        }
+    }
+
+    /// 详单保存成功后才从预授权中按实际费用扣款，详单未保存时账单置为 Failed；
+    /// 渠道扣款失败时账单已置为 Failed，改从钱包扣费
+    async fn capture_payment(&self, payment_service: &PaymentService, record: &ChargingRecord, record_saved: bool) {
+        if !record_saved {
+            println!("⚠️ 充电详单 {} 未保存，不从预授权扣款", record.id);
+            payment_service.fail(record.id, "充电详单保存失败，未扣款").await;
+            metrics::db_error("payment");
+            return;
+        }
+        let Err(e) = payment_service.capture(record.id, &record.total_fee).await else {
+            return;
+        };
+        println!("⚠️ 充电详单 {} 扣款失败，改从钱包扣费: {}", record.id, e);
+        metrics::db_error("payment");
+        if let Some(pool) = self.db_pool.read().await.clone() {
+            if let Err(e) = record.charge_wallet(&pool).await {
+                println!("❌ 充电详单 {} 钱包扣费失败: {}", record.id, e);
+                metrics::db_error("payment");
+            }
+        }
+    }
+
+    /// 记录充电桩进入或退出故障状态，供利用率统计使用
+    pub async fn record_pile_fault(&self, pile_number: &str, faulted: bool, at: DateTime<Utc>) {
+        let Some(pool) = self.db_pool.read().await.clone() else {
+            return;
+        };
+        let result = if faulted {
+            PileFault::open(pile_number, at, &pool).await
+        } else {
+            PileFault::close(pile_number, at, &pool).await
+        };
+        if let Err(e) = result {
+            println!("⚠️ 记录充电桩 {} 故障时段失败: {}", pile_number, e);
+            metrics::db_error("scheduler");
+        }
+    }
+
+    /// 保存电表采样到数据库
+    pub async fn save_meter_sample(&self, sample: &MeterSample) {
+        if let Some(pool) = self.db_pool.read().await.as_ref() {
+            if let Err(e) = sample.insert(pool).await {
+                println!("⚠️ 保存电表采样失败: {}", e);
+                metrics::db_error("scheduler");
+            }
+        }
+    }
+
+    /// 设置OCPP中央系统
+    pub async fn set_central_system(&self, central_system: Arc<CentralSystem>) {
+        let mut slot = self.central_system.write().await;
+        *slot = Some(central_system);
+        println!("✅ 队列管理器OCPP中央系统已设置");
+    }
+
+    /// 车辆开始充电时通知真实充电桩（RemoteStartTransaction）
+    pub async fn notify_charging_started(&self, pile_info: &PileInfo, request: &ChargingRequest) {
+        if !pile_info.ocpp_connected {
+            return;
+        }
+        if let Some(central_system) = self.central_system.read().await.as_ref() {
+            let pile_number = pile_info.pile.read().await.number.clone();
+            central_system.remote_start_transaction(&pile_number, request).await;
         }
     }
 
@@ -420,6 +913,7 @@ impl QueueManager {
                 queue_users: info.queue.iter().map(|r| r.user_id).collect(),
                 queue_count: info.queue.len(),
                 is_idle: info.is_idle(),
+                awaiting_unplug: info.plugged_in.is_some(),
                 charging_progress: info.get_charging_progress(&self.time_system).await,
                 current_request,
                 queue_requests,
@@ -455,7 +949,8 @@ impl QueueManager {
             pile_statuses.push(PileRealTimeStatus {
                 pile_number: pile.number.clone(),
                 pile_mode: pile.mode,
-                is_idle: info.current_charging.is_none() && info.queue.is_empty(),
+                is_idle: info.is_idle(),
+                awaiting_unplug: info.plugged_in.is_some(),
                 current_charging_user: info.current_charging.as_ref().map(|req| req.user_id),
                 current_request: info.current_charging.as_ref().map(|req| (**req).clone()),
                 queue_count: info.queue.len(),
@@ -505,6 +1000,7 @@ pub struct PileStatusInfo {
     pub queue_users: Vec<Uuid>,
     pub queue_count: usize,
     pub is_idle: bool,
+    pub awaiting_unplug: bool, // 充电已结束、等待拔枪
     pub charging_progress: Option<f64>, // 充电进度百分比
     pub current_request: Option<ChargingRequestInfo>, // 当前充电请求的完整信息
     pub queue_requests: Vec<ChargingRequestInfo>, // 队列中的请求信息
@@ -536,6 +1032,7 @@ pub struct PileRealTimeStatus {
     pub pile_number: String,
     pub pile_mode: ChargingMode,
     pub is_idle: bool,
+    pub awaiting_unplug: bool,
     pub current_charging_user: Option<Uuid>,
     pub current_request: Option<ChargingRequest>,
     pub queue_count: usize,